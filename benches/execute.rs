@@ -0,0 +1,45 @@
+//! Times the expansion of simple commands into their arguments and the execution of commands which don't spawn programs,
+//! the path every command of a script takes, run with `cargo bench`
+//!
+//! Only std is used so it works on SafaOS too, each case is run a number of times first so the allocator is warm
+
+use std::{hint::black_box, time::Instant};
+
+use safa::{Shell, expand, vars::Variables};
+
+const ITERATIONS: u32 = 100_000;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let each = started.elapsed() / ITERATIONS;
+    println!("{name:<24} {each:>12?}");
+}
+
+fn main() {
+    let vars = &Variables::isolated([("HOME", "/home/user"), ("NAME", "a b c")]);
+    let argv = |command: &'static str| {
+        move || {
+            _ = black_box(expand::argv(black_box(command), vars));
+        }
+    };
+    bench("argv: words", argv("echo hello world from safa"));
+    bench(
+        "argv: variables",
+        argv("echo $HOME/bin $NAME \"$NAME\" ${HOME}"),
+    );
+    bench("argv: braces", argv("cp file.{txt,bak} dir/{a,b}/"));
+
+    let mut shell = Shell::new();
+    bench("execute: assignments", || {
+        _ = black_box(shell.execute(black_box("a=1; b=$a$a; c=\"$b\" d=${c}x")));
+    });
+    bench("execute: builtin", || {
+        _ = black_box(shell.execute(black_box("unset a b c d")));
+    });
+}
@@ -0,0 +1,46 @@
+//! Records the commit and the time the shell was built for `safa --version`
+
+use std::{
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Returns what `git rev-parse args...` prints, `None` outside of a repository or without git
+fn rev_parse(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .arg("rev-parse")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+}
+
+fn main() {
+    if let Some(commit) = rev_parse(&["--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=SAFA_GIT_COMMIT={commit}");
+    }
+
+    // committing changes the branch HEAD points to rather than HEAD itself, which is packed into `packed-refs` once
+    // git packs its references, missing files would make cargo run this script every time
+    let branch = rev_parse(&["--symbolic-full-name", "HEAD"]).filter(|branch| branch != "HEAD");
+    for file in ["HEAD", "packed-refs"]
+        .into_iter()
+        .map(str::to_string)
+        .chain(branch)
+        .filter_map(|file| rev_parse(&["--git-path", &file]))
+        .filter(|path| Path::new(path).exists())
+    {
+        println!("cargo:rerun-if-changed={file}");
+    }
+
+    // reproducible builds pin the build time with `SOURCE_DATE_EPOCH`
+    let build_time = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        now.map(|now| now.as_secs()).unwrap_or(0).to_string()
+    });
+    println!("cargo:rustc-env=SAFA_BUILD_TIME={build_time}");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
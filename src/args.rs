@@ -0,0 +1,189 @@
+//! Parses the shell's command line arguments
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ArgsError {
+    #[error("`{0}` expected a value")]
+    MissingValue(String),
+    #[error("unexpected argument `{0}`")]
+    UnknownFlag(String),
+    #[error("`--cursor` expected a byte offset, got `{0}`")]
+    InvalidCursor(String),
+}
+
+/// What the shell was asked to run
+#[derive(Debug, Default)]
+pub enum Mode {
+    /// Read commands from the terminal, or run the script read from stdin if it isn't one on hosts that can tell
+    #[default]
+    Interactive,
+    /// `-c command`
+    Command(String),
+    /// `script`
+    Script(String),
+    /// `--serve socket`
+    Serve(String),
+    /// `--complete-line line [--cursor offset]`
+    Complete {
+        line: String,
+        cursor: Option<usize>,
+    },
+    Help,
+    Version,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub mode: Mode,
+    /// Whether to read commands with the line editor even if stdin isn't a terminal
+    pub interactive: bool,
+    /// Whether the shell is a login shell, which shows the message of the day
+    pub login: bool,
+    /// Whether to skip the message of the day even in a login shell
+    pub quiet: bool,
+    /// Whether `--version` prints the build information as JSON
+    pub json: bool,
+    /// Whether to turn colors off as `NO_COLOR` does
+    pub no_color: bool,
+    /// Whether to ignore the inherited environment and the configuration file, see [`safa::Shell::privileged`]
+    pub privileged: bool,
+    /// The positional arguments after `-c command` or after the script
+    pub rest: Vec<String>,
+}
+
+/// Takes the value following `flag`
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, ArgsError> {
+    args.next()
+        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut results = Args::default();
+        let mut complete_line = None;
+        let mut cursor = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => break,
+                "--interactive" => results.interactive = true,
+                "--login" => results.login = true,
+                "--quiet" | "--no-banner" => results.quiet = true,
+                "--privileged" => results.privileged = true,
+                "--no-color" => results.no_color = true,
+                "--help" => results.mode = Mode::Help,
+                "--version" => results.mode = Mode::Version,
+                "--json" => results.json = true,
+                "--serve" => results.mode = Mode::Serve(value(&mut args, &arg)?),
+                // hidden flags letting other programs reuse the shell's completion, prints a candidate per line
+                "--complete-line" => complete_line = Some(value(&mut args, &arg)?),
+                "--cursor" => {
+                    let offset = value(&mut args, &arg)?;
+                    cursor = Some(
+                        offset
+                            .parse()
+                            .map_err(|_| ArgsError::InvalidCursor(offset))?,
+                    );
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(ArgsError::UnknownFlag(flag.to_string()));
+                }
+                // combined short flags such as `-ic`
+                flags if flags.starts_with('-') && flags.len() > 1 => {
+                    for flag in flags[1..].chars() {
+                        match flag {
+                            'i' => results.interactive = true,
+                            'l' => results.login = true,
+                            'q' => results.quiet = true,
+                            'p' => results.privileged = true,
+                            'c' => results.mode = Mode::Command(value(&mut args, "-c")?),
+                            _ => return Err(ArgsError::UnknownFlag(format!("-{flag}"))),
+                        }
+                    }
+                }
+                _ => {
+                    results.rest.push(arg);
+                    break;
+                }
+            }
+
+            if matches!(results.mode, Mode::Command(_)) {
+                break;
+            }
+        }
+
+        results.rest.extend(args);
+        if let Some(line) = complete_line {
+            results.mode = Mode::Complete { line, cursor };
+        } else if matches!(results.mode, Mode::Interactive) && !results.rest.is_empty() {
+            results.mode = Mode::Script(results.rest.remove(0));
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, ArgsError, Mode};
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn combined_flags() {
+        let args = parse(&["-ic", "echo hi", "name", "a"]).unwrap();
+        assert!(args.interactive);
+        assert!(matches!(&args.mode, Mode::Command(command) if command == "echo hi"));
+        assert_eq!(args.rest, ["name", "a"]);
+
+        let args = parse(&["-lq"]).unwrap();
+        assert!(args.login && args.quiet);
+        assert!(matches!(args.mode, Mode::Interactive));
+    }
+
+    #[test]
+    fn script_arguments() {
+        // flags after the script are its own
+        let args = parse(&["-p", "script.sh", "-i", "--", "x"]).unwrap();
+        assert!(args.privileged && !args.interactive);
+        assert!(matches!(&args.mode, Mode::Script(script) if script == "script.sh"));
+        assert_eq!(args.rest, ["-i", "--", "x"]);
+    }
+
+    #[test]
+    fn separator() {
+        let args = parse(&["--", "-c", "x"]).unwrap();
+        assert!(matches!(&args.mode, Mode::Script(script) if script == "-c"));
+        assert_eq!(args.rest, ["x"]);
+
+        let args = parse(&["--login", "--"]).unwrap();
+        assert!(args.login);
+        assert!(matches!(args.mode, Mode::Interactive));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(parse(&["-c"]), Err(ArgsError::MissingValue(flag)) if flag == "-c"));
+        assert!(matches!(parse(&["-ix"]), Err(ArgsError::UnknownFlag(flag)) if flag == "-x"));
+        assert!(
+            matches!(parse(&["--nope"]), Err(ArgsError::UnknownFlag(flag)) if flag == "--nope")
+        );
+        assert!(matches!(
+            parse(&["--complete-line", "ls", "--cursor", "x"]),
+            Err(ArgsError::InvalidCursor(offset)) if offset == "x"
+        ));
+    }
+
+    #[test]
+    fn modes() {
+        assert!(matches!(parse(&["--version"]).unwrap().mode, Mode::Version));
+        assert!(matches!(parse(&["--help"]).unwrap().mode, Mode::Help));
+        assert!(matches!(
+            parse(&["--complete-line", "ls s", "--cursor", "2"]).unwrap().mode,
+            Mode::Complete { line, cursor: Some(2) } if line == "ls s"
+        ));
+    }
+}
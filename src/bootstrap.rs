@@ -0,0 +1,37 @@
+//! Sets the variables programs expect when the shell starts with an empty environment, as it does when SafaOS's init
+//! starts it, the defaults can be changed by setting `SAFA_DEFAULT_PATH`, `SAFA_DEFAULT_HOME` and `SAFA_DEFAULT_TERM`
+//! when building the shell, and the whole step is left out by building without the `env-bootstrap` feature
+
+use crate::identity;
+
+/// The home directory used when `HOME` isn't set
+const DEFAULT_HOME: &str = match option_env!("SAFA_DEFAULT_HOME") {
+    Some(home) => home,
+    None if cfg!(target_os = "safaos") => "ram:/",
+    None => "/",
+};
+
+/// The `TERM` used when it isn't set, SafaOS's terminal doesn't set it and programs elsewhere
+/// can only assume a `dumb` terminal
+const DEFAULT_TERM: &str = match option_env!("SAFA_DEFAULT_TERM") {
+    Some(term) => term,
+    None if cfg!(target_os = "safaos") => "safaos",
+    None => "dumb",
+};
+
+/// Exports `PATH`, `HOME`, `TERM` and `USER` if they are missing or empty,
+/// `SHELL` doesn't need a default since it always names the shell
+pub fn environment() {
+    let defaults = [
+        ("PATH", crate::DEFAULT_PATH.to_string()),
+        ("HOME", DEFAULT_HOME.to_string()),
+        ("TERM", DEFAULT_TERM.to_string()),
+        ("USER", identity::user()),
+    ];
+
+    for (name, value) in defaults {
+        if std::env::var_os(name).is_none_or(|value| value.is_empty()) {
+            unsafe { std::env::set_var(name, value) };
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Brace expansion, the first expansion of a word: `a{b,c}d` becomes `abd acd` and `file{1..3}` becomes `file1 file2 file3`
+//!
+//! Braces are expanded in the raw text of a word, so the words it results in are lexed again and expanded as usual.
+//! Braces inside quotes, escaped braces and the braces of `${name}` aren't expanded,
+//! neither are braces without a `,` or a `..` range such as `{}` or `{a}`.
+//! Words that would expand to more than [`MAX_WORDS`] words are left as they are
+
+/// The most words a word expands to, so `{1..99999999999}` doesn't exhaust the memory
+const MAX_WORDS: usize = 1_000_000;
+
+/// Returns the offsets and characters of `raw` that aren't quoted, escaped or part of a `$` expansion or a `<(...)` substitution
+fn unquoted(raw: &str) -> Vec<(usize, char)> {
+    let mut unquoted = Vec::new();
+    let mut chars = raw.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => _ = chars.next(),
+            '\'' => _ = chars.find(|&(_, c)| c == '\''),
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => _ = chars.next(),
+                        _ => {}
+                    }
+                }
+            }
+            '$' | '<' => {
+                let (open, close) = match (c, chars.peek()) {
+                    ('$', Some((_, '{'))) => ('{', '}'),
+                    (_, Some((_, '('))) => ('(', ')'),
+                    ('$', _) => continue,
+                    _ => {
+                        unquoted.push((i, c));
+                        continue;
+                    }
+                };
+                let mut depth = 0usize;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        c if c == open => depth += 1,
+                        c if c == close => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            c => unquoted.push((i, c)),
+        }
+    }
+    unquoted
+}
+
+/// Returns the numbers or characters of the range `{start..end}` or `{start..end..step}`, `None` if `range` isn't one
+fn range(range: &str) -> Option<Vec<String>> {
+    let mut bounds = range.split("..");
+    let (start, end) = (bounds.next()?, bounds.next()?);
+    let step = match bounds.next() {
+        Some(step) => step.parse::<i64>().ok()?.unsigned_abs().max(1),
+        None => 1,
+    };
+    if bounds.next().is_some() {
+        return None;
+    }
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        // `{01..10}` pads the numbers with zeros to the width of the widest bound
+        let is_padded =
+            |bound: &str| bound.trim_start_matches('-').starts_with('0') && bound.len() > 1;
+        let width = if is_padded(start) || is_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        let numbers = stepped(first, last, step)?;
+        return Some(numbers.map(|n| format!("{n:0width$}")).collect());
+    }
+
+    let (mut start_chars, mut end_chars) = (start.chars(), end.chars());
+    match (
+        start_chars.next(),
+        start_chars.next(),
+        end_chars.next(),
+        end_chars.next(),
+    ) {
+        (Some(first), None, Some(last), None)
+            if first.is_ascii_alphabetic() && last.is_ascii_alphabetic() =>
+        {
+            let chars = stepped(first as i64, last as i64, step)?;
+            Some(chars.map(|c| (c as u8 as char).to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Counts from `first` to `last` included by `step`, downwards if `last` is smaller,
+/// `None` if that makes more than [`MAX_WORDS`] numbers
+fn stepped(first: i64, last: i64, step: u64) -> Option<impl Iterator<Item = i64>> {
+    let count = (first.abs_diff(last) / step).checked_add(1)?;
+    if count > MAX_WORDS as u64 {
+        return None;
+    }
+    // `i * step` is at most the distance between the bounds, so only adding it to `first` can overflow
+    Some((0..count).map_while(move |i| {
+        if last < first {
+            first.checked_sub_unsigned(i * step)
+        } else {
+            first.checked_add_unsigned(i * step)
+        }
+    }))
+}
+
+/// The offsets of the braces of a brace expression and the words it expands to
+type Found = (usize, usize, Vec<String>);
+
+/// Finds the first brace expression of `raw`
+fn find(raw: &str) -> Option<Found> {
+    let unquoted = unquoted(raw);
+
+    for (start, &(open, c)) in unquoted.iter().enumerate() {
+        if c != '{' {
+            continue;
+        }
+
+        let mut depth = 0usize;
+        let mut commas = Vec::new();
+        let mut close = None;
+        for &(i, c) in &unquoted[start + 1..] {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    close = Some(i);
+                    break;
+                }
+                '}' => depth -= 1,
+                ',' if depth == 0 => commas.push(i),
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            continue;
+        };
+
+        if commas.is_empty() {
+            match range(&raw[open + 1..close]) {
+                Some(words) => return Some((open, close, words)),
+                None => continue,
+            }
+        }
+
+        let bounds = [open]
+            .into_iter()
+            .chain(commas)
+            .chain([close])
+            .collect::<Vec<_>>();
+        let words = bounds
+            .windows(2)
+            .map(|bounds| raw[bounds[0] + 1..bounds[1]].to_string())
+            .collect();
+        return Some((open, close, words));
+    }
+    None
+}
+
+/// Returns the raw words the raw word `raw` expands to, `None` if it has no braces to expand
+/// or they expand to more than [`MAX_WORDS`] words
+pub fn expand(raw: &str) -> Option<Vec<String>> {
+    let found = find(raw)?;
+    let mut words = Vec::new();
+    push_expanded(raw, found, &mut words).then_some(words)
+}
+
+/// Pushes the words `raw` expands to onto `words` given its first brace expression,
+/// returns `false` as soon as there are more than [`MAX_WORDS`] of them
+fn push_expanded(raw: &str, (open, close, alternatives): Found, words: &mut Vec<String>) -> bool {
+    let (preamble, postscript) = (&raw[..open], &raw[close + 1..]);
+
+    // the alternatives and what follows the braces may have braces of their own
+    alternatives.into_iter().all(|alternative| {
+        let word = format!("{preamble}{alternative}{postscript}");
+        match find(&word) {
+            Some(found) => push_expanded(&word, found, words),
+            None => {
+                words.push(word);
+                words.len() <= MAX_WORDS
+            }
+        }
+    })
+}
@@ -1,35 +1,1515 @@
 //! this module contains all the builtin shell commands
 
-use std::io::Write;
+use std::{
+    io::{self, Read, Write},
+    process::Stdio,
+    time::{Duration, Instant},
+};
 
-use crate::{Shell, ShellError};
+use crate::{
+    Shell, ShellError,
+    completion::Rule,
+    drives, errors,
+    exec::ChildEnv,
+    expand,
+    history::History,
+    i18n::{self, Message},
+    identity,
+    json::Value,
+    limits::{self, Limit, Resource},
+    parser, printf, privilege,
+    prompt::{Glyphs, Prompt, SEGMENTS, Segment, Theme},
+    readline,
+    service::{self, Action},
+    stdio::{Input, Output},
+    system, terminal,
+    time::format_utc,
+    umask,
+};
 use phf::phf_map;
 
-type BuiltInCommand = fn(&mut Shell, &[&str]) -> Result<(), ShellError>;
-type BuiltInCommandList = phf::Map<&'static str, BuiltInCommand>;
+pub(crate) type BuiltInCommand = fn(&mut Shell, &[&str]) -> Result<(), ShellError>;
+pub(crate) type BuiltInCommandList = phf::Map<&'static str, BuiltInCommand>;
+
+/// Returns the builtin used when no program called `name` is found in `PATH`
+pub fn fallback(name: &str) -> Option<&'static BuiltInCommand> {
+    #[cfg(feature = "fs-builtins")]
+    if let Some(f) = crate::fs_builtins::FS_BUILTINS.get(name) {
+        return Some(f);
+    }
+    #[cfg(feature = "net-utils")]
+    if let Some(f) = crate::net_builtins::NET_BUILTINS.get(name) {
+        return Some(f);
+    }
+
+    _ = name;
+    None
+}
+
+/// Returns the names of all the builtins, including the fallback ones
+pub fn names() -> impl Iterator<Item = &'static str> {
+    let names = BUILTIN_COMMANDS.keys();
+    #[cfg(feature = "fs-builtins")]
+    let names = names.chain(crate::fs_builtins::FS_BUILTINS.keys());
+    #[cfg(feature = "net-utils")]
+    let names = names.chain(crate::net_builtins::NET_BUILTINS.keys());
+    names.copied()
+}
 
 pub static BUILTIN_COMMANDS: BuiltInCommandList = phf_map! {
-    "exit" => |_, _| std::process::exit(0),
-    "clear" => |_, _| {
-        print!("\x1b[2J\x1b[H");
-        std::io::stdout().flush()?;
+    "exit" => |shell, args| {
+        let code = match args {
+            [] => shell.vars.get("?").and_then(|status| status.parse().ok()).unwrap_or(0),
+            [code] => match code.parse::<i32>() {
+                // statuses are a byte, `exit -1` is `exit 255`
+                Ok(code) => (code & 0xff) as u32,
+                Err(_) => {
+                    writeln!(shell.streams.stderr, "{}", i18n::format(Message::NumericArgumentRequired, &[&"exit", &code]))?;
+                    2
+                }
+            },
+            _ => {
+                writeln!(shell.streams.stderr, "{}", usage("exit [status]"))?;
+                return Err(ShellError::BuiltinError);
+            }
+        };
+        Err(ShellError::Exit(code))
+    },
+    "clear" => clear,
+    "cd" => cd,
+    "help" => |shell, _| {
+        let stdout = &mut shell.streams.stdout;
+        writeln!(stdout, "{}", i18n::text(Message::HelpBuiltins))?;
+        for name in names() {
+            writeln!(stdout, "- {name}")?;
+        }
+        writeln!(stdout, "{}", i18n::text(Message::HelpTerminal))?;
         Ok(())
     },
-    "cd" => |_, args| if args.len() < 1 {
-        println!("cd: Not enough arguments");
-        Err(ShellError::BuiltinError)
-    } else {
-        std::env::set_current_dir(args[0])?;
+    "prompt" => prompt,
+    "ulimit" => ulimit,
+    "umask" => umask,
+    "exec" => |shell, args| match args.split_first() {
+        Some((program, args)) => Err(shell.exec_program(program, args)),
+        None => {
+            shell.keep_redirections = true;
+            Ok(())
+        }
+    },
+    "history" => history,
+    "elevate" => elevate,
+    "stat" => stat,
+    "explain" => explain,
+    "mounts" => |shell, args| list(shell, "mounts", args, system::mounts()),
+    "lsdev" => |shell, args| list(shell, "lsdev", args, system::devices()),
+    "svc" => svc,
+    "env" => env,
+    "capture" => capture,
+    "wait" => wait,
+    "timeout" => timeout,
+    "watch" => watch,
+    "version" => |shell, args| match args {
+        [] | ["--json"] => {
+            let report = crate::version::report(!args.is_empty());
+            writeln!(shell.streams.stdout, "{report}")?;
+            Ok(())
+        }
+        _ => {
+            writeln!(shell.streams.stderr, "{}", usage("version [--json]"))?;
+            Err(ShellError::BuiltinError)
+        }
+    },
+    "jobs" => jobs,
+    "disown" => disown,
+    "detach" => detach,
+    "readonly" => readonly,
+    "unset" => unset,
+    "local" => |shell, args| {
+        for arg in args {
+            let (name, value) = arg.split_once('=').unwrap_or((arg, ""));
+            shell.check_writable(name)?;
+            if !shell.vars.declare_local(name, value.to_string()) {
+                writeln!(shell.streams.stderr, "{}", i18n::format(Message::OnlyInFunction, &[&"local"]))?;
+                return Err(ShellError::BuiltinError);
+            }
+        }
+        Ok(())
+    },
+    "export" => |shell, args| {
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) => {
+                    shell.check_writable(name)?;
+                    shell.vars.export(name, Some(value.to_string()))
+                }
+                None => shell.vars.export(arg, None),
+            }
+        }
         Ok(())
     },
-    "help" => |_, _| {
-        println!("Builtin commands:");
-        for (name, _) in &BUILTIN_COMMANDS {
-            println!("- {name}");
+    "hash" => hash,
+    "complete" => complete,
+    "set" => set,
+    "source" => source,
+    "." => source,
+    "return" => |shell, args| {
+        let code = match args {
+            [] => shell.vars.get("?").and_then(|status| status.parse().ok()).unwrap_or(0),
+            [code] => match code.parse() {
+                Ok(code) => code,
+                Err(_) => {
+                    writeln!(shell.streams.stderr, "{}", i18n::format(Message::NumericArgumentRequired, &[&"return", &code]))?;
+                    return Err(ShellError::BuiltinError);
+                }
+            },
+            _ => {
+                writeln!(shell.streams.stderr, "{}", usage("return [status]"))?;
+                return Err(ShellError::BuiltinError);
+            }
+        };
+        if shell.frames == 0 {
+            writeln!(shell.streams.stderr, "{}", i18n::format(Message::OnlyInFunctionOrSource, &[&"return"]))?;
+            return Err(ShellError::BuiltinError);
+        }
+        Err(ShellError::Return(code))
+    },
+    "ps" => |shell, args| {
+        let json = match json_flag(args) {
+            (json, []) => json,
+            _ => {
+                writeln!(shell.streams.stderr, "{}", usage("ps [--json]"))?;
+                return Err(ShellError::BuiltinError);
+            }
+        };
+        let processes = match system::processes() {
+            Ok(processes) => processes,
+            Err(err) => {
+                writeln!(shell.streams.stderr, "ps: {err}")?;
+                return Err(ShellError::BuiltinError);
+            }
+        };
+
+        let stdout = &mut shell.streams.stdout;
+        if json {
+            let processes = processes.into_iter().map(|process| {
+                Value::from_iter([
+                    ("pid", Value::from(process.pid)),
+                    ("name", process.name.into()),
+                    ("state", process.state.into()),
+                    ("memory_kib", process.memory.into()),
+                ])
+            });
+            writeln!(stdout, "{}", Value::Array(processes.collect()))?;
+            return Ok(());
+        }
+
+        writeln!(stdout, "{:>7}  {:<20} {:<14} {:>10}", "PID", "NAME", "STATE", "MEM (KiB)")?;
+        for process in processes {
+            let memory = process.memory.map_or("-".to_string(), |memory| memory.to_string());
+            writeln!(
+                stdout,
+                "{:>7}  {:<20} {:<14} {memory:>10}",
+                process.pid, process.name, process.state
+            )?;
         }
-        println!("Terminal usage:");
-        println!("- PageUp/PageDown: scroll up/down");
-        println!("- Ctrl+Shift+C: clear screen");
         Ok(())
     },
+    "whoami" => |shell, _| {
+        writeln!(shell.streams.stdout, "{}", identity::user())?;
+        Ok(())
+    },
+    "printf" => |shell, args| {
+        let Some((format, args)) = args.split_first() else {
+            writeln!(shell.streams.stderr, "{}", usage("printf <format> [args...]"))?;
+            return Err(ShellError::BuiltinError);
+        };
+
+        match printf::format(format, args) {
+            Ok(output) => {
+                write!(shell.streams.stdout, "{output}")?;
+                shell.streams.stdout.flush()?;
+                Ok(())
+            }
+            Err(err) => {
+                writeln!(shell.streams.stderr, "printf: {err}")?;
+                Err(ShellError::BuiltinError)
+            }
+        }
+    },
 };
+
+/// Returns how to call a builtin, `synopsis`, in the user's language
+pub(crate) fn usage(synopsis: &str) -> String {
+    i18n::format(Message::BuiltinUsage, &[&synopsis])
+}
+
+/// Splits the `--json` flag the builtins reporting the state of the shell or the system take from their other arguments
+fn json_flag<'a, 'b>(args: &'a [&'b str]) -> (bool, &'a [&'b str]) {
+    match args.split_first() {
+        Some((&"--json", args)) => (true, args),
+        _ => (false, args),
+    }
+}
+
+/// Prints each of `items` on its own line or as a JSON array with `--json`, or the error that happened while listing them
+fn list(
+    shell: &mut Shell,
+    name: &str,
+    args: &[&str],
+    items: io::Result<Vec<String>>,
+) -> Result<(), ShellError> {
+    let (json, []) = json_flag(args) else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage(&format!("{name} [--json]"))
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    match items {
+        Ok(items) if json => {
+            let items = items.into_iter().map(Value::from).collect();
+            writeln!(shell.streams.stdout, "{}", Value::Array(items))?;
+            Ok(())
+        }
+        Ok(items) => {
+            for item in items {
+                writeln!(shell.streams.stdout, "{item}")?;
+            }
+            Ok(())
+        }
+        Err(err) => {
+            writeln!(shell.streams.stderr, "{name}: {err}")?;
+            Err(ShellError::BuiltinError)
+        }
+    }
+}
+
+/// Saves a setting changed using `prompt` which has already been applied
+fn save_prompt_setting(shell: &mut Shell, key: &str, value: &str) -> Result<(), ShellError> {
+    if let Err(err) = shell.config.set(key, value) {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::SettingNotSaved, &[&"prompt", &err])
+        )?;
+        return Err(ShellError::BuiltinError);
+    }
+    Ok(())
+}
+
+fn prompt(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    match args {
+        ["theme"] => {
+            for theme in Theme::ALL {
+                let marker = if theme == shell.prompt.theme {
+                    '*'
+                } else {
+                    ' '
+                };
+                writeln!(shell.streams.stdout, "{marker} {}", theme.name())?;
+            }
+            Ok(())
+        }
+        ["theme", name] => {
+            let Some(theme) = Theme::from_name(name) else {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::UnknownTheme, &[&"prompt", &name])
+                )?;
+                return Err(ShellError::BuiltinError);
+            };
+
+            shell.prompt.theme = theme;
+            if let Err(err) = shell.config.set("prompt.theme", theme.name()) {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::SettingNotSaved, &[&"prompt", &err])
+                )?;
+                return Err(ShellError::BuiltinError);
+            }
+            Ok(())
+        }
+        ["glyphs"] => {
+            for glyphs in Glyphs::ALL {
+                let marker = if glyphs == shell.prompt.glyphs {
+                    '*'
+                } else {
+                    ' '
+                };
+                writeln!(shell.streams.stdout, "{marker} {}", glyphs.name())?;
+            }
+            Ok(())
+        }
+        ["glyphs", name] => {
+            let Some(glyphs) = Glyphs::from_name(name) else {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::UnknownGlyphs, &[&"prompt", &name])
+                )?;
+                return Err(ShellError::BuiltinError);
+            };
+
+            shell.prompt.glyphs = glyphs;
+            if let Err(err) = shell.config.set("prompt.glyphs", glyphs.name()) {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::SettingNotSaved, &[&"prompt", &err])
+                )?;
+                return Err(ShellError::BuiltinError);
+            }
+            Ok(())
+        }
+        ["dirs"] => {
+            match shell.prompt.max_dirs {
+                Some(max_dirs) => writeln!(shell.streams.stdout, "{max_dirs}")?,
+                None => writeln!(shell.streams.stdout, "all")?,
+            }
+            Ok(())
+        }
+        ["dirs", value] => {
+            let Some(max_dirs) = Prompt::parse_max_dirs(value) else {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::InvalidDirs, &[&"prompt", &value])
+                )?;
+                return Err(ShellError::BuiltinError);
+            };
+
+            shell.prompt.max_dirs = max_dirs;
+            save_prompt_setting(shell, "prompt.dirs", value)
+        }
+        ["segments"] => {
+            for segment in SEGMENTS {
+                let enabled = shell
+                    .prompt
+                    .segments
+                    .iter()
+                    .any(|enabled| enabled.name == segment.name);
+                let marker = if enabled { '*' } else { ' ' };
+                writeln!(shell.streams.stdout, "{marker} {}", segment.name)?;
+            }
+            Ok(())
+        }
+        ["segments", names @ ..] => {
+            let mut segments = Vec::new();
+            for name in names {
+                let Some(segment) = Segment::from_name(name) else {
+                    writeln!(
+                        shell.streams.stderr,
+                        "{}",
+                        i18n::format(Message::UnknownSegment, &[&"prompt", &name])
+                    )?;
+                    return Err(ShellError::BuiltinError);
+                };
+                segments.push(segment);
+            }
+
+            shell.prompt.segments = segments;
+            save_prompt_setting(shell, "prompt.segments", &names.join(","))
+        }
+        ["title"] => {
+            let title = if shell.prompt.title { "on" } else { "off" };
+            writeln!(shell.streams.stdout, "{title}")?;
+            Ok(())
+        }
+        ["title", value @ ("on" | "off")] => {
+            shell.prompt.title = *value == "on";
+            if !shell.prompt.title && terminal::detect().title {
+                shell.write_title("");
+            }
+            save_prompt_setting(shell, "prompt.title", value)
+        }
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage(
+                    "prompt theme [minimal|powerline|classic]
+       prompt glyphs [auto|unicode|ascii]
+       prompt dirs [all|<count>]
+       prompt segments [time] [duration]
+       prompt title [on|off]"
+                )
+            )?;
+            Err(ShellError::BuiltinError)
+        }
+    }
+}
+
+fn ulimit(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let usage = |shell: &mut Shell| -> Result<(), ShellError> {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("ulimit [-a] [-m|-n|-p [limit]]")
+        )?;
+        Err(ShellError::BuiltinError)
+    };
+
+    let resource = match args {
+        [] | ["-a"] => {
+            for resource in Resource::ALL {
+                writeln!(
+                    shell.streams.stdout,
+                    "{:<24}(-{}) {}",
+                    resource.description(),
+                    resource.flag(),
+                    shell.limits.get(resource)
+                )?;
+            }
+            return Ok(());
+        }
+        [flag] | [flag, _] => {
+            let resource = flag
+                .strip_prefix('-')
+                .filter(|flag| flag.len() == 1)
+                .and_then(|flag| flag.chars().next())
+                .and_then(Resource::from_flag);
+            match resource {
+                Some(resource) => resource,
+                None => return usage(shell),
+            }
+        }
+        _ => return usage(shell),
+    };
+
+    let Some(limit) = args.get(1) else {
+        writeln!(shell.streams.stdout, "{}", shell.limits.get(resource))?;
+        return Ok(());
+    };
+
+    if !limits::SUPPORTED {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::LimitsUnsupported, &[&"ulimit"])
+        )?;
+        return Err(ShellError::BuiltinError);
+    }
+
+    let Some(limit) = Limit::parse(limit) else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::InvalidLimit, &[&"ulimit", &limit])
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+    shell.limits.set(resource, limit);
+    Ok(())
+}
+
+/// `clear` clears the screen and the scrollback, `clear -x` keeps the scrollback
+fn clear(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let keep_scrollback = match args {
+        [] => false,
+        ["-x"] => true,
+        _ => {
+            writeln!(shell.streams.stderr, "{}", usage("clear [-x]"))?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let terminal = terminal::detect();
+    let stdout = &mut shell.streams.stdout;
+    if terminal.escapes {
+        write!(stdout, "\x1b[H\x1b[2J")?;
+        if !keep_scrollback && terminal.clear_scrollback {
+            write!(stdout, "\x1b[3J")?;
+        }
+    } else {
+        // terminals without escape sequences can only scroll what was shown out of sight
+        let lines = shell.vars.get("LINES").and_then(|lines| lines.parse().ok());
+        write!(stdout, "{}", "\n".repeat(lines.unwrap_or(24)))?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// `cd dir` changes the current directory, looking `dir` up in `$CDPATH` first and in `$DRIVEPATH` if it isn't in the current directory
+fn cd(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let Some(target) = args.first() else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::NotEnoughArguments, &[&"cd"])
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    match drives::resolve_dir(target, &shell.vars) {
+        Some(dir) => {
+            std::env::set_current_dir(&dir)?;
+            // tells where `cd` went since it isn't where the user typed
+            let dir = std::env::current_dir().unwrap_or(dir);
+            writeln!(shell.streams.stdout, "{}", dir.display())?;
+        }
+        None => std::env::set_current_dir(target)?,
+    }
+    Ok(())
+}
+
+fn umask(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let Some(current) = umask::get() else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::PermissionsUnsupported, &[&"umask"])
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    match args {
+        [] => writeln!(shell.streams.stdout, "{current:04o}")?,
+        ["-S"] => writeln!(shell.streams.stdout, "{}", umask::symbolic(current))?,
+        [mask] => match umask::parse(mask, current) {
+            Some(mask) => umask::set(mask),
+            None => {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::InvalidMask, &[&"umask", &mask])
+                )?;
+                return Err(ShellError::BuiltinError);
+            }
+        },
+        _ => {
+            writeln!(shell.streams.stderr, "{}", usage("umask [-S] [mask]"))?;
+            return Err(ShellError::BuiltinError);
+        }
+    }
+    Ok(())
+}
+
+fn history(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    match args {
+        // the same as `history export`, the format every builtin reporting state uses with `--json`
+        ["--json"] => {
+            writeln!(shell.streams.stdout, "{}", shell.history.to_json())?;
+            Ok(())
+        }
+        [] => {
+            for (i, entry) in shell.history.entries().iter().enumerate() {
+                writeln!(shell.streams.stdout, "{:>5}  {}", i + 1, entry.command)?;
+            }
+            Ok(())
+        }
+        ["export"] => {
+            writeln!(shell.streams.stdout, "{}", shell.history.to_json())?;
+            Ok(())
+        }
+        ["export", path] => {
+            std::fs::write(path, format!("{}\n", shell.history.to_json()))?;
+            Ok(())
+        }
+        ["import", path] => {
+            let contents = std::fs::read_to_string(path)?;
+            let entries = match History::parse_json(&contents) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    writeln!(shell.streams.stderr, "history: {path}: {err}")?;
+                    return Err(ShellError::BuiltinError);
+                }
+            };
+
+            shell.history.extend(entries);
+            shell.sync_editor_history();
+            Ok(())
+        }
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage("history [--json | export [file] | import <file>]")
+            )?;
+            Err(ShellError::BuiltinError)
+        }
+    }
+}
+
+fn elevate(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("elevate <program> [args...]")
+        )?;
+        return Err(ShellError::BuiltinError);
+    }
+
+    let program = match privilege::elevating_program() {
+        Ok(program) => program,
+        Err(reason) => {
+            writeln!(shell.streams.stderr, "elevate: {}", i18n::text(reason))?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let status = shell.spawn_program(program, args)?.wait()?;
+    if !status.success() {
+        return Err(ShellError::ExitError(status));
+    }
+    Ok(())
+}
+
+fn stat(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        writeln!(shell.streams.stderr, "{}", usage("stat <paths...>"))?;
+        return Err(ShellError::BuiltinError);
+    }
+
+    let mut failed = false;
+    for path in args {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                writeln!(shell.streams.stderr, "stat: {path}: {err}")?;
+                failed = true;
+                continue;
+            }
+        };
+
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_dir() {
+            "directory"
+        } else if file_type.is_symlink() {
+            "symbolic link"
+        } else if file_type.is_file() {
+            "regular file"
+        } else {
+            "device or special file"
+        };
+
+        let stdout = &mut shell.streams.stdout;
+        writeln!(stdout, "    File: {path}")?;
+        writeln!(stdout, "    Type: {kind}")?;
+        writeln!(stdout, "    Size: {} bytes", metadata.len())?;
+        if metadata.permissions().readonly() {
+            writeln!(stdout, "  Access: read-only")?;
+        } else {
+            writeln!(stdout, "  Access: read-write")?;
+        }
+
+        // there is no SafaOS branch: SafaOS has no users, modes, inodes or links yet, a file only has the kind
+        // and size std already reports, so safa_api has nothing more to query
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            writeln!(stdout, "    Mode: {:o}", metadata.mode() & 0o7777)?;
+            writeln!(stdout, "   Inode: {}", metadata.ino())?;
+            writeln!(stdout, "   Links: {}", metadata.nlink())?;
+        }
+
+        for (name, time) in [
+            ("Modified", metadata.modified()),
+            ("Accessed", metadata.accessed()),
+            (" Created", metadata.created()),
+        ] {
+            if let Ok(time) = time {
+                writeln!(stdout, "{name}: {}", format_utc(time))?;
+            }
+        }
+    }
+
+    if failed {
+        return Err(ShellError::BuiltinError);
+    }
+    Ok(())
+}
+
+fn svc(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let [action, name] = args else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("svc <start|stop|restart|status> <service>")
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    let Some(action) = Action::from_name(action) else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::UnknownAction, &[&"svc", &action])
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    match service::request(action, name) {
+        Ok(reply) if reply.ok => {
+            if !reply.message.is_empty() {
+                writeln!(shell.streams.stdout, "{}", reply.message)?;
+            }
+            Ok(())
+        }
+        Ok(reply) => {
+            writeln!(shell.streams.stderr, "svc: {name}: {}", reply.message)?;
+            Err(ShellError::BuiltinError)
+        }
+        Err(err) => {
+            writeln!(shell.streams.stderr, "svc: {err}")?;
+            Err(ShellError::BuiltinError)
+        }
+    }
+}
+
+fn env(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let mut env = ChildEnv::default();
+    let mut args = args;
+
+    if let Some(("-i", rest)) = args.split_first().map(|(first, rest)| (*first, rest)) {
+        env.clear = true;
+        args = rest;
+    }
+
+    while let Some((assignment, rest)) = args.split_first() {
+        let Some((name, value)) = assignment.split_once('=') else {
+            break;
+        };
+        env.vars.push((name.to_string(), value.to_string()));
+        args = rest;
+    }
+
+    let Some((program, args)) = args.split_first() else {
+        // without a command, prints the environment the command would have gotten
+        let mut vars = if env.clear {
+            Vec::new()
+        } else {
+            std::env::vars().collect::<Vec<_>>()
+        };
+        for (name, value) in env.vars {
+            vars.retain(|(existing, _)| *existing != name);
+            vars.push((name, value));
+        }
+
+        for (name, value) in vars {
+            writeln!(shell.streams.stdout, "{name}={value}")?;
+        }
+        return Ok(());
+    };
+
+    let status = shell.spawn_program_with_env(program, args, &env)?.wait()?;
+    if !status.success() {
+        return Err(ShellError::ExitError(status));
+    }
+    Ok(())
+}
+
+/// `capture VAR -- program args...` runs the command and stores what it wrote to stdout in `VAR`,
+/// what it wrote to stderr in `VAR_stderr` and its status in `VAR_status`, both outputs without their trailing line breaks,
+/// succeeds whenever the command could be run even if the command itself failed
+fn capture(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let [name, "--", program, args @ ..] = args else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("capture VAR -- program [args...]")
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    let names = [
+        name.to_string(),
+        format!("{name}_stderr"),
+        format!("{name}_status"),
+    ];
+    names
+        .iter()
+        .try_for_each(|name| shell.check_writable(name))?;
+
+    let (stdout, stderr, status) = shell.capture_command(program, args)?;
+    let trim = |output: &str| output.trim_end_matches('\n').to_string();
+    shell.vars.set(name, trim(&stdout));
+    shell.vars.set(&format!("{name}_stderr"), trim(&stderr));
+    shell
+        .vars
+        .set(&format!("{name}_status"), status.to_string());
+    Ok(())
+}
+
+/// `readonly` or `readonly -p` lists the read-only variables, `readonly name[=value]...` sets the variables
+/// then marks them read-only
+fn readonly(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if matches!(args, [] | ["-p"]) {
+        let names = shell
+            .vars
+            .readonly_names()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for name in names {
+            match shell.vars.get(&name) {
+                Some(value) => {
+                    let value = expand::quote(&value);
+                    writeln!(shell.streams.stdout, "readonly {name}={value}")?
+                }
+                None => writeln!(shell.streams.stdout, "readonly {name}")?,
+            }
+        }
+        return Ok(());
+    }
+
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (*arg, None),
+        };
+        if !parser::is_name(name) {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                i18n::format(Message::NotAValidName, &[&"readonly", &name])
+            )?;
+            return Err(ShellError::BuiltinError);
+        }
+        if let Some(value) = value {
+            shell.check_writable(name)?;
+            shell.vars.set(name, value);
+        }
+        shell.vars.set_readonly(name);
+    }
+    Ok(())
+}
+
+/// `complete -c command -W words` completes the arguments of `command` with the words of `words`,
+/// `complete -c command -F function` with the lines `function` prints given the command's name, the word being completed
+/// and the word before it, `complete -r commands...` removes the rules of `commands` and `complete` lists the rules
+fn complete(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    match args {
+        [] => {
+            let mut rules = shell.completion_rules.iter().collect::<Vec<_>>();
+            rules.sort_by_key(|(command, _)| *command);
+            let rules = rules
+                .into_iter()
+                .map(|(command, rule)| {
+                    let command = expand::quote(command);
+                    match rule {
+                        Rule::Words(words) => {
+                            let words = expand::quote(&words.join(" "));
+                            format!("complete -c {command} -W {words}")
+                        }
+                        Rule::Function(function) => format!("complete -c {command} -F {function}"),
+                    }
+                })
+                .collect::<Vec<_>>();
+            for rule in rules {
+                writeln!(shell.streams.stdout, "{rule}")?;
+            }
+        }
+        ["-r", commands @ ..] if !commands.is_empty() => {
+            for command in commands {
+                shell.completion_rules.remove(*command);
+            }
+        }
+        ["-c", command, "-W", words] | ["-W", words, "-c", command] => {
+            let words = words.split_whitespace().map(str::to_string).collect();
+            shell
+                .completion_rules
+                .insert(command.to_string(), Rule::Words(words));
+        }
+        ["-c", command, "-F", function] | ["-F", function, "-c", command] => {
+            shell
+                .completion_rules
+                .insert(command.to_string(), Rule::Function(function.to_string()));
+        }
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage("complete [-c command (-W words | -F function) | -r commands...]")
+            )?;
+            return Err(ShellError::BuiltinError);
+        }
+    }
+    Ok(())
+}
+
+/// The options `set -o` turns on and `set +o` turns off
+const OPTIONS: [&str; 4] = ["autorehash", "errexit", "menu-complete", "menu-select"];
+
+/// Returns whether the option `name` is on, `None` if there is no such option
+fn option<'a>(shell: &'a mut Shell, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "autorehash" => Some(&mut shell.autorehash),
+        "errexit" => Some(&mut shell.errexit),
+        "menu-complete" => Some(&mut shell.menu_complete),
+        "menu-select" => Some(&mut shell.menu_select),
+        _ => None,
+    }
+}
+
+/// `set` lists the variables and their values quoted so they can be read back, `set --json` lists them as a JSON object,
+/// `set -e` makes the commands being executed stop once one fails and `set +e` undoes it
+fn set(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let json = match (args, json_flag(args)) {
+        (["-e" | "+e"], _) => {
+            shell.errexit = args[0] == "-e";
+            return Ok(());
+        }
+        (["-o" | "+o"], _) => {
+            for name in OPTIONS {
+                let flag = if *option(shell, name).unwrap() {
+                    '-'
+                } else {
+                    '+'
+                };
+                writeln!(shell.streams.stdout, "set {flag}o {name}")?;
+            }
+            return Ok(());
+        }
+        ([flag @ ("-o" | "+o"), name], _) => {
+            let on = *flag == "-o";
+            let Some(option) = option(shell, name) else {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::NoSuchOption, &[&"set", &name])
+                )?;
+                return Err(ShellError::BuiltinError);
+            };
+            *option = on;
+            return Ok(());
+        }
+        (_, (json, [])) => json,
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage("set [--json | -e | +e | -o [option] | +o [option]]")
+            )?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let names = shell
+        .vars
+        .names()
+        .collect::<std::collections::BTreeSet<_>>();
+    let arrays = shell
+        .vars
+        .arrays()
+        .map(|(name, array)| (name.to_string(), array.to_vec()))
+        .collect::<std::collections::HashMap<_, _>>();
+    let values = names.into_iter().filter_map(|name| {
+        let value = match arrays.get(&name) {
+            Some(array) => Value::Array(
+                array
+                    .iter()
+                    .map(|element| element.as_str().into())
+                    .collect(),
+            ),
+            None => shell.vars.get(&name)?.as_ref().into(),
+        };
+        Some((name, value))
+    });
+
+    if json {
+        let values = values.collect::<Value>();
+        writeln!(shell.streams.stdout, "{values}")?;
+        return Ok(());
+    }
+
+    let lines = values
+        .map(|(name, value)| match value {
+            Value::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(expand::quote)
+                    .collect::<Vec<_>>();
+                format!("{name}=({})", elements.join(" "))
+            }
+            value => format!(
+                "{name}={}",
+                expand::quote(value.as_str().unwrap_or_default())
+            ),
+        })
+        .collect::<Vec<_>>();
+    for line in lines {
+        writeln!(shell.streams.stdout, "{line}")?;
+    }
+    Ok(())
+}
+
+/// `unset names...` removes variables, `unset -f names...` removes functions
+fn unset(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if let Some((&"-f", names)) = args.split_first() {
+        for name in names {
+            shell.functions.remove(*name);
+        }
+        return Ok(());
+    }
+
+    for name in args {
+        shell.check_writable(name)?;
+        shell.vars.unset(name);
+    }
+    Ok(())
+}
+
+/// Parses a duration such as `1.5`, `30s`, `2m` or `1h`, in seconds without a suffix
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let (number, unit) = match duration.strip_suffix(['s', 'm', 'h']) {
+        Some(number) => (number, &duration[number.len()..]),
+        None => (duration, "s"),
+    };
+    let seconds = number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.)?;
+    let scale = match unit {
+        "m" => 60.,
+        "h" => 3600.,
+        _ => 1.,
+    };
+    Duration::try_from_secs_f64(seconds * scale).ok()
+}
+
+/// `timeout duration command [args...]` runs `command` and kills it if it is still running after `duration`,
+/// failing with the status 124 in that case
+fn timeout(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let Some((duration, [program, args @ ..])) = args
+        .split_first()
+        .and_then(|(duration, command)| Some((parse_duration(duration)?, command)))
+    else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("timeout <duration> <command> [args...]")
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    let mut child = shell.spawn_command(program, args)?;
+    let deadline = Instant::now() + duration;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return if status.success() {
+                Ok(())
+            } else {
+                Err(ShellError::ExitError(status))
+            };
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(ShellError::TimedOut(program.to_string()));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// `watch [-n interval] command [args...]` clears the screen and runs `command` every 2 seconds or every `interval`,
+/// showing its output under a header with when it ran and its status, until Ctrl+C or `q` is pressed
+fn watch(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (interval, command) = match args {
+        ["-n", interval, command @ ..] => (parse_duration(interval), command),
+        command => (Some(Duration::from_secs(2)), command),
+    };
+    let (Some(interval), [program, args @ ..]) = (interval, command) else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("watch [-n interval] <command> [args...]")
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+    let source = command.join(" ");
+    // the terminal is in raw mode so Ctrl+C is read as a key rather than interrupting the command,
+    // keys can only be read while the terminal is the input
+    let keys = matches!(shell.streams.stdin, Input::Inherit);
+
+    loop {
+        let started = std::time::SystemTime::now();
+        let mut command = shell.command(program, args)?;
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        if keys {
+            command.stdin(Stdio::null());
+        }
+        let mut child = command.spawn()?;
+        // the output is read while the command runs so it isn't blocked writing it,
+        // and shown once the command is done so the header can tell its status
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stdout = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            _ = stdout.read_to_end(&mut output);
+            output
+        });
+        let stderr = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            _ = stderr.read_to_end(&mut output);
+            output
+        });
+
+        let exit_status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if keys && watch_stopped(Duration::from_millis(50))? {
+                _ = child.kill();
+                _ = child.wait();
+                return Ok(());
+            }
+        };
+        let output = std::process::Output {
+            status: exit_status,
+            stdout: stdout.join().expect("stdout reader panicked"),
+            stderr: stderr.join().expect("stderr reader panicked"),
+        };
+        let status = crate::exit_status_code(&output.status);
+
+        clear(shell, &["-x"])?;
+        let stdout = &mut shell.streams.stdout;
+        writeln!(
+            stdout,
+            "Every {}: {source}    {}    status {status}\n",
+            crate::time::format_duration(interval),
+            format_utc(started)
+        )?;
+        stdout.write_all(&output.stdout)?;
+        shell.streams.stderr.write_all(&output.stderr)?;
+        shell.streams.stdout.flush()?;
+
+        // without raw mode Ctrl+C interrupts the command being watched
+        if crate::terminating_signal(&output.status) == Some(2) {
+            return Err(ShellError::ExitError(output.status));
+        }
+        if !keys {
+            std::thread::sleep(interval);
+        } else if watch_stopped(interval)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Waits up to `timeout` for the user to stop `watch` by pressing Ctrl+C or `q`
+fn watch_stopped(timeout: Duration) -> io::Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match readline::poll_key(left)? {
+            Some(0x03 | b'q') => return Ok(true),
+            // other keys are ignored
+            Some(_) => {}
+            None if Instant::now() >= deadline => return Ok(false),
+            // interrupted or the input ended
+            None => std::thread::sleep(left.min(Duration::from_millis(10))),
+        }
+    }
+}
+
+/// `jobs` lists the background jobs, reporting those that are done, `jobs -p` only prints their process IDs
+/// and `jobs --json` describes them as JSON, all can be given job designators such as `%1` or `%+` to list only some jobs
+fn jobs(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (json, args) = json_flag(args);
+    let (pids_only, specs) = match args.split_first() {
+        Some((&"-p", specs)) => (true, specs),
+        _ => (false, args),
+    };
+
+    let mut ids = Vec::new();
+    for spec in specs {
+        match shell.jobs.find(spec) {
+            Some(id) => ids.push(id),
+            None => {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::NoSuchJob, &[&"jobs", &spec])
+                )?;
+                return Err(ShellError::BuiltinError);
+            }
+        }
+    }
+
+    if pids_only {
+        let pids = shell
+            .jobs
+            .iter()
+            .filter(|job| ids.is_empty() || ids.contains(&job.id))
+            .map(|job| job.pid)
+            .collect::<Vec<_>>();
+        for pid in pids {
+            writeln!(shell.streams.stdout, "{pid}")?;
+        }
+        return Ok(());
+    }
+
+    if json {
+        let jobs = shell
+            .jobs
+            .iter()
+            .filter(|job| ids.is_empty() || ids.contains(&job.id))
+            .map(|job| {
+                let mark = shell.jobs.mark(job.id);
+                Value::from_iter([
+                    ("id", Value::from(job.id)),
+                    ("pid", job.pid.into()),
+                    ("command", job.command.as_str().into()),
+                    (
+                        "state",
+                        if job.is_done() { "done" } else { "running" }.into(),
+                    ),
+                    ("mark", (mark != ' ').then(|| mark.to_string()).into()),
+                ])
+            });
+        writeln!(shell.streams.stdout, "{}", Value::Array(jobs.collect()))?;
+        return Ok(());
+    }
+
+    if ids.is_empty() {
+        for notification in shell.jobs.take_done() {
+            writeln!(shell.streams.stdout, "{notification}")?;
+        }
+    }
+    let running = i18n::text(Message::JobRunning);
+    let jobs = shell
+        .jobs
+        .iter()
+        .filter(|job| ids.is_empty() || ids.contains(&job.id))
+        .map(|job| {
+            let state = if job.is_done() {
+                i18n::text(Message::JobDone)
+            } else {
+                running
+            };
+            let mark = shell.jobs.mark(job.id);
+            format!("[{}]{mark} {state}  {}", job.id, job.command)
+        })
+        .collect::<Vec<_>>();
+    for job in jobs {
+        writeln!(shell.streams.stdout, "{job}")?;
+    }
+    Ok(())
+}
+
+/// `source file [args...]` executes the commands of `file` in the shell itself, with the positional parameters set to
+/// `args` if there are any, `return` stops it early
+fn source(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let [path, args @ ..] = args else {
+        writeln!(shell.streams.stderr, "{}", usage("source file [args...]"))?;
+        return Err(ShellError::BuiltinError);
+    };
+    let script = match std::fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(err) => {
+            writeln!(shell.streams.stderr, "source: {path}: {err}")?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    match shell.source(path, &script, args)? {
+        0 => Ok(()),
+        code => Err(ShellError::Status(code)),
+    }
+}
+
+/// `disown` removes the current job from the job table so it isn't hung up when the shell exits, `disown jobs...` removes
+/// the jobs with the given process IDs or designators such as `%1` and `disown -a` removes all of them
+fn disown(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let ids = match args {
+        ["-a"] => shell.jobs.iter().map(|job| job.id).collect(),
+        [] => match shell.jobs.find("%+") {
+            Some(id) => vec![id],
+            None => {
+                writeln!(
+                    shell.streams.stderr,
+                    "{}",
+                    i18n::format(Message::NoCurrentJob, &[&"disown"])
+                )?;
+                return Err(ShellError::BuiltinError);
+            }
+        },
+        specs => {
+            let mut ids = Vec::new();
+            for spec in specs {
+                match shell.jobs.find(spec) {
+                    Some(id) => ids.push(id),
+                    None => {
+                        writeln!(
+                            shell.streams.stderr,
+                            "{}",
+                            i18n::format(Message::NoSuchJob, &[&"disown", &spec])
+                        )?;
+                        return Err(ShellError::BuiltinError);
+                    }
+                }
+            }
+            ids
+        }
+    };
+
+    for id in ids {
+        shell.jobs.disown(id);
+    }
+    Ok(())
+}
+
+/// `detach command [args...]` starts `command` in the background outside of the job table and detached from the terminal,
+/// so it keeps running once the shell exits, its output goes nowhere unless it is redirected
+fn detach(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let Some((program, args)) = args.split_first() else {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            usage("detach command [args...]")
+        )?;
+        return Err(ShellError::BuiltinError);
+    };
+
+    // the terminal belongs to the shell, redirected streams are kept
+    let stdio = |output: &Output| match output {
+        Output::Stdout | Output::Stderr => Ok(Stdio::null()),
+        output => output.to_stdio(),
+    };
+    let stdin = match &shell.streams.stdin {
+        Input::Inherit => Stdio::null(),
+        stdin => stdin.to_stdio()?,
+    };
+    let mut command = shell.command(program, args)?;
+    command
+        .stdin(stdin)
+        .stdout(stdio(&shell.streams.stdout)?)
+        .stderr(stdio(&shell.streams.stderr)?);
+    system::detach(&mut command);
+
+    let mut child = command.spawn()?;
+    shell.vars.set("!", child.id().to_string());
+    // reaps the program once it exits without the shell keeping track of it
+    std::thread::spawn(move || _ = child.wait());
+    Ok(())
+}
+
+/// `wait` waits for all the background jobs, `wait jobs...` waits for the jobs with the given process IDs or designators such as `%1`
+/// and fails with the status of the last one
+fn wait(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        let ids = shell.jobs.iter().map(|job| job.id).collect::<Vec<_>>();
+        for id in ids {
+            shell.jobs.wait(id).transpose()?;
+        }
+        return Ok(());
+    }
+
+    let mut results = Ok(());
+    for spec in args {
+        let Some(id) = shell.jobs.find(spec) else {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                i18n::format(Message::NoSuchJob, &[&"wait", &spec])
+            )?;
+            results = Err(ShellError::BuiltinError);
+            continue;
+        };
+
+        let status = shell.jobs.wait(id).expect("the job was just found")?;
+        results = if status.success() {
+            Ok(())
+        } else {
+            Err(ShellError::ExitError(status))
+        };
+    }
+    results
+}
+
+/// `hash` lists the remembered locations of commands, `hash names...` looks them up and `hash -r` forgets them
+fn hash(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    match args {
+        ["--json"] => {
+            let entries = shell
+                .command_hash
+                .entries(&crate::search_path())
+                .map(|(name, path)| (name, Value::from(path.display().to_string())))
+                .collect::<Value>();
+            writeln!(shell.streams.stdout, "{entries}")?;
+            Ok(())
+        }
+        [] => {
+            for (name, path) in shell.command_hash.entries(&crate::search_path()) {
+                writeln!(shell.streams.stdout, "{name}\t{}", path.display())?;
+            }
+            Ok(())
+        }
+        ["-r"] => {
+            shell.command_hash.clear();
+            Ok(())
+        }
+        names => {
+            let search_path = crate::search_path();
+            let mut failed = false;
+            for name in names {
+                if name.starts_with('-') {
+                    writeln!(
+                        shell.streams.stderr,
+                        "{}",
+                        usage("hash [--json | -r | names...]")
+                    )?;
+                    return Err(ShellError::BuiltinError);
+                }
+
+                if name.contains('/') || shell.command_hash.lookup(name, &search_path).is_none() {
+                    writeln!(
+                        shell.streams.stderr,
+                        "{}",
+                        i18n::format(Message::NotFound, &[&"hash", &name])
+                    )?;
+                    failed = true;
+                }
+            }
+
+            if failed {
+                Err(ShellError::BuiltinError)
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `explain 13` and `explain NoSuchFile` print the name, number and description of an error
+fn explain(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        writeln!(shell.streams.stderr, "{}", usage("explain <code|name>..."))?;
+        return Err(ShellError::BuiltinError);
+    }
+
+    let mut failed = false;
+    for arg in args {
+        // the prompt shows the codes of failed programs as negative numbers
+        let info = match arg.trim_start_matches('-').parse::<u16>() {
+            Ok(code) => errors::by_code(code),
+            Err(_) => errors::by_name(arg),
+        };
+
+        let Some(info) = info else {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                i18n::format(Message::UnknownError, &[&"explain", &arg])
+            )?;
+            failed = true;
+            continue;
+        };
+
+        write!(shell.streams.stdout, "{} ({})", info.name, info.code)?;
+        match info.description {
+            Some(description) => writeln!(shell.streams.stdout, ": {description}")?,
+            None => writeln!(shell.streams.stdout)?,
+        }
+    }
+
+    if failed {
+        Err(ShellError::BuiltinError)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! The colors of the prompt, the line editor and the banner, which can be changed at runtime using `SAFA_COLOR_*` variables
+//! or for good with the `color.*` settings of the configuration file, `SAFA_COLOR_UNKNOWN_COMMAND` being `color.unknown-command`
+//!
+//! A color is a list of names or SGR codes separated by spaces or `;`, such as `SAFA_COLOR_CWD="bold blue"` or `SAFA_COLOR_CWD="1;34"`,
+//! an empty or invalid value keeps the theme's color
+//!
+//! Setting `NO_COLOR` to anything but an empty value, or starting the shell with `--no-color`, turns the colors off
+//! as they are on `dumb` terminals, attributes such as bold and reverse are kept
+
+use crate::{config::Config, terminal, vars::Variables};
+
+/// Something the shell draws in color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The current directory in the prompt
+    Cwd,
+    /// The optional segments of the prompt such as `time`
+    Segment,
+    /// The status of the last command when it failed
+    Error,
+    /// The prompt's `#` when the last command succeeded
+    Success,
+    /// Messages such as the confirmation of pasted lines
+    Notice,
+    /// A command name that can be run in the line being edited
+    Command,
+    /// A command name that can't be found in the line being edited
+    UnknownCommand,
+    /// A quoted string in the line being edited
+    String,
+    /// A variable in the line being edited
+    Variable,
+    /// The rest of a history entry suggested after the cursor
+    Suggestion,
+    /// The SafaOS logo shown by interactive shells
+    Banner,
+    /// The welcome text under the logo
+    BannerText,
+}
+
+impl Role {
+    pub const ALL: [Role; 12] = [
+        Role::Cwd,
+        Role::Segment,
+        Role::Error,
+        Role::Success,
+        Role::Notice,
+        Role::Command,
+        Role::UnknownCommand,
+        Role::String,
+        Role::Variable,
+        Role::Suggestion,
+        Role::Banner,
+        Role::BannerText,
+    ];
+
+    /// The variable the color is read from
+    pub const fn variable(&self) -> &'static str {
+        match self {
+            Role::Cwd => "SAFA_COLOR_CWD",
+            Role::Segment => "SAFA_COLOR_SEGMENT",
+            Role::Error => "SAFA_COLOR_ERROR",
+            Role::Success => "SAFA_COLOR_SUCCESS",
+            Role::Notice => "SAFA_COLOR_NOTICE",
+            Role::Command => "SAFA_COLOR_COMMAND",
+            Role::UnknownCommand => "SAFA_COLOR_UNKNOWN_COMMAND",
+            Role::String => "SAFA_COLOR_STRING",
+            Role::Variable => "SAFA_COLOR_VARIABLE",
+            Role::Suggestion => "SAFA_COLOR_SUGGESTION",
+            Role::Banner => "SAFA_COLOR_BANNER",
+            Role::BannerText => "SAFA_COLOR_BANNER_TEXT",
+        }
+    }
+
+    /// The setting of the configuration file the color is read from when its variable isn't set
+    pub fn key(&self) -> String {
+        let name = &self.variable()["SAFA_COLOR_".len()..];
+        format!("color.{}", name.to_ascii_lowercase().replace('_', "-"))
+    }
+}
+
+/// Whether colors are turned off, by a non-empty `NO_COLOR` as <https://no-color.org> asks or by a terminal
+/// that doesn't understand escape sequences
+pub fn disabled(vars: &Variables) -> bool {
+    vars.get("NO_COLOR").is_some_and(|value| !value.is_empty()) || !terminal::detect().escapes
+}
+
+/// Drops the colors from the SGR parameters `params`, keeping the attributes such as bold and the resets
+fn without_colors(params: &str) -> String {
+    let mut kept = Vec::new();
+    let mut codes = params.split(';');
+    while let Some(code) = codes.next() {
+        match code.parse::<u8>() {
+            Ok(0..=29) => kept.push(code),
+            // 256 and RGB colors are followed by the number or the components of the color
+            Ok(38 | 48) => match codes.next() {
+                Some("5") => _ = codes.next(),
+                Some("2") => _ = codes.nth(2),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    kept.join(";")
+}
+
+const NAMES: &[(&str, u8)] = &[
+    ("reset", 0),
+    ("bold", 1),
+    ("dim", 2),
+    ("italic", 3),
+    ("underline", 4),
+    ("reverse", 7),
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+    ("gray", 90),
+    ("bright-red", 91),
+    ("bright-green", 92),
+    ("bright-yellow", 93),
+    ("bright-blue", 94),
+    ("bright-magenta", 95),
+    ("bright-cyan", 96),
+    ("bright-white", 97),
+];
+
+/// A color as the SGR codes selecting it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Color(Vec<u8>);
+
+impl Color {
+    pub fn parse(value: &str) -> Option<Self> {
+        let codes = value
+            .split(|c: char| c == ';' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                NAMES
+                    .iter()
+                    .find(|(name, _)| *name == part)
+                    .map(|(_, code)| *code)
+                    .or_else(|| part.parse().ok())
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        (!codes.is_empty()).then_some(Self(codes))
+    }
+
+    /// The SGR parameters of the color, such as `1;34`
+    pub fn foreground(&self) -> String {
+        self.join(|code| code)
+    }
+
+    /// The SGR parameters of the color used as a background, attributes such as bold are kept
+    pub fn background(&self) -> String {
+        self.join(|code| match code {
+            30..=37 | 90..=97 => code + 10,
+            code => code,
+        })
+    }
+
+    fn join(&self, map: impl Fn(u8) -> u8) -> String {
+        self.0
+            .iter()
+            .map(|code| map(*code).to_string())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+/// The colors overridden by `SAFA_COLOR_*` variables and `color.*` settings
+#[derive(Debug, Clone)]
+pub struct Colors {
+    overrides: Vec<(Role, Color)>,
+    /// Whether colors are shown rather than only attributes, see [`disabled`]
+    enabled: bool,
+}
+
+impl Colors {
+    /// Reads the colors from the variables then from `config`, called before drawing so changes show up right away
+    pub fn load(vars: &Variables, config: &Config) -> Self {
+        Self {
+            overrides: Role::ALL
+                .into_iter()
+                .filter_map(|role| {
+                    let color = match vars
+                        .get(role.variable())
+                        .and_then(|value| Color::parse(&value))
+                    {
+                        Some(color) => color,
+                        None => Color::parse(config.get(&role.key())?)?,
+                    };
+                    Some((role, color))
+                })
+                .collect(),
+            enabled: !disabled(vars),
+        }
+    }
+
+    pub fn get(&self, role: Role) -> Option<&Color> {
+        self.overrides
+            .iter()
+            .find(|(overridden, _)| *overridden == role)
+            .map(|(_, color)| color)
+    }
+
+    /// The SGR parameters of `role`, `default` unless it was overridden
+    pub fn foreground(&self, role: Role, default: &str) -> String {
+        let params = self
+            .get(role)
+            .map_or_else(|| default.to_string(), Color::foreground);
+        self.shown(&params)
+    }
+
+    /// The SGR parameters of `role` used as a background, `default` unless it was overridden
+    pub fn background(&self, role: Role, default: &str) -> String {
+        let params = self
+            .get(role)
+            .map_or_else(|| default.to_string(), Color::background);
+        self.shown(&params)
+    }
+
+    /// Returns the part of the SGR parameters `params` that is shown, all of it unless colors are turned off,
+    /// for the colors that aren't a [`Role`] of their own
+    pub fn shown(&self, params: &str) -> String {
+        if self.enabled {
+            params.to_string()
+        } else {
+            without_colors(params)
+        }
+    }
+}
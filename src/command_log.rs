@@ -0,0 +1,43 @@
+//! A log of every command the shell executes, enabled by setting `$SAFA_SHELL_LOG` to the path of the log file,
+//! for debugging boot scripts and reproducing interactive sessions
+//!
+//! Each command of a line or a script is appended as a line of tab separated fields: when it started such as
+//! `2026-10-15 09:41:07 UTC`, the current directory, its status and the command with its line breaks and tabs escaped.
+//! Commands started in the background have `&` as their status, the commands run by functions aren't logged
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    time::SystemTime,
+};
+
+use crate::time::format_utc;
+
+/// The variable holding the path of the log
+pub const LOG_VARIABLE: &str = "SAFA_SHELL_LOG";
+
+/// Appends `command`, which was started at `started` in `cwd` and finished with `status`, to the log at `path`
+pub fn record(
+    path: &str,
+    started: SystemTime,
+    cwd: &str,
+    status: &str,
+    command: &str,
+) -> io::Result<()> {
+    let escape = |field: &str| {
+        field
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\t', "\\t")
+    };
+    let line = format!(
+        "{}\t{}\t{status}\t{}\n",
+        format_utc(started),
+        escape(cwd),
+        escape(command)
+    );
+
+    // a single write so the lines of concurrent shells don't interleave
+    let mut log = OpenOptions::new().create(true).append(true).open(path)?;
+    log.write_all(line.as_bytes())
+}
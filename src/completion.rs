@@ -0,0 +1,281 @@
+//! Completes the word under the cursor into commands, paths or variable names
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    MULTI_PATH_SEP, builtin,
+    completion_defs::{Definition, Operands},
+    drives, exec, help_flags, system,
+    vars::Variables,
+};
+
+/// How the arguments of a command are completed, registered using the `complete` builtin
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// `complete -W words`
+    Words(Vec<String>),
+    /// `complete -F function`, the candidates are the lines `function` prints given the command's name,
+    /// the word being completed and the word before it
+    Function(String),
+}
+
+/// Characters that separate the word being completed from what comes before it
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '|' | '&' | '(' | ')' | '<' | '>')
+}
+
+/// Returns the names in `dir` that start with `prefix`, directories get a trailing `/`
+fn dir_entries(dir: &Path, prefix: &str, dirs_only: bool) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (prefix.is_empty() && name.starts_with('.')) {
+                return None;
+            }
+
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if dirs_only && !is_dir {
+                return None;
+            }
+
+            Some(if is_dir { name + "/" } else { name })
+        })
+        .collect()
+}
+
+fn complete_path(word: &str, dirs_only: bool) -> Vec<String> {
+    // `ram:/` and `sys:/` style prefixes are directories too
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+
+    let search_dir = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+    let mut candidates = dir_entries(search_dir, prefix, dirs_only)
+        .into_iter()
+        .map(|name| format!("{dir}{name}"))
+        .collect::<Vec<_>>();
+
+    // a word without a `/` may be the start of a drive, `:` is part of the word rather than a separator
+    if dir.is_empty() && !word.is_empty() {
+        let drives = system::mounts().unwrap_or_default();
+        candidates.extend(
+            drives
+                .into_iter()
+                .filter(|drive| drive.starts_with(word))
+                .map(|drive| format!("{drive}/")),
+        );
+    }
+    candidates
+}
+
+/// Completes the directory `cd` is given, including the directories `cd` finds in `$CDPATH` and `$DRIVEPATH`
+fn complete_cd(word: &str, vars: &Variables) -> Vec<String> {
+    let mut candidates = complete_path(word, true)
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+
+    if drives::is_searched(word) {
+        let (dir, prefix) = match word.rfind('/') {
+            Some(i) => word.split_at(i + 1),
+            None => ("", word),
+        };
+        for search_dir in drives::search_dirs(vars) {
+            candidates.extend(
+                dir_entries(&search_dir.join(dir), prefix, true)
+                    .into_iter()
+                    .map(|name| format!("{dir}{name}")),
+            );
+        }
+    }
+    candidates.into_iter().collect()
+}
+
+/// Returns the builtins and the programs in `PATH` whose name starts with `prefix`
+pub(crate) fn command_names(prefix: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    names.extend(
+        builtin::names()
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string),
+    );
+
+    let path = crate::search_path();
+    for dir in path.split(MULTI_PATH_SEP).filter(|dir| !dir.is_empty()) {
+        names.extend(
+            dir_entries(Path::new(dir), prefix, false)
+                .into_iter()
+                .filter(|name| !name.ends_with('/')),
+        );
+    }
+
+    names
+}
+
+fn complete_command(word: &str) -> Vec<String> {
+    if word.contains('/') {
+        return complete_path(word, false);
+    }
+
+    command_names(word).into_iter().collect()
+}
+
+fn complete_var(word: &str, vars: &Variables) -> Vec<String> {
+    let name = word.trim_start_matches('$');
+    let names = vars
+        .names()
+        .filter(|var| var.starts_with(name))
+        .map(|var| format!("${var}"))
+        .collect::<BTreeSet<_>>();
+    names.into_iter().collect()
+}
+
+/// Completes `word` using a program's completion definition
+fn complete_defined(definition: &Definition, word: &str) -> Vec<String> {
+    let matching = |candidates: &mut dyn Iterator<Item = &String>| {
+        candidates
+            .filter(|candidate| candidate.starts_with(word))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    if word.starts_with('-') {
+        return matching(&mut definition.flags.iter());
+    }
+
+    let mut candidates = matching(&mut definition.subcommands.keys());
+    match &definition.operands {
+        Operands::Files => candidates.extend(complete_path(word, false)),
+        Operands::Dirs => candidates.extend(complete_path(word, true)),
+        Operands::None => {}
+        Operands::List(list) => candidates.extend(matching(&mut list.iter())),
+    }
+    candidates
+}
+
+/// Runs the function of a `complete -F` rule in a child shell, which has the shell's functions but not its variables,
+/// and returns the lines it prints
+fn generate(
+    function: &str,
+    words: &[&str],
+    word: &str,
+    functions: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut script = functions
+        .iter()
+        .map(|(name, body)| format!("{name}() {body}\n"))
+        .collect::<String>();
+    script.push_str(&format!("{function} \"$@\""));
+
+    let command = words.first().copied().unwrap_or_default();
+    let previous = words.last().copied().unwrap_or_default();
+    let output = Command::new(exec::shell_program())
+        .arg("-c")
+        .arg(script)
+        .arg(function)
+        .args([command, word, previous])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    output.map_or(Vec::new(), |output| {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+/// Completes `word` using a rule registered with the `complete` builtin, `words` being the command's words before it
+fn complete_rule(
+    rule: &Rule,
+    words: &[&str],
+    word: &str,
+    functions: &HashMap<String, String>,
+) -> Vec<String> {
+    let candidates = match rule {
+        Rule::Words(candidates) => candidates.clone(),
+        Rule::Function(function) => generate(function, words, word, functions),
+    };
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .collect()
+}
+
+/// Moves `cursor` back to the start of the character it is in, or to the end of `line` if it is past it
+fn char_boundary(line: &str, cursor: usize) -> usize {
+    let mut cursor = cursor.min(line.len());
+    while !line.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    cursor
+}
+
+/// Returns the offset in `line` of the start of the word that ends at `cursor`, which the candidates replace
+pub fn word_start(line: &str, cursor: usize) -> usize {
+    let before = &line[..char_boundary(line, cursor)];
+    before
+        .rfind(is_separator)
+        .map_or(0, |i| i + before[i..].chars().next().unwrap().len_utf8())
+}
+
+/// Completes the word that ends at `cursor` in `line`, the candidates replace the whole word
+pub fn complete(line: &str, cursor: usize, vars: &Variables) -> Vec<String> {
+    complete_with_rules(line, cursor, vars, &HashMap::new(), &HashMap::new())
+}
+
+/// Completes like [`complete`], consulting the rules registered with the `complete` builtin before anything else,
+/// `functions` being the shell's functions which `complete -F` rules run
+pub(crate) fn complete_with_rules(
+    line: &str,
+    cursor: usize,
+    vars: &Variables,
+    rules: &HashMap<String, Rule>,
+    functions: &HashMap<String, String>,
+) -> Vec<String> {
+    let before = &line[..char_boundary(line, cursor)];
+    let start = word_start(line, cursor);
+    let word = &before[start..];
+
+    // the word is a command if nothing but separators precede it in the current command
+    let preceding = before[..start].trim_end_matches(char::is_whitespace);
+    let is_command = preceding.is_empty() || preceding.ends_with([';', '|', '&', '(']);
+    let command_words = preceding
+        .rsplit([';', '|', '&', '('])
+        .next()
+        .map_or(Vec::new(), |command| command.split_whitespace().collect());
+    let command_name = command_words.first().copied();
+
+    if word.starts_with('$') {
+        complete_var(word, vars)
+    } else if is_command {
+        complete_command(word)
+    } else if let Some(rule) = command_name.and_then(|name| rules.get(name)) {
+        complete_rule(rule, &command_words, word, functions)
+    } else if let Some(definition) = command_name.and_then(|name| Definition::load(name, vars)) {
+        complete_defined(definition.resolve(&command_words[1..]), word)
+    } else if let Some(program) = command_name.filter(|name| {
+        word.starts_with('-')
+            && help_flags::enabled(vars)
+            && !builtin::BUILTIN_COMMANDS.contains_key(name)
+    }) {
+        help_flags::complete(program, word)
+    } else if command_name == Some("cd") {
+        complete_cd(word, vars)
+    } else {
+        complete_path(word, false)
+    }
+}
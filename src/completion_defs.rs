@@ -0,0 +1,91 @@
+//! Completion definitions that packages ship for their programs, loaded from JSON files named after the program
+//! such as `sys:/share/safa-shell/completions/pkg.json`:
+//! ```json
+//! {
+//!     "flags": ["--help", "--verbose"],
+//!     "subcommands": {
+//!         "install": { "flags": ["--force"], "args": "files" },
+//!         "remove": { "args": ["vim", "nano"] }
+//!     }
+//! }
+//! ```
+//! Every field is optional, `args` is either `"files"`, `"dirs"`, `"none"` or a list of candidates and defaults to `"files"`,
+//! subcommands take the same fields as the program itself
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{json::Value, vars::Variables};
+
+/// Where the definitions are looked up, unless `$SAFA_COMPLETIONS_DIR` is set
+const COMPLETIONS_DIR: &str = "sys:/share/safa-shell/completions";
+
+/// What the operands of a program or a subcommand are
+#[derive(Debug)]
+pub enum Operands {
+    Files,
+    Dirs,
+    None,
+    List(Vec<String>),
+}
+
+#[derive(Debug)]
+pub struct Definition {
+    pub flags: Vec<String>,
+    pub operands: Operands,
+    pub subcommands: BTreeMap<String, Definition>,
+}
+
+/// Returns the strings in the array `value`, ignoring anything else
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect()
+}
+
+impl Definition {
+    /// Loads the definition of `program`, returns `None` if there is none or it isn't valid JSON
+    pub fn load(program: &str, vars: &Variables) -> Option<Self> {
+        let name = Path::new(program).file_name()?.to_str()?;
+        let dir = vars
+            .get("SAFA_COMPLETIONS_DIR")
+            .map_or(COMPLETIONS_DIR.to_string(), |dir| dir.into_owned());
+
+        let contents =
+            std::fs::read_to_string(Path::new(&dir).join(format!("{name}.json"))).ok()?;
+        Some(Self::from_json(&Value::parse(&contents).ok()?))
+    }
+
+    fn from_json(value: &Value) -> Self {
+        let operands = match value.get("args") {
+            Some(Value::String(kind)) if kind == "dirs" => Operands::Dirs,
+            Some(Value::String(kind)) if kind == "none" => Operands::None,
+            Some(list @ Value::Array(_)) => Operands::List(strings(Some(list))),
+            _ => Operands::Files,
+        };
+
+        let subcommands = match value.get("subcommands") {
+            Some(Value::Object(subcommands)) => subcommands
+                .iter()
+                .map(|(name, definition)| (name.clone(), Self::from_json(definition)))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+
+        Self {
+            flags: strings(value.get("flags")),
+            operands,
+            subcommands,
+        }
+    }
+
+    /// Returns the definition of the subcommand `words` select, `words` being the words after the program's name
+    pub fn resolve(&self, words: &[&str]) -> &Self {
+        words.iter().fold(self, |definition, word| {
+            definition.subcommands.get(*word).unwrap_or(definition)
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! A persistent `key = value` configuration file for settings changed using builtins
+
+use std::{collections::BTreeMap, fmt::Write, io, path::PathBuf};
+
+pub struct Config {
+    path: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Returns where the configuration file lives, `$SAFA_SHELL_CONFIG` or `$HOME/.safa_config`
+    fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("SAFA_SHELL_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".safa_config"))
+    }
+
+    fn parse(contents: &str) -> BTreeMap<String, String> {
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect()
+    }
+
+    /// Loads the configuration file, a missing or unreadable file results in an empty configuration
+    pub fn load() -> Self {
+        let path = Self::default_path();
+        let values = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default();
+
+        Self { path, values }
+    }
+
+    /// An empty configuration that can't be saved
+    pub fn empty() -> Self {
+        Self {
+            path: None,
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Sets `key` to `value` and writes the configuration file back
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.values.insert(key.to_string(), value.to_string());
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither SAFA_SHELL_CONFIG nor HOME is set",
+            ));
+        };
+
+        let mut contents = String::new();
+        for (key, value) in &self.values {
+            writeln!(contents, "{key} = {value}").unwrap();
+        }
+
+        std::fs::write(path, contents)
+    }
+}
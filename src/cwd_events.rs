@@ -0,0 +1,55 @@
+//! Tells GUI components such as opal-wm's file manager where the shell is, enabled by the `cwd-events` feature
+//!
+//! Whenever the current directory changes, a `cwd <pid> <path>` line is sent to the socket at `$SAFA_CWD_SOCKET`,
+//! or [`DEFAULT_SOCKET`] if it isn't set, nothing is sent if nobody listens.
+//! Setting `events.cwd = off` in the configuration file disables the notifications
+//!
+//! The feature is off by default and only builds on Unix until SafaOS gains sockets
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Where the listener is expected when `$SAFA_CWD_SOCKET` isn't set
+pub const DEFAULT_SOCKET: &str = "/run/opal-wm/cwd.sock";
+
+#[derive(Debug, Default)]
+pub struct CwdNotifier {
+    /// The directory the listener was last told about
+    last: Option<PathBuf>,
+}
+
+impl CwdNotifier {
+    /// Notifies the listener if the current directory changed since the last call
+    pub fn update(&mut self) {
+        let Ok(cwd) = std::env::current_dir() else {
+            return;
+        };
+        if self.last.as_ref() == Some(&cwd) {
+            return;
+        }
+
+        // the listener may not be running, which isn't an error
+        _ = notify(&cwd);
+        self.last = Some(cwd);
+    }
+}
+
+fn notify(cwd: &Path) -> io::Result<()> {
+    let socket = std::env::var("SAFA_CWD_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET.to_string());
+    let mut stream = connect(&socket)?;
+    writeln!(stream, "cwd {} {}", std::process::id(), cwd.display())?;
+    stream.flush()
+}
+
+#[cfg(unix)]
+fn connect(socket: &str) -> io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(socket)
+}
+
+// SafaOS has no sockets yet, so the feature would silently do nothing there
+#[cfg(not(unix))]
+compile_error!(
+    "the `cwd-events` feature needs Unix sockets, which this system doesn't provide yet"
+);
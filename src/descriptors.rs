@@ -0,0 +1,157 @@
+//! The descriptors the shell holds besides the standard streams, such as the pipes connected to a coprocess or the
+//! files opened with `exec 3>file`, which `<&N` and `>&N` give to commands
+//!
+//! The numbers are the shell's own rather than the host's so they work the same on SafaOS,
+//! 0, 1 and 2 always refer to the standard streams of the command being executed.
+//! On unix hosts the programs the shell runs are also given the descriptors 3 to 9 under the same numbers,
+//! SafaOS can only give programs their standard streams
+
+use std::{collections::BTreeMap, io, process::Command};
+
+use crate::stdio::{Input, Output};
+
+#[cfg(unix)]
+mod sys {
+    use std::{
+        io,
+        os::{
+            fd::{AsRawFd, RawFd},
+            unix::process::CommandExt,
+        },
+        process::Command,
+    };
+
+    use super::{Descriptor, FIRST};
+    use crate::stdio::{Input, Output};
+
+    /// The same on Linux, macOS and the BSDs
+    const F_DUPFD: i32 = 0;
+
+    // the C library is always linked on unix
+    unsafe extern "C" {
+        fn fcntl(fd: i32, cmd: i32, ...) -> i32;
+        fn dup2(old: i32, new: i32) -> i32;
+        fn close(fd: i32) -> i32;
+    }
+
+    /// Returns the host's descriptor `descriptor` refers to, `None` if it is closed
+    fn raw_fd(descriptor: &Descriptor) -> Option<RawFd> {
+        Some(match descriptor {
+            Descriptor::Input(Input::Inherit) => 0,
+            Descriptor::Input(Input::File(file)) => file.as_raw_fd(),
+            Descriptor::Input(Input::Pipe(pipe)) => pipe.as_raw_fd(),
+            Descriptor::Output(Output::Stdout) => 1,
+            Descriptor::Output(Output::Stderr) => 2,
+            Descriptor::Output(Output::File(file)) => file.as_raw_fd(),
+            Descriptor::Output(Output::Pipe(pipe) | Output::Forwarded(pipe)) => pipe.as_raw_fd(),
+            Descriptor::Input(Input::Null) | Descriptor::Output(Output::Closed) => return None,
+        })
+    }
+
+    /// Makes the program `command` runs see each of `descriptors` under its number
+    pub fn pass_to<'d>(
+        descriptors: impl Iterator<Item = (u32, &'d Descriptor)>,
+        command: &mut Command,
+    ) {
+        let descriptors = descriptors
+            .filter_map(|(number, descriptor)| Some((number as RawFd, raw_fd(descriptor)?)))
+            .collect::<Vec<_>>();
+        if descriptors.is_empty() {
+            return;
+        }
+
+        // runs in the child before it executes the program, where allocating isn't safe
+        let pass = move || {
+            // each descriptor is first moved past the numbers being assigned so none is overwritten before it is duplicated
+            let mut moved = [-1; FIRST as usize];
+            for &(number, fd) in &descriptors {
+                let temporary = unsafe { fcntl(fd, F_DUPFD, FIRST as i32) };
+                if temporary == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                moved[number as usize] = temporary;
+            }
+            for (number, temporary) in moved.into_iter().enumerate() {
+                if temporary == -1 {
+                    continue;
+                }
+                // unlike the descriptors std opens, duplicated ones are kept open by `exec`
+                if unsafe { dup2(temporary, number as RawFd) } == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                unsafe { close(temporary) };
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(pass) };
+    }
+}
+
+/// The first number given to the descriptors the shell opens, lower numbers are left to scripts
+const FIRST: u32 = 10;
+
+#[derive(Debug)]
+pub enum Descriptor {
+    Input(Input),
+    Output(Output),
+}
+
+#[derive(Debug, Default)]
+pub struct Descriptors {
+    open: BTreeMap<u32, Descriptor>,
+}
+
+impl Descriptors {
+    /// Keeps `descriptor` open, returns the lowest number from 10 up that isn't taken
+    pub fn open(&mut self, descriptor: Descriptor) -> u32 {
+        let number = (FIRST..)
+            .find(|number| !self.open.contains_key(number))
+            .expect("ran out of descriptor numbers");
+        self.open.insert(number, descriptor);
+        number
+    }
+
+    /// Returns a copy of the descriptor `number` to read from, `None` if it isn't open for reading
+    pub fn input(&self, number: u32) -> Option<io::Result<Input>> {
+        match self.open.get(&number)? {
+            Descriptor::Input(input) => Some(input.try_clone()),
+            Descriptor::Output(_) => None,
+        }
+    }
+
+    /// Returns a copy of the descriptor `number` to write to, `None` if it isn't open for writing
+    pub fn output(&self, number: u32) -> Option<io::Result<Output>> {
+        match self.open.get(&number)? {
+            Descriptor::Output(output) => Some(output.try_clone()),
+            Descriptor::Input(_) => None,
+        }
+    }
+
+    /// Replaces the descriptor `number` by `descriptor`, closing it if `None`, returns what it was before
+    pub fn replace(&mut self, number: u32, descriptor: Option<Descriptor>) -> Option<Descriptor> {
+        match descriptor {
+            Some(descriptor) => self.open.insert(number, descriptor),
+            None => self.open.remove(&number),
+        }
+    }
+
+    /// Closes the descriptor `number`, returns whether it was open
+    pub fn close(&mut self, number: u32) -> bool {
+        self.open.remove(&number).is_some()
+    }
+
+    /// Gives the descriptors 3 to 9 to the program `command` runs under the same numbers, the ones the shell opened
+    /// itself such as those of coprocesses are kept from programs so a coprocess sees the end of its input once
+    /// the shell closes it
+    pub fn pass_to(&self, command: &mut Command) {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                // 0, 1 and 2 are the standard streams std gives programs
+                let passed = self.open.range(3..FIRST);
+                sys::pass_to(passed.map(|(number, descriptor)| (*number, descriptor)), command);
+            } else {
+                _ = command;
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! SafaOS paths start with the drive they are on, such as `sys:/bin` or `ram:/tmp`
+//!
+//! `$DRIVEPATH` lists directories separated like `PATH`, usually drive roots such as `sys:/;ram:/`,
+//! where `cd` and its completion look for relative directories that aren't in the current directory
+//!
+//! `$CDPATH` lists directories the same way but is searched before the current directory as POSIX describes,
+//! an empty entry standing for the current directory
+
+use std::path::{Path, PathBuf};
+
+use crate::{MULTI_PATH_SEP, vars::Variables};
+
+/// Returns the drive `path` starts with including its `:`, such as `sys:` for `sys:/bin`
+pub fn drive_of(path: &str) -> Option<&str> {
+    let end = path.find(':')?;
+    let drive = &path[..end];
+    (!drive.is_empty() && !drive.contains('/')).then_some(&path[..=end])
+}
+
+/// Returns the entries of the variable `name`, which are separated like `PATH`
+fn entries(vars: &Variables, name: &str) -> Vec<PathBuf> {
+    vars.get(name)
+        .unwrap_or_default()
+        .split(MULTI_PATH_SEP)
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Returns the directories listed in `$CDPATH` then `$DRIVEPATH`, without the current directory
+pub fn search_dirs(vars: &Variables) -> Vec<PathBuf> {
+    entries(vars, "CDPATH")
+        .into_iter()
+        .chain(entries(vars, "DRIVEPATH"))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect()
+}
+
+/// Returns whether `target` is looked up in `$CDPATH` and `$DRIVEPATH`, which is the case for relative paths
+/// that don't explicitly start from the current directory like `./bin` does
+pub fn is_searched(target: &str) -> bool {
+    drive_of(target).is_none()
+        && !target.starts_with('/')
+        && !matches!(target.split('/').next(), Some("." | ".."))
+}
+
+/// Returns the directory in `$CDPATH` that `target` refers to, or the one in `$DRIVEPATH` if it isn't a directory in the current directory,
+/// `None` if `target` is taken as is
+pub fn resolve_dir(target: &str, vars: &Variables) -> Option<PathBuf> {
+    if !is_searched(target) {
+        return None;
+    }
+
+    for dir in entries(vars, "CDPATH") {
+        if dir.as_os_str().is_empty() && Path::new(target).is_dir() {
+            return None;
+        }
+        let path = dir.join(target);
+        if !dir.as_os_str().is_empty() && path.is_dir() {
+            return Some(path);
+        }
+    }
+    if Path::new(target).is_dir() {
+        return None;
+    }
+
+    entries(vars, "DRIVEPATH")
+        .into_iter()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.join(target))
+        .find(|path| path.is_dir())
+}
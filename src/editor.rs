@@ -0,0 +1,854 @@
+//! The line editor reading commands from the terminal, which is expected to be in raw mode
+
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::{
+    readline::{IOWrapper, Received},
+    terminal, width,
+};
+
+/// A key press decoded from the terminal's input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    /// `Ctrl` and a letter, the letter is lowercase
+    Ctrl(char),
+    /// `Alt` and a character, sent as an escape followed by the character
+    Alt(char),
+    Enter,
+    Tab,
+    /// `Shift` and `Tab`
+    BackTab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// The start of pasted text, which terminals in bracketed paste mode surround with `ESC [ 200 ~` and `ESC [ 201 ~`
+    PasteStart,
+    /// Not a key but the terminal being resized while waiting for one
+    Resize,
+    /// An escape sequence the editor doesn't handle
+    Unknown,
+}
+
+impl Key {
+    /// Reads the next key from `io`, returns `None` if the input ended
+    fn read(io: &mut IOWrapper) -> Option<Self> {
+        let Received::Byte(byte) = io.receive()? else {
+            return Some(Key::Resize);
+        };
+        Some(match byte {
+            b'\r' | b'\n' => Key::Enter,
+            b'\t' => Key::Tab,
+            0x7f | 0x08 => Key::Backspace,
+            0x1b => Self::read_escape(io)?,
+            0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+            byte if byte < 0x80 => Key::Char(byte as char),
+            first => Self::read_utf8(io, first)?,
+        })
+    }
+
+    /// Reads the rest of a character encoded in UTF-8 starting with `first`
+    fn read_utf8(io: &mut IOWrapper, first: u8) -> Option<Self> {
+        let len = match first {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Some(Key::Unknown),
+        };
+
+        let mut bytes = vec![first];
+        for _ in 1..len {
+            bytes.push(io.read_byte()?);
+        }
+        Some(
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|str| str.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            },
+        )
+    }
+
+    /// Reads what follows an escape, either a control sequence such as `ESC [ A` or an `Alt` combination
+    fn read_escape(io: &mut IOWrapper) -> Option<Self> {
+        let byte = io.read_byte()?;
+        if byte != b'[' && byte != b'O' {
+            return Some(match byte {
+                0x7f => Key::Alt('\x7f'),
+                byte if byte < 0x80 => Key::Alt(byte as char),
+                _ => Key::Unknown,
+            });
+        }
+
+        // the parameters are digits and `;` and the sequence ends with a byte in `@..=~`
+        let mut params = String::new();
+        let last = loop {
+            let byte = io.read_byte()?;
+            if (0x40..=0x7e).contains(&byte) {
+                break byte;
+            }
+            params.push(byte as char);
+        };
+
+        Some(match (last, params.as_str()) {
+            (b'A', _) => Key::Up,
+            (b'B', _) => Key::Down,
+            (b'C', _) => Key::Right,
+            (b'D', _) => Key::Left,
+            (b'H', _) | (b'~', "1" | "7") => Key::Home,
+            (b'F', _) | (b'~', "4" | "8") => Key::End,
+            (b'~', "3") => Key::Delete,
+            (b'~', "200") => Key::PasteStart,
+            (b'Z', _) => Key::BackTab,
+            _ => Key::Unknown,
+        })
+    }
+}
+
+/// The line being edited and the byte offset of the cursor in it
+#[derive(Debug, Default)]
+struct Line {
+    buffer: String,
+    cursor: usize,
+}
+
+impl Line {
+    fn new(buffer: &str, cursor: usize) -> Self {
+        let mut cursor = cursor.min(buffer.len());
+        while !buffer.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+
+        Self {
+            buffer: buffer.to_string(),
+            cursor,
+        }
+    }
+
+    fn set(&mut self, buffer: String) {
+        self.cursor = buffer.len();
+        self.buffer = buffer;
+    }
+
+    fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// The offset of the character before the cursor, as the user sees characters such as `é` written with a combining accent
+    fn previous(&self) -> Option<usize> {
+        width::previous_boundary(&self.buffer, self.cursor)
+    }
+
+    /// The offset of the end of the character after the cursor
+    fn next(&self) -> Option<usize> {
+        width::next_boundary(&self.buffer, self.cursor)
+    }
+
+    fn backspace(&mut self) {
+        if let Some(previous) = self.previous() {
+            self.buffer.replace_range(previous..self.cursor, "");
+            self.cursor = previous;
+        }
+    }
+
+    fn delete(&mut self) {
+        if let Some(next) = self.next() {
+            self.buffer.replace_range(self.cursor..next, "");
+        }
+    }
+
+    fn left(&mut self) {
+        self.cursor = self.previous().unwrap_or(self.cursor);
+    }
+
+    fn right(&mut self) {
+        self.cursor = self.next().unwrap_or(self.cursor);
+    }
+
+    /// The offset of the start of the word before the cursor, words are separated by characters matching `separator`
+    fn word_start(&self, separator: fn(char) -> bool) -> usize {
+        let before = self.buffer[..self.cursor].trim_end_matches(separator);
+        before.rfind(separator).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(0, char::len_utf8)
+        })
+    }
+
+    /// The offset of the end of the word after the cursor, words are separated by characters matching `separator`
+    fn word_end(&self, separator: fn(char) -> bool) -> usize {
+        let after = &self.buffer[self.cursor..];
+        let start = after.len() - after.trim_start_matches(separator).len();
+        let end = after[start..]
+            .find(separator)
+            .map_or(after.len(), |i| start + i);
+        self.cursor + end
+    }
+
+    /// Removes the text between the cursor and `to` and returns it, the cursor ends up where the text was
+    fn cut(&mut self, to: usize) -> String {
+        let range = self.cursor.min(to)..self.cursor.max(to);
+        self.cursor = range.start;
+        self.buffer.drain(range).collect()
+    }
+
+    fn insert_str(&mut self, str: &str) {
+        self.buffer.insert_str(self.cursor, str);
+        self.cursor += str.len();
+    }
+}
+
+/// Reads pasted text up to the end of the paste, with its line breaks as `\n`
+fn read_paste(io: &mut IOWrapper) -> String {
+    const END: &[u8] = b"\x1b[201~";
+
+    let mut bytes = Vec::new();
+    while !bytes.ends_with(END) {
+        match io.read_byte() {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    let text = bytes.strip_suffix(END).unwrap_or(&bytes);
+    String::from_utf8_lossy(text)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+/// Lays `candidates` out in as many columns as fit in `columns`, sorted down each column then across as `ls` does,
+/// on a single row if the width of the terminal isn't known
+fn layout_candidates(candidates: &[String], columns: Option<usize>) -> String {
+    let cell_width = candidates
+        .iter()
+        .map(|candidate| width::width(candidate))
+        .max()
+        .unwrap_or_default()
+        + 2;
+    let Some(per_row) = columns.map(|columns| (columns / cell_width).max(1)) else {
+        return candidates.join("  ");
+    };
+
+    let rows = candidates.len().div_ceil(per_row);
+    let mut text = String::new();
+    for row in 0..rows {
+        if row > 0 {
+            text.push_str("\r\n");
+        }
+        let cells = (0..per_row).filter_map(|column| candidates.get(column * rows + row));
+        let cells = cells.collect::<Vec<_>>();
+        for (i, candidate) in cells.iter().enumerate() {
+            text.push_str(candidate);
+            // the last cell of a row isn't padded so nothing is written past the last column
+            if i + 1 < cells.len() {
+                text.push_str(&" ".repeat(cell_width - width::width(candidate)));
+            }
+        }
+    }
+    text
+}
+
+/// Returns the longest text all of `candidates` start with
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = &candidates[0];
+    let len = candidates[1..].iter().fold(first.len(), |len, candidate| {
+        first[..len]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(candidate.len()), |((i, _), _)| i)
+    });
+    &first[..len]
+}
+
+/// What Tab does when the word before the cursor has several candidates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompletionStyle {
+    /// Completes the text the candidates start with, listing them if there is no more of it
+    #[default]
+    List,
+    /// Replaces the word by each candidate in turn, Shift+Tab going backwards
+    Cycle,
+    /// Cycles like [`CompletionStyle::Cycle`] while showing the candidates in a menu under the line,
+    /// which the arrow keys move through
+    Menu,
+}
+
+/// The most rows of candidates the menu shows at once
+const MENU_ROWS: usize = 8;
+
+/// The candidates Tab cycles through, see [`CompletionStyle::Cycle`]
+#[derive(Debug)]
+struct Menu {
+    /// Where the completed word starts
+    start: usize,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    /// Selects the candidate `step` away from the selected one, wrapping around, and puts it in `line` in place of the word
+    fn select(&mut self, step: isize, line: &mut Line) {
+        let len = self.candidates.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+        line.cut(self.start);
+        line.insert_str(&self.candidates[self.selected]);
+    }
+
+    /// The width each candidate is shown with
+    fn cell_width(&self) -> usize {
+        let widest = self
+            .candidates
+            .iter()
+            .map(|candidate| width::width(candidate));
+        widest.max().unwrap_or_default() + 2
+    }
+}
+
+/// Separates the words Alt+F, Alt+B and Alt+D work on
+fn is_word_separator(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+/// The most text kept in the kill ring
+const KILL_RING_SIZE: usize = 16;
+
+/// Text cut from the line, which can be yanked back with Ctrl+Y and cycled through with Alt+Y
+#[derive(Debug, Default)]
+struct KillRing {
+    entries: Vec<String>,
+    /// The entry yanked last, counting from the most recent one
+    yanked: usize,
+}
+
+impl KillRing {
+    /// Adds `text` cut by the key before the cursor if `before`, appending it to the last entry if the previous key also cut text
+    fn kill(&mut self, text: String, before: bool, append: bool) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.entries.last_mut() {
+            Some(last) if append && before => last.insert_str(0, &text),
+            Some(last) if append => last.push_str(&text),
+            _ => {
+                if self.entries.len() == KILL_RING_SIZE {
+                    self.entries.remove(0);
+                }
+                self.entries.push(text);
+            }
+        }
+    }
+
+    fn yank(&mut self) -> Option<&str> {
+        self.yanked = 0;
+        self.entries.last().map(String::as_str)
+    }
+
+    /// The entry before the one yanked last
+    fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yanked = (self.yanked + 1) % self.entries.len();
+        self.entries
+            .get(self.entries.len() - 1 - self.yanked)
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Editor {
+    history: Vec<String>,
+    /// The index in `history` of the last occurrence of each line, sorted so the lines starting with some text are next to each other
+    history_lines: BTreeMap<String, usize>,
+    kill_ring: KillRing,
+    /// The SGR parameters of the suggestion shown after the cursor
+    pub suggestion_color: String,
+    /// The command Ctrl+X Ctrl+E edits the line with, `$VISUAL` or `$EDITOR`
+    pub external_editor: Option<String>,
+    /// What the line breaks of pasted text are shown as, so the line is still drawn on a single row
+    pub line_break_glyph: &'static str,
+    /// The width of the terminal, lines longer than it wrap onto the next rows
+    pub columns: Option<usize>,
+    /// What Tab does when there are several candidates
+    pub completion_style: CompletionStyle,
+    /// How many rows below the first row of the line the terminal's cursor is
+    cursor_row: usize,
+    /// The column of the terminal's cursor, once the line has been drawn
+    cursor_column: usize,
+    /// How many rows below the first row of the line its last row is, once it has been drawn
+    end_row: usize,
+}
+
+/// Creates a file only the user can access under a random name in the temporary directory, a file that already
+/// exists there such as a link planted by another user is never opened
+fn create_temporary_file() -> io::Result<(PathBuf, File)> {
+    use std::hash::{BuildHasher, RandomState};
+
+    loop {
+        let name = format!(
+            "safa-line-{:016x}.sh",
+            RandomState::new().hash_one(std::process::id())
+        );
+        let path = std::env::temp_dir().join(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Edits `text` in a temporary file using the command `editor`, returns the edited text without its trailing line break
+fn edit_externally(editor: &str, text: &str) -> io::Result<String> {
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the editor is empty"))?;
+
+    let (path, mut file) = create_temporary_file()?;
+    file.write_all(format!("{text}\n").as_bytes())?;
+    // the editor may replace the file rather than write to it
+    drop(file);
+    let status = Command::new(program).args(words).arg(&path).status();
+    let edited = std::fs::read_to_string(&path);
+    _ = std::fs::remove_file(&path);
+
+    if !status?.success() {
+        return Err(io::Error::other("the editor failed"));
+    }
+    Ok(edited?.trim_end_matches('\n').to_string())
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the lines that can be recalled using the arrow keys and suggested to `entries`, the shell records the
+    /// lines it executes in its history so `$HISTCONTROL` and `$HISTSIZE` apply to them too
+    pub fn set_history<'a>(&mut self, entries: impl Iterator<Item = &'a str>) {
+        self.history.clear();
+        self.history_lines.clear();
+        for entry in entries {
+            self.add_history(entry);
+        }
+    }
+
+    fn add_history(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.history.last().is_none_or(|last| last != line) {
+            self.history_lines
+                .insert(line.to_string(), self.history.len());
+            self.history.push(line.to_string());
+        }
+    }
+
+    /// Returns the rest of the most recent history entry starting with `prefix`
+    fn suggestion(&self, prefix: &str) -> Option<&str> {
+        if prefix.is_empty() {
+            return None;
+        }
+
+        self.history_lines
+            .range::<str, _>((
+                std::ops::Bound::Excluded(prefix),
+                std::ops::Bound::Unbounded,
+            ))
+            .take_while(|(entry, _)| entry.starts_with(prefix))
+            .max_by_key(|(_, index)| **index)
+            .map(|(entry, _)| &entry[prefix.len()..])
+    }
+
+    /// Redraws the last line of the prompt followed by `line` colored by `highlight` and the suggestion if `suggest`,
+    /// then moves the terminal's cursor to the line's cursor
+    fn redraw(
+        &mut self,
+        io: &mut IOWrapper,
+        prompt: &str,
+        line: &Line,
+        highlight: &mut impl FnMut(&str) -> String,
+        suggest: bool,
+    ) -> io::Result<()> {
+        let prompt = prompt.rsplit('\n').next().unwrap_or_default();
+        let glyph = self.line_break_glyph;
+        let highlighted = highlight(&line.buffer).replace('\n', glyph);
+        if self.cursor_row > 0 {
+            write!(io.stdout, "\x1b[{}A", self.cursor_row)?;
+        }
+        write!(io.stdout, "\r{prompt}{highlighted}")?;
+
+        // suggestions are only shown with the cursor at the end, where they can be accepted
+        let suggestion = Some(&line.buffer)
+            .filter(|_| suggest && line.cursor == line.buffer.len())
+            .and_then(|buffer| self.suggestion(buffer))
+            .unwrap_or_default()
+            .replace('\n', glyph);
+        if !suggestion.is_empty() {
+            let color = &self.suggestion_color;
+            write!(io.stdout, "\x1b[{color}m{suggestion}\x1b[0m")?;
+        }
+        // clears the rows a longer line wrapped onto too
+        write!(io.stdout, "\x1b[J")?;
+
+        let before_cursor = width::visible_width(prompt)
+            + width::width(&line.buffer[..line.cursor].replace('\n', glyph));
+        let after_cursor = width::width(&line.buffer[line.cursor..].replace('\n', glyph))
+            + width::width(&suggestion);
+        let Some(columns) = self.columns.filter(|&columns| columns > 0) else {
+            if after_cursor > 0 {
+                write!(io.stdout, "\x1b[{after_cursor}D")?;
+            }
+            return io.stdout.flush();
+        };
+
+        let end = before_cursor + after_cursor;
+        // terminals only move to the next row once something is written past the last column
+        if end > 0 && end.is_multiple_of(columns) {
+            write!(io.stdout, "\r\n")?;
+        }
+        let (row, column) = (before_cursor / columns, before_cursor % columns);
+        if end / columns > row {
+            write!(io.stdout, "\x1b[{}A", end / columns - row)?;
+        }
+        write!(io.stdout, "\r")?;
+        if column > 0 {
+            write!(io.stdout, "\x1b[{column}C")?;
+        }
+        self.cursor_row = row;
+        self.cursor_column = column;
+        self.end_row = end / columns;
+        io.stdout.flush()
+    }
+
+    /// Sets the width of the terminal to `columns` once it was resized, terminals reflow the rows the line wrapped onto
+    /// so the cursor is on another row of the line and the next redraw has to start from there
+    fn resize(&mut self, columns: Option<usize>) {
+        if let (Some(old @ 1..), Some(new @ 1..)) = (self.columns, columns) {
+            let before_cursor = self.cursor_row * old + self.cursor_column;
+            self.cursor_row = before_cursor / new;
+            self.cursor_column = before_cursor % new;
+        }
+        self.columns = columns;
+    }
+
+    /// Returns how many candidates of `menu` each row of the menu shows, `None` if the menu can't be shown
+    fn menu_columns(&self, menu: &Menu) -> Option<usize> {
+        let columns = self.columns?;
+        let cell_width = menu.cell_width();
+        (self.completion_style == CompletionStyle::Menu && cell_width < columns)
+            .then(|| columns / cell_width)
+    }
+
+    /// Shows the candidates of `menu` under the line drawn last, the selected one in reverse video,
+    /// and leaves the terminal's cursor where it was
+    fn draw_menu(&mut self, io: &mut IOWrapper, menu: &Menu) -> io::Result<()> {
+        let Some(per_row) = self.menu_columns(menu) else {
+            return Ok(());
+        };
+        let cell_width = menu.cell_width();
+        let rows = menu.candidates.chunks(per_row).collect::<Vec<_>>();
+        // scrolls so the row of the selected candidate is shown
+        let selected_row = menu.selected / per_row;
+        let first_row = selected_row.saturating_sub(MENU_ROWS - 1);
+
+        // line breaks rather than cursor movements so the terminal scrolls if the line is at the bottom
+        let below_line = self.end_row - self.cursor_row + 1;
+        write!(io.stdout, "{}", "\r\n".repeat(below_line))?;
+        let shown = &rows[first_row..rows.len().min(first_row + MENU_ROWS)];
+        for (i, row) in shown.iter().enumerate() {
+            for (j, candidate) in row.iter().enumerate() {
+                let padding = " ".repeat(cell_width - width::width(candidate));
+                match (first_row + i) * per_row + j == menu.selected {
+                    true => write!(io.stdout, "\x1b[7m{candidate}\x1b[0m{padding}")?,
+                    false => write!(io.stdout, "{candidate}{padding}")?,
+                }
+            }
+            if i + 1 < shown.len() {
+                write!(io.stdout, "\r\n")?;
+            }
+        }
+
+        write!(io.stdout, "\x1b[{}A\r", below_line + shown.len() - 1)?;
+        if self.cursor_column > 0 {
+            write!(io.stdout, "\x1b[{}C", self.cursor_column)?;
+        }
+        io.stdout.flush()
+    }
+
+    /// Redraws the line without its suggestion and leaves the terminal's cursor after it, so what follows is written below it
+    fn redraw_to_end(
+        &mut self,
+        io: &mut IOWrapper,
+        prompt: &str,
+        line: &mut Line,
+        highlight: &mut impl FnMut(&str) -> String,
+    ) -> io::Result<()> {
+        line.cursor = line.buffer.len();
+        self.redraw(io, prompt, line, highlight, false)?;
+        self.cursor_row = 0;
+        Ok(())
+    }
+
+    /// Reads a line after showing `prompt`, starting with the text of `initial` in the buffer and the cursor at its byte offset,
+    /// the line is redrawn as `highlight` returns it after each key and replaced by what `expand` returns on Alt+E,
+    /// Tab completes the word before the cursor using `complete`, which returns where the word starts and its candidates,
+    /// returns `None` if the input ended or Ctrl+D was pressed on an empty line
+    pub fn readline(
+        &mut self,
+        prompt: &str,
+        io: &mut IOWrapper,
+        (initial, cursor): (&str, usize),
+        mut highlight: impl FnMut(&str) -> String,
+        mut expand: impl FnMut(&str) -> String,
+        mut complete: impl FnMut(&str, usize) -> (usize, Vec<String>),
+    ) -> io::Result<Option<String>> {
+        let mut line = Line::new(initial, cursor);
+        // the position in the history, the line being written if it is past the last entry
+        let mut history_index = self.history.len();
+        let mut draft = String::new();
+        // whether the previous key cut text, consecutive cuts are joined into one kill ring entry
+        let mut killing = false;
+        // where the text yanked by the previous key starts, so Alt+Y can replace it
+        let mut yank_start = None;
+        // whether the previous key was Ctrl+X, which starts a sequence
+        let mut after_ctrl_x = false;
+        // the candidates Tab is cycling through
+        let mut menu: Option<Menu> = None;
+
+        write!(io.stdout, "{prompt}")?;
+        self.cursor_row = 0;
+        self.redraw(io, prompt, &line, &mut highlight, true)?;
+
+        loop {
+            let Some(key) = Key::read(io) else {
+                if line.buffer.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            };
+
+            if key == Key::Resize {
+                self.resize(terminal::size().map(|(columns, _)| columns));
+                self.redraw(io, prompt, &line, &mut highlight, menu.is_none())?;
+                if let Some(active) = &menu {
+                    self.draw_menu(io, active)?;
+                }
+                continue;
+            }
+
+            // keys moving through the candidates keep cycling, any other key keeps the selected candidate
+            if let Some(active) = &mut menu {
+                let per_row = self.menu_columns(active).map(|per_row| per_row as isize);
+                let step = match (key, per_row) {
+                    (Key::Tab, _) => Some(1),
+                    (Key::BackTab, _) => Some(-1),
+                    (Key::Right, Some(_)) => Some(1),
+                    (Key::Left, Some(_)) => Some(-1),
+                    (Key::Down, Some(per_row)) => Some(per_row),
+                    (Key::Up, Some(per_row)) => Some(-per_row),
+                    _ => None,
+                };
+                if let Some(step) = step {
+                    active.select(step, &mut line);
+                    self.redraw(io, prompt, &line, &mut highlight, false)?;
+                    self.draw_menu(io, active)?;
+                    continue;
+                }
+
+                menu = None;
+                // Enter only closes the menu
+                if key == Key::Enter {
+                    self.redraw(io, prompt, &line, &mut highlight, true)?;
+                    continue;
+                }
+            }
+
+            let was_killing = std::mem::take(&mut killing);
+            let last_yank_start = yank_start.take();
+            let ctrl_x_sequence = std::mem::take(&mut after_ctrl_x);
+
+            match key {
+                Key::Enter => break,
+                Key::Ctrl('x') => after_ctrl_x = true,
+                Key::Ctrl('e') if ctrl_x_sequence => {
+                    let edited = self
+                        .external_editor
+                        .as_deref()
+                        .map(|editor| edit_externally(editor, &line.buffer));
+                    match edited {
+                        // several lines can't be edited here, they are executed right away
+                        Some(Ok(edited)) if edited.contains('\n') => {
+                            write!(io.stdout, "\r\n")?;
+                            io.stdout.flush()?;
+                            return Ok(Some(edited));
+                        }
+                        Some(Ok(edited)) => line.set(edited),
+                        Some(Err(err)) => write!(io.stdout, "\r\nsafa: {err}\r\n")?,
+                        None => write!(io.stdout, "\x07")?,
+                    }
+                    // the editor took over the terminal
+                    write!(io.stdout, "\r\x1b[K{prompt}")?;
+                    self.cursor_row = 0;
+                }
+                Key::Tab | Key::BackTab => {
+                    let (start, candidates) = complete(&line.buffer, line.cursor);
+                    if self.completion_style != CompletionStyle::List && candidates.len() > 1 {
+                        let mut new = Menu {
+                            start,
+                            candidates,
+                            selected: 0,
+                        };
+                        new.select(if key == Key::Tab { 0 } else { -1 }, &mut line);
+                        self.redraw(io, prompt, &line, &mut highlight, false)?;
+                        self.draw_menu(io, &new)?;
+                        menu = Some(new);
+                        continue;
+                    }
+
+                    match candidates.as_slice() {
+                        [] => write!(io.stdout, "\x07")?,
+                        [candidate] => {
+                            line.cut(start);
+                            line.insert_str(candidate);
+                            // directories are completed further, anything else is followed by the next word
+                            if !candidate.ends_with('/')
+                                && !line.buffer[line.cursor..].starts_with(' ')
+                            {
+                                line.insert(' ');
+                            }
+                        }
+                        candidates => {
+                            let common = common_prefix(candidates);
+                            if common.len() > line.cursor - start {
+                                line.cut(start);
+                                line.insert_str(common);
+                            } else {
+                                // lists the candidates under the line, which is shown again after them
+                                let cursor = line.cursor;
+                                self.redraw_to_end(io, prompt, &mut line, &mut highlight)?;
+                                line.cursor = cursor;
+                                let list = layout_candidates(candidates, self.columns);
+                                write!(io.stdout, "\r\n{list}\r\n{prompt}")?;
+                            }
+                        }
+                    }
+                }
+                // pasted text is inserted as is, its lines are only executed once Enter is pressed
+                Key::PasteStart => line.insert_str(&read_paste(io)),
+                Key::Char(c) => line.insert(c),
+                Key::Backspace => line.backspace(),
+                Key::Delete => line.delete(),
+                Key::Left | Key::Ctrl('b') => line.left(),
+                Key::Right | Key::Ctrl('f') | Key::End | Key::Ctrl('e')
+                    if line.cursor == line.buffer.len() =>
+                {
+                    if let Some(suggestion) = self.suggestion(&line.buffer) {
+                        line.insert_str(suggestion);
+                    }
+                }
+                Key::Right | Key::Ctrl('f') => line.right(),
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.buffer.len(),
+                Key::Up | Key::Ctrl('p') if history_index > 0 => {
+                    if history_index == self.history.len() {
+                        draft = std::mem::take(&mut line.buffer);
+                    }
+                    history_index -= 1;
+                    line.set(self.history[history_index].clone());
+                }
+                Key::Down | Key::Ctrl('n') if history_index < self.history.len() => {
+                    history_index += 1;
+                    match self.history.get(history_index) {
+                        Some(entry) => line.set(entry.clone()),
+                        None => line.set(std::mem::take(&mut draft)),
+                    }
+                }
+                Key::Ctrl('c') => {
+                    self.redraw_to_end(io, prompt, &mut line, &mut highlight)?;
+                    write!(io.stdout, "^C\r\n")?;
+                    io.stdout.flush()?;
+                    return Ok(Some(String::new()));
+                }
+                Key::Ctrl('d') if line.buffer.is_empty() => {
+                    write!(io.stdout, "\r\n")?;
+                    io.stdout.flush()?;
+                    return Ok(None);
+                }
+                Key::Ctrl('d') => line.delete(),
+                Key::Ctrl('k') => {
+                    let text = line.cut(line.buffer.len());
+                    self.kill_ring.kill(text, false, was_killing);
+                    killing = true;
+                }
+                Key::Ctrl('u') => {
+                    let text = line.cut(0);
+                    self.kill_ring.kill(text, true, was_killing);
+                    killing = true;
+                }
+                Key::Ctrl('w') => {
+                    let text = line.cut(line.word_start(char::is_whitespace));
+                    self.kill_ring.kill(text, true, was_killing);
+                    killing = true;
+                }
+                Key::Alt('d') => {
+                    let text = line.cut(line.word_end(is_word_separator));
+                    self.kill_ring.kill(text, false, was_killing);
+                    killing = true;
+                }
+                Key::Alt('\x7f') => {
+                    let text = line.cut(line.word_start(is_word_separator));
+                    self.kill_ring.kill(text, true, was_killing);
+                    killing = true;
+                }
+                Key::Alt('f') => line.cursor = line.word_end(is_word_separator),
+                Key::Alt('b') => line.cursor = line.word_start(is_word_separator),
+                Key::Ctrl('y') => {
+                    if let Some(text) = self.kill_ring.yank() {
+                        yank_start = Some(line.cursor);
+                        line.insert_str(text);
+                    }
+                }
+                Key::Alt('y') => {
+                    let rotated =
+                        last_yank_start.and_then(|start| Some((start, self.kill_ring.rotate()?)));
+                    if let Some((start, text)) = rotated {
+                        line.cut(start);
+                        yank_start = Some(start);
+                        line.insert_str(text);
+                    }
+                }
+                // shows what the line runs before running it
+                Key::Alt('e') => line.set(expand(&line.buffer)),
+                Key::Ctrl('l') => {
+                    write!(io.stdout, "\x1b[2J\x1b[H{prompt}")?;
+                    self.cursor_row = 0;
+                }
+                _ => {}
+            }
+
+            self.redraw(io, prompt, &line, &mut highlight, true)?;
+        }
+
+        // the suggestion isn't part of the submitted line
+        self.redraw_to_end(io, prompt, &mut line, &mut highlight)?;
+        write!(io.stdout, "\r\n")?;
+        io.stdout.flush()?;
+        Ok(Some(line.buffer))
+    }
+}
@@ -0,0 +1,48 @@
+//! Decodes the operating system's error codes for `explain`
+
+/// The highest code looked at when searching errors by name
+const MAX_CODE: u16 = 255;
+
+pub struct ErrorInfo {
+    pub code: u16,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Returns the error with the code `code` from safa_api's error table
+#[cfg(target_os = "safaos")]
+pub fn by_code(code: u16) -> Option<ErrorInfo> {
+    let status = safa_api::errors::ErrorStatus::try_from(code).ok()?;
+    Some(ErrorInfo {
+        code,
+        name: format!("{status:?}"),
+        description: None,
+    })
+}
+
+/// Returns the errno `code` named after the [`std::io::ErrorKind`] it maps to
+#[cfg(not(target_os = "safaos"))]
+pub fn by_code(code: u16) -> Option<ErrorInfo> {
+    let err = std::io::Error::from_raw_os_error(code.into());
+    let description = err.to_string();
+    let description = description
+        .strip_suffix(&format!(" (os error {code})"))
+        .unwrap_or(&description);
+
+    if description.starts_with("Unknown error") {
+        return None;
+    }
+
+    Some(ErrorInfo {
+        code,
+        name: format!("{:?}", err.kind()),
+        description: Some(description.to_string()),
+    })
+}
+
+/// Returns the first error called `name`, ignoring case
+pub fn by_name(name: &str) -> Option<ErrorInfo> {
+    (1..=MAX_CODE)
+        .filter_map(by_code)
+        .find(|info| info.name.eq_ignore_ascii_case(name))
+}
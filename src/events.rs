@@ -0,0 +1,105 @@
+//! Structured events about the lines the shell executes, for programs that record or drive the shell
+//! without scraping its output, see [`Shell::events`]
+
+use std::{
+    io::{self, PipeReader, Read, Write},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use crate::{Shell, stdio::Output};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The shell started executing `line`
+    Started { line: String },
+    /// The commands of the line being executed wrote `data` to `stream`
+    Output { stream: Stream, data: Vec<u8> },
+    /// The shell finished executing `line`, with the numeric exit `status` `$?` reports
+    Exited { line: String, status: isize },
+}
+
+/// Copies everything read from `reader` to `output` while sending it as [`Event::Output`]s
+fn tee(
+    mut reader: PipeReader,
+    mut output: Output,
+    stream: Stream,
+    events: Sender<Event>,
+) -> JoinHandle<io::Result<()>> {
+    std::thread::spawn(move || {
+        let mut buf = [0; 4096];
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                return output.flush();
+            }
+
+            output.write_all(&buf[..read])?;
+            _ = events.send(Event::Output {
+                stream,
+                data: buf[..read].to_vec(),
+            });
+        }
+    })
+}
+
+impl Shell {
+    /// Returns a channel receiving an [`Event`] for everything the shell executes from now on,
+    /// replaces the channel returned by previous calls
+    pub fn events(&mut self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.events = Some(sender);
+        receiver
+    }
+
+    /// Executes `line` while capturing its output into events, the output still reaches the current streams
+    pub(crate) fn execute_line_with_events(
+        &mut self,
+        line: &str,
+        events: Sender<Event>,
+    ) -> io::Result<isize> {
+        _ = events.send(Event::Started {
+            line: line.to_string(),
+        });
+
+        let (stdout_reader, stdout_writer) = std::io::pipe()?;
+        let (stderr_reader, stderr_writer) = std::io::pipe()?;
+        let stdout = tee(
+            stdout_reader,
+            self.streams.stdout.try_clone()?,
+            Stream::Stdout,
+            events.clone(),
+        );
+        let stderr = tee(
+            stderr_reader,
+            self.streams.stderr.try_clone()?,
+            Stream::Stderr,
+            events.clone(),
+        );
+
+        let previous_stdout =
+            std::mem::replace(&mut self.streams.stdout, Output::Pipe(stdout_writer));
+        let previous_stderr =
+            std::mem::replace(&mut self.streams.stderr, Output::Pipe(stderr_writer));
+
+        let status = self.report_line(line);
+
+        // drops the shell's ends of the pipes so the tee threads see the end of the output
+        self.streams.stdout = previous_stdout;
+        self.streams.stderr = previous_stderr;
+        stdout.join().expect("stdout tee panicked")?;
+        stderr.join().expect("stderr tee panicked")?;
+
+        _ = events.send(Event::Exited {
+            line: line.to_string(),
+            status,
+        });
+        Ok(status)
+    }
+}
@@ -0,0 +1,1236 @@
+//! Executes the commands produced by the [`Parser`]
+
+use std::{
+    borrow::Cow,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread::JoinHandle,
+    time::SystemTime,
+};
+
+use crate::{
+    Shell, ShellError, braces, builtin, command_log,
+    descriptors::Descriptor,
+    expand, hash,
+    i18n::{self, Message},
+    lexer::{Lexer, Word, WordPart},
+    parser::{
+        self, AndOr, AssignmentValue, CaseItem, CommandKind, Connector, IoNumber, List, Parser,
+        Pipeline, Redirect, RedirectKind, SimpleCommand,
+    },
+    pattern,
+    process_substitution::ProcessSubstitution,
+    stdio::{Input, Output, Streams},
+    suggest, system,
+};
+
+/// The function called with an unknown command and its arguments instead of failing, if it is defined
+const COMMAND_NOT_FOUND_HANDLER: &str = "command_not_found_handle";
+
+/// The descriptors the redirections of a command replaced in the shell's table, along with what they were before
+type Replaced = Vec<(u32, Option<Descriptor>)>;
+
+/// A command that has been started by the shell
+enum Started {
+    /// An external program that may still be running
+    Child(Child),
+    /// A builtin or a compound command, those run inside the shell and have already finished
+    Finished(Result<u32, ShellError>),
+}
+
+/// Returns the text of the words each of `words` results in once its braces are expanded, see [`brace_words`]
+fn brace_text(words: &[Word]) -> Vec<Option<Vec<String>>> {
+    words.iter().map(|word| braces::expand(word.raw)).collect()
+}
+
+/// Replaces the words of `words` that have braces by the words they expand to, whose text `braced` holds,
+/// `words` are borrowed as they are if none of them has braces which is the case of most commands
+fn brace_words<'s, 'w>(
+    words: &'s [Word<'w>],
+    braced: &'w [Option<Vec<String>>],
+) -> Cow<'s, [Word<'w>]> {
+    if braced.iter().all(Option::is_none) {
+        return Cow::Borrowed(words);
+    }
+    words
+        .iter()
+        .zip(braced)
+        .flat_map(|(word, braced)| match braced {
+            Some(words) => words.iter().map(|word| Lexer::word(word)).collect(),
+            None => vec![word.clone()],
+        })
+        .collect()
+}
+
+/// The value of an assignment once expanded
+enum Value {
+    Scalar(String),
+    Array(Vec<String>),
+}
+
+impl Started {
+    fn wait(self) -> Result<u32, ShellError> {
+        match self {
+            Started::Child(mut child) => {
+                let results = child.wait()?;
+                if !results.success() {
+                    Err(ShellError::ExitError(results))
+                } else {
+                    Ok(results.code().unwrap_or(0) as u32)
+                }
+            }
+            Started::Finished(results) => results,
+        }
+    }
+}
+
+/// Whether `results` should be considered a success by `&&`, `||` and `!`
+fn succeeded(results: &Result<u32, ShellError>) -> bool {
+    matches!(results, Ok(0))
+}
+
+/// Whether `results` are `return`, `exit` or `set -e` leaving the commands being executed rather than their status
+fn unwinding(results: &Result<u32, ShellError>) -> bool {
+    matches!(
+        results,
+        Err(ShellError::Return(_) | ShellError::Errexit(_) | ShellError::Exit(_))
+    )
+}
+
+/// Changes to the environment a program is spawned with
+#[derive(Debug, Default)]
+pub(crate) struct ChildEnv {
+    /// Whether to start from an empty environment instead of the shell's
+    pub clear: bool,
+    pub vars: Vec<(String, String)>,
+}
+
+/// Returns the path to the shell's own executable, which runs the commands started in the background
+pub(crate) fn shell_program() -> PathBuf {
+    std::env::current_exe().unwrap_or_else(|_| {
+        std::env::var_os("SHELL").map_or_else(|| PathBuf::from("safa"), PathBuf::from)
+    })
+}
+
+/// Converts the error of spawning `program` into the shell's error for it
+fn spawn_error(program: &str, err: io::Error) -> ShellError {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => ShellError::NotExecutable(program.to_string()),
+        io::ErrorKind::NotFound => ShellError::CommandNotFound(program.to_string()),
+        _ => err.into(),
+    }
+}
+
+impl Shell {
+    pub(crate) fn spawn_program(
+        &mut self,
+        program: &str,
+        args: &[&str],
+    ) -> Result<Child, ShellError> {
+        self.spawn_program_with_env(program, args, &ChildEnv::default())
+    }
+
+    /// Spawns `program` with `args` as a process of its own even if it is a function or a builtin, which run in a child shell,
+    /// so it can be waited for without blocking the shell and killed
+    pub(crate) fn spawn_command(
+        &mut self,
+        program: &str,
+        args: &[&str],
+    ) -> Result<Child, ShellError> {
+        let mut command = self.command(program, args)?;
+        command.spawn().map_err(|err| spawn_error(program, err))
+    }
+
+    /// Builds the command running `program` with `args` as a process of its own, see [`Shell::spawn_command`]
+    pub(crate) fn command(&mut self, program: &str, args: &[&str]) -> Result<Command, ShellError> {
+        if !self.functions.contains_key(program) && !builtin::BUILTIN_COMMANDS.contains_key(program)
+        {
+            return self.program_command(program, args, &ChildEnv::default());
+        }
+
+        let source = [program]
+            .iter()
+            .chain(args)
+            .map(|word| expand::quote(word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut command = self.child_shell(&source);
+        command
+            .stdin(self.streams.stdin.to_stdio()?)
+            .stdout(self.streams.stdout.to_stdio()?)
+            .stderr(self.streams.stderr.to_stdio()?);
+        Ok(command)
+    }
+
+    /// Returns where `program` is, looking in `PATH` then in the current directory,
+    /// `PATH=dirs program` looks `program` up in `dirs`
+    fn find_program(&mut self, program: &str, env: &ChildEnv) -> Option<PathBuf> {
+        let search_path = env
+            .vars
+            .iter()
+            .rfind(|(name, _)| name == "PATH")
+            .map(|(_, value)| value);
+        let hashed = match search_path {
+            // a name with a path separator is a path and is never remembered
+            _ if program.contains('/') => None,
+            // neither are the programs found in a search path given to a single command
+            Some(search_path) => hash::search(program, search_path),
+            None => self.command_hash.lookup(program, &crate::search_path()),
+        };
+
+        hashed.or_else(|| {
+            // the current directory changes too often to be remembered
+            let cwd = std::env::current_dir().expect("Failed to get CWD");
+            Some(cwd.join(program)).filter(|path| path.exists())
+        })
+    }
+
+    /// Builds the command running `program` with the shell's streams and limits
+    fn program_command(
+        &mut self,
+        program: &str,
+        args: &[&str],
+        env: &ChildEnv,
+    ) -> Result<Command, ShellError> {
+        let program_path = self.find_program(program, env);
+
+        // a name with a path separator is a path, spawning it reports whether it exists
+        if program_path.is_none() && !program.contains('/') {
+            return Err(ShellError::CommandNotFound(program.to_string()));
+        }
+
+        let mut command = Command::new(program_path.as_deref().unwrap_or(Path::new(program)));
+        command
+            .args(args)
+            .stdin(self.streams.stdin.to_stdio()?)
+            .stdout(self.streams.stdout.to_stdio()?)
+            .stderr(self.streams.stderr.to_stdio()?);
+        self.limits.apply(&mut command);
+        self.descriptors.pass_to(&mut command);
+        ProcessSubstitution::pass_to(&mut self.process_substitutions, &mut command);
+        if env.clear {
+            command.env_clear();
+        }
+        command.envs(env.vars.iter().map(|(name, value)| (name, value)));
+        Ok(command)
+    }
+
+    pub(crate) fn spawn_program_with_env(
+        &mut self,
+        program: &str,
+        args: &[&str],
+        env: &ChildEnv,
+    ) -> Result<Child, ShellError> {
+        let mut command = self.program_command(program, args, env)?;
+        command.spawn().map_err(|err| spawn_error(program, err))
+    }
+
+    /// Replaces the shell with `program`, only returns if it couldn't be executed
+    pub(crate) fn exec_program(&mut self, program: &str, args: &[&str]) -> ShellError {
+        let mut command = match self.program_command(program, args, &ChildEnv::default()) {
+            Ok(command) => command,
+            Err(err) => return err,
+        };
+        self.close_history();
+
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::unix::process::CommandExt;
+                spawn_error(program, command.exec())
+            } else {
+                // SafaOS can't replace a process yet, the program runs as a child and the shell exits with its status
+                match command.status() {
+                    Ok(status) => std::process::exit(crate::exit_status_code(&status) as i32),
+                    Err(err) => spawn_error(program, err),
+                }
+            }
+        }
+    }
+
+    /// Prints `err` unless the failing command has already reported it itself
+    pub fn report_error(&mut self, err: &ShellError) {
+        match err {
+            ShellError::ExitError(status) => {
+                if let Some(signal) = crate::terminating_signal(status) {
+                    let message = i18n::format(Message::TerminatedBySignal, &[&signal]);
+                    _ = writeln!(self.streams.stderr, "safa: {message}");
+                }
+            }
+            ShellError::BuiltinError
+            | ShellError::Status(_)
+            | ShellError::Return(_)
+            | ShellError::Errexit(_)
+            | ShellError::Exit(_) => {}
+            ShellError::CommandNotFound(name) => {
+                let message = i18n::format(Message::CommandNotFound, &[name]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+
+                let suggestions = if suggest::enabled(&self.vars) {
+                    suggest::similar_commands(name)
+                } else {
+                    Vec::new()
+                };
+                if !suggestions.is_empty() {
+                    let suggestions = suggestions.join(", ");
+                    let message = i18n::format(Message::DidYouMean, &[&suggestions]);
+                    _ = writeln!(self.streams.stderr, "{message}");
+                }
+            }
+            ShellError::NotExecutable(name) => {
+                let message = i18n::format(Message::NotExecutable, &[name]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+            }
+            ShellError::ReadonlyVariable(name) => {
+                let message = i18n::format(Message::ReadonlyVariable, &[name]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+            }
+            ShellError::TimedOut(command) => {
+                let message = i18n::format(Message::TimedOut, &[command]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+            }
+            ShellError::BadDescriptor(descriptor) => {
+                let message = i18n::format(Message::BadDescriptor, &[descriptor]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+            }
+            ShellError::TooDeep(name) => {
+                let message = i18n::format(Message::TooDeep, &[name, &parser::MAX_DEPTH]);
+                _ = writeln!(self.streams.stderr, "safa: {message}");
+            }
+            ShellError::IoError(err) => {
+                let message = i18n::format(Message::IoError, &[err]);
+                _ = writeln!(self.streams.stderr, "Shell: {message}");
+            }
+            ShellError::ParseError(err) => {
+                let message = i18n::format(Message::SyntaxError, &[err]);
+                _ = writeln!(self.streams.stderr, "Shell: {message}");
+                _ = writeln!(self.streams.stderr, "{}", err.diagnostic());
+            }
+        }
+    }
+
+    /// Returns the streams a command with the given `redirects` should use, opening the redirected files,
+    /// along with the descriptors it replaced in the shell's table which are restored once the command is done
+    fn redirected_streams(
+        &mut self,
+        redirects: &[Redirect],
+    ) -> Result<(Streams, Replaced), ShellError> {
+        let mut streams = self.streams.try_clone()?;
+        let mut replaced = Vec::new();
+
+        for redirect in redirects {
+            if let Err(err) = self.redirect(redirect, &mut streams, &mut replaced) {
+                self.restore_descriptors(replaced);
+                return Err(err);
+            }
+        }
+
+        Ok((streams, replaced))
+    }
+
+    /// Applies `redirect` to `streams`, or to the shell's table for the descriptors from 3 up, recording the
+    /// descriptors it replaced in `replaced`
+    fn redirect(
+        &mut self,
+        redirect: &Redirect,
+        streams: &mut Streams,
+        replaced: &mut Replaced,
+    ) -> Result<(), ShellError> {
+        let (mut outputs, _) = self.substitute([&redirect.target])?;
+        let target = expand::expand_string(&redirect.target, &self.vars, &mut outputs);
+        let target = target.as_str();
+
+        let descriptor = match redirect.kind {
+            RedirectKind::DuplicateInput | RedirectKind::DuplicateOutput if target == "-" => None,
+            RedirectKind::Input => Some(Descriptor::Input(Input::File(File::open(target)?))),
+            RedirectKind::Output => Some(Descriptor::Output(Output::File(File::create(target)?))),
+            RedirectKind::Append => {
+                let file = OpenOptions::new().create(true).append(true).open(target)?;
+                Some(Descriptor::Output(Output::File(file)))
+            }
+            RedirectKind::DuplicateInput => {
+                Some(Descriptor::Input(self.input_descriptor(target, streams)?))
+            }
+            RedirectKind::DuplicateOutput => {
+                Some(Descriptor::Output(self.output_descriptor(target, streams)?))
+            }
+        };
+
+        let number = match redirect.descriptor {
+            Some(IoNumber::Fixed(number)) => number,
+            Some(IoNumber::Variable(name)) => return self.redirect_variable(name, descriptor),
+            None if matches!(
+                redirect.kind,
+                RedirectKind::Input | RedirectKind::DuplicateInput
+            ) =>
+            {
+                0
+            }
+            None => 1,
+        };
+        match (number, descriptor) {
+            (0, Some(Descriptor::Input(input))) => streams.stdin = input,
+            (0, None) => streams.stdin = Input::Null,
+            (1, Some(Descriptor::Output(output))) => streams.stdout = output,
+            (1, None) => streams.stdout = Output::Closed,
+            (2, Some(Descriptor::Output(output))) => streams.stderr = output,
+            (2, None) => streams.stderr = Output::Closed,
+            // the standard streams are only read from or only written to
+            (0..=2, Some(_)) => return Err(ShellError::BadDescriptor(number.to_string())),
+            (number, descriptor) => {
+                let previous = self.descriptors.replace(number, descriptor);
+                replaced.push((number, previous));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies `{name}>target`, which opens `descriptor` under a new number stored in `name`,
+    /// or `{name}>&-` which closes the descriptor whose number `name` holds, neither is undone after the command
+    fn redirect_variable(
+        &mut self,
+        name: &str,
+        descriptor: Option<Descriptor>,
+    ) -> Result<(), ShellError> {
+        let Some(descriptor) = descriptor else {
+            let number = expand::value(name, &self.vars);
+            return match number.parse() {
+                Ok(parsed) if parsed > 2 && self.descriptors.close(parsed) => Ok(()),
+                _ => Err(ShellError::BadDescriptor(number)),
+            };
+        };
+
+        if !parser::is_name(name) {
+            return Err(ShellError::BadDescriptor(format!("{{{name}}}")));
+        }
+        self.check_writable(name)?;
+        let number = self.descriptors.open(descriptor);
+        self.vars.set(name, number.to_string());
+        Ok(())
+    }
+
+    /// Puts back the descriptors a command's redirections replaced
+    fn restore_descriptors(&mut self, replaced: Replaced) {
+        for (number, previous) in replaced.into_iter().rev() {
+            self.descriptors.replace(number, previous);
+        }
+    }
+
+    /// Returns a copy of the descriptor `number` to read from, as `<&number` gives it to a command
+    /// whose streams are `streams` so far
+    fn input_descriptor(&self, number: &str, streams: &Streams) -> Result<Input, ShellError> {
+        let descriptor = match number.parse() {
+            Ok(0) => Some(streams.stdin.try_clone()),
+            Ok(number) => self.descriptors.input(number),
+            Err(_) => None,
+        };
+        match descriptor {
+            Some(descriptor) => Ok(descriptor?),
+            None => Err(ShellError::BadDescriptor(number.to_string())),
+        }
+    }
+
+    /// Returns a copy of the descriptor `number` to write to, as `>&number` gives it to a command
+    /// whose streams are `streams` so far
+    fn output_descriptor(&self, number: &str, streams: &Streams) -> Result<Output, ShellError> {
+        let descriptor = match number.parse() {
+            Ok(1) => Some(streams.stdout.try_clone()),
+            Ok(2) => Some(streams.stderr.try_clone()),
+            Ok(number) => self.descriptors.output(number),
+            Err(_) => None,
+        };
+        match descriptor {
+            Some(descriptor) => Ok(descriptor?),
+            None => Err(ShellError::BadDescriptor(number.to_string())),
+        }
+    }
+
+    /// Fails if the variable `name` can't be changed because it is read-only
+    pub(crate) fn check_writable(&self, name: &str) -> Result<(), ShellError> {
+        if self.vars.is_readonly(name) {
+            return Err(ShellError::ReadonlyVariable(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Runs the builtin `f` with the variables in `env` set for its duration only
+    fn run_builtin(
+        &mut self,
+        f: &builtin::BuiltInCommand,
+        args: &[&str],
+        env: &ChildEnv,
+    ) -> Result<u32, ShellError> {
+        let previous = env
+            .vars
+            .iter()
+            .map(|(name, value)| {
+                let previous = self.vars.get(name).map(|value| value.into_owned());
+                self.vars.set(name, value.as_str());
+                (name, previous)
+            })
+            .collect::<Vec<_>>();
+
+        let results = f(self, args).map(|()| 0);
+
+        for (name, previous) in previous.into_iter().rev() {
+            match previous {
+                Some(value) => self.vars.set(name, value),
+                None => self.vars.unset(name),
+            }
+        }
+        results
+    }
+
+    fn start_simple(&mut self, command: &SimpleCommand) -> Started {
+        // braces are expanded first, so `$(command){a,b}` runs `command` once for each word
+        let array_braces = command
+            .assignments
+            .iter()
+            .map(|assignment| match &assignment.value {
+                AssignmentValue::Array(words) => brace_text(words),
+                AssignmentValue::Scalar(_) => Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let values = command
+            .assignments
+            .iter()
+            .zip(&array_braces)
+            .map(|(assignment, braced)| match &assignment.value {
+                AssignmentValue::Array(words) => {
+                    AssignmentValue::Array(brace_words(words, braced).into_owned())
+                }
+                value => value.clone(),
+            })
+            .collect::<Vec<_>>();
+        let braced = brace_text(&command.words);
+        let command_words = brace_words(&command.words, &braced);
+
+        // assignments are expanded before the words so their substitutions come first
+        let words = values
+            .iter()
+            .flat_map(AssignmentValue::words)
+            .chain(command_words.iter());
+        let (mut outputs, substitution_status) = match self.substitute(words) {
+            Ok(substituted) => substituted,
+            Err(err) => return Started::Finished(Err(err)),
+        };
+        let assignments = command
+            .assignments
+            .iter()
+            .zip(&values)
+            .map(|(assignment, value)| {
+                let value = match value {
+                    AssignmentValue::Scalar(word) => {
+                        Value::Scalar(expand::expand_string(word, &self.vars, &mut outputs))
+                    }
+                    AssignmentValue::Array(words) => {
+                        Value::Array(expand::expand_words(words, &self.vars, &mut outputs))
+                    }
+                };
+                (assignment.name.to_string(), value)
+            })
+            .collect::<Vec<_>>();
+        if let Err(err) = assignments
+            .iter()
+            .try_for_each(|(name, _)| self.check_writable(name))
+        {
+            return Started::Finished(Err(err));
+        }
+
+        let words = expand::expand_words(&command_words, &self.vars, &mut outputs);
+        let mut words = words.iter().map(String::as_str);
+        let Some(program) = words.next() else {
+            // assignments without a command set shell variables
+            for (name, value) in assignments {
+                match value {
+                    Value::Scalar(value) => self.vars.set(&name, value),
+                    Value::Array(elements) => self.vars.set_array(&name, elements),
+                }
+            }
+            // `var=$(command)` fails if `command` does
+            return Started::Finished(Ok(substitution_status.unwrap_or(0)));
+        };
+        let args = words.collect::<Vec<_>>();
+        // assignments before a command only apply to that command, arrays are passed as their elements separated by spaces
+        let vars = assignments
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::Scalar(value) => (name, value),
+                Value::Array(elements) => (name, elements.join(" ")),
+            })
+            .collect();
+        let env = ChildEnv { clear: false, vars };
+        self.start_program(program, &args, &env)
+    }
+
+    /// Starts `program`, which is a function, a builtin or an external program, with `args` and the variables in `env`
+    fn start_program(&mut self, program: &str, args: &[&str], env: &ChildEnv) -> Started {
+        if let Some(body) = self.functions.get(program).cloned() {
+            return Started::Finished(self.call_function(program, &body, args, env));
+        }
+        if let Some(f) = builtin::BUILTIN_COMMANDS.get(program) {
+            return Started::Finished(self.run_builtin(f, args, env));
+        }
+
+        match self.spawn_program_with_env(program, args, env) {
+            Ok(child) => Started::Child(child),
+            Err(err @ ShellError::CommandNotFound(_)) => {
+                if let Some(f) = builtin::fallback(program) {
+                    return Started::Finished(self.run_builtin(f, args, env));
+                }
+
+                // lets users handle unknown commands themselves, the handler gets the command as its arguments
+                match self.functions.get(COMMAND_NOT_FOUND_HANDLER).cloned() {
+                    Some(handler) => {
+                        let args = [program]
+                            .into_iter()
+                            .chain(args.iter().copied())
+                            .collect::<Vec<_>>();
+                        Started::Finished(self.call_function(
+                            COMMAND_NOT_FOUND_HANDLER,
+                            &handler,
+                            &args,
+                            env,
+                        ))
+                    }
+                    None => Started::Finished(Err(err)),
+                }
+            }
+            Err(err) => Started::Finished(Err(err)),
+        }
+    }
+
+    /// Runs the function whose source is `body` with `args` as its positional parameters
+    /// and the variables in `env` set for its duration only
+    fn call_function(
+        &mut self,
+        function: &str,
+        body: &str,
+        args: &[&str],
+        env: &ChildEnv,
+    ) -> Result<u32, ShellError> {
+        self.check_frames(function)?;
+        let list = Parser::new(body).parse()?;
+        let name = self.vars.get("0").unwrap_or_default().into_owned();
+        let positional = self.vars.positional();
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        self.vars.push_scope();
+        for (name, value) in &env.vars {
+            self.vars.declare_local(name, value.clone());
+        }
+        self.vars.set_positional(&name, &args);
+
+        self.frames += 1;
+        let results = self.execute_list(&list);
+        self.frames -= 1;
+
+        self.vars.set_positional(&name, &positional);
+        self.vars.pop_scope();
+        match results {
+            Err(ShellError::Return(code)) => Ok(code),
+            results => results,
+        }
+    }
+
+    /// Executes `script` in the shell itself as `source` does, with the positional parameters set to `args` for its
+    /// duration unless there are none, `return` stops it with the given status
+    pub(crate) fn source(
+        &mut self,
+        path: &str,
+        script: &str,
+        args: &[&str],
+    ) -> Result<u32, ShellError> {
+        self.check_frames(path)?;
+        let list = Parser::new(script).parse()?;
+        let name = self.vars.get("0").unwrap_or_default().into_owned();
+        let positional = self.vars.positional();
+        if !args.is_empty() {
+            let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+            self.vars.set_positional(&name, &args);
+        }
+
+        self.frames += 1;
+        let results = self.execute_list(&list);
+        self.frames -= 1;
+
+        if !args.is_empty() {
+            self.vars.set_positional(&name, &positional);
+        }
+        match results {
+            Err(ShellError::Return(code)) => Ok(code),
+            results => results,
+        }
+    }
+
+    /// Fails if `name`, a function or a sourced file, can't be executed without nesting functions and sourced files
+    /// more than [`parser::MAX_DEPTH`] levels deep, each level recurses into the commands executing it
+    fn check_frames(&self, name: &str) -> Result<(), ShellError> {
+        if self.frames == parser::MAX_DEPTH {
+            return Err(ShellError::TooDeep(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Starts executing `command`, external programs are spawned without waiting for them to exit
+    fn start_command(&mut self, command: &parser::Command) -> Started {
+        let (previous_streams, replaced) = if command.redirects.is_empty() {
+            (None, Vec::new())
+        } else {
+            match self.redirected_streams(&command.redirects) {
+                Ok((streams, replaced)) => (
+                    Some(std::mem::replace(&mut self.streams, streams)),
+                    replaced,
+                ),
+                Err(err) => return Started::Finished(Err(err)),
+            }
+        };
+
+        let started = match &command.kind {
+            CommandKind::Simple(command) => self.start_simple(command),
+            CommandKind::Subshell(list) => Started::Finished(self.execute_subshell(list)),
+            CommandKind::Group(list) => Started::Finished(self.execute_list(list)),
+            CommandKind::Case { word, items } => Started::Finished(self.execute_case(word, items)),
+            CommandKind::Function { name, body } => {
+                self.functions.insert(name.to_string(), body.to_string());
+                Started::Finished(Ok(0))
+            }
+            CommandKind::Coproc { name, body } => {
+                Started::Finished(self.start_coproc(name.unwrap_or("COPROC"), body))
+            }
+        };
+
+        // `exec` without a command keeps its redirections for the rest of the shell's life
+        let keep_redirections = std::mem::take(&mut self.keep_redirections);
+        if !keep_redirections {
+            if let Some(streams) = previous_streams {
+                self.streams = streams;
+            }
+            self.restore_descriptors(replaced);
+        }
+        started
+    }
+
+    /// Executes the body of the first item of a `case` with a pattern matching `word`
+    fn execute_case(&mut self, word: &Word, items: &[CaseItem]) -> Result<u32, ShellError> {
+        let (mut outputs, _) = self.substitute([word])?;
+        let value = expand::expand_string(word, &self.vars, &mut outputs);
+
+        for item in items {
+            let mut matched = false;
+            // patterns are only expanded until one matches
+            for word in &item.patterns {
+                let (mut outputs, _) = self.substitute([word])?;
+                let pattern = expand::expand_pattern(word, &self.vars, &mut outputs);
+                if pattern::alternatives(&pattern)
+                    .into_iter()
+                    .any(|alternative| pattern::matches(alternative, &value))
+                {
+                    matched = true;
+                    break;
+                }
+            }
+
+            if matched {
+                return self.execute_list(&item.body);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Executes `list` making sure changes to the current directory, variables, functions, options and limits
+    /// don't leak into the shell
+    fn execute_subshell(&mut self, list: &List) -> Result<u32, ShellError> {
+        let cwd = std::env::current_dir()?;
+        let env = std::env::vars_os().collect::<std::collections::HashMap<_, _>>();
+        let vars = self.vars.clone();
+        let functions = self.functions.clone();
+        let limits = self.limits.clone();
+        let options = (
+            self.errexit,
+            self.autorehash,
+            self.menu_complete,
+            self.menu_select,
+        );
+
+        // `return`, `exit` and `set -e` only leave the subshell, whose commands run inside the shell
+        let results = match self.execute_list(list) {
+            Err(ShellError::Return(code) | ShellError::Exit(code) | ShellError::Errexit(code)) => {
+                Ok(code)
+            }
+            results => results,
+        };
+
+        self.vars = vars;
+        self.functions = functions;
+        self.limits = limits;
+        (
+            self.errexit,
+            self.autorehash,
+            self.menu_complete,
+            self.menu_select,
+        ) = options;
+        std::env::set_current_dir(cwd)?;
+        for (name, _) in std::env::vars_os() {
+            if !env.contains_key(&name) {
+                unsafe { std::env::remove_var(name) };
+            }
+        }
+        for (name, value) in env {
+            unsafe { std::env::set_var(name, value) };
+        }
+
+        results
+    }
+
+    /// Runs `f` with stdout, and stderr if `capture_stderr` is set, writing into pipes,
+    /// returning what `f` returned along with what was written to stdout and stderr
+    fn capture<T>(
+        &mut self,
+        capture_stderr: bool,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> io::Result<(T, String, String)> {
+        fn read_pipe() -> io::Result<(io::PipeWriter, JoinHandle<io::Result<Vec<u8>>>)> {
+            let (mut reader, writer) = std::io::pipe()?;
+            // reads concurrently so the commands don't block once they have written more than the pipe can hold
+            let output = std::thread::spawn(move || {
+                let mut output = Vec::new();
+                reader.read_to_end(&mut output).map(|_| output)
+            });
+            Ok((writer, output))
+        }
+
+        let (stdout_writer, stdout) = read_pipe()?;
+        let previous_stdout =
+            std::mem::replace(&mut self.streams.stdout, Output::Pipe(stdout_writer));
+        let stderr = if capture_stderr {
+            let (stderr_writer, stderr) = read_pipe()?;
+            let previous_stderr =
+                std::mem::replace(&mut self.streams.stderr, Output::Pipe(stderr_writer));
+            Some((previous_stderr, stderr))
+        } else {
+            None
+        };
+
+        let results = f(self);
+
+        // drops the shell's ends of the pipes so the readers see the end of the output
+        self.streams.stdout = previous_stdout;
+        let stderr = match stderr {
+            Some((previous_stderr, stderr)) => {
+                self.streams.stderr = previous_stderr;
+                stderr.join().expect("stderr reader panicked")?
+            }
+            None => Vec::new(),
+        };
+        let stdout = stdout.join().expect("stdout reader panicked")?;
+
+        Ok((
+            results,
+            String::from_utf8_lossy(&stdout).into_owned(),
+            String::from_utf8_lossy(&stderr).into_owned(),
+        ))
+    }
+
+    /// Runs `program` with `args` as a command line would, then returns what it wrote to stdout and stderr and its status,
+    /// errors such as the command not being found are reported into the captured stderr
+    pub(crate) fn capture_command(
+        &mut self,
+        program: &str,
+        args: &[&str],
+    ) -> io::Result<(String, String, isize)> {
+        let (status, stdout, stderr) = self.capture(true, |shell| {
+            match shell
+                .start_program(program, args, &ChildEnv::default())
+                .wait()
+            {
+                Ok(code) => code as isize,
+                Err(err) => {
+                    shell.report_error(&err);
+                    err.status()
+                }
+            }
+        })?;
+        Ok((stdout, stderr, status))
+    }
+
+    /// Executes `source` in a subshell and returns what it wrote to stdout without the trailing line breaks
+    /// along with its status, for the `$(source)` command substitution, `exit` only ends the substitution
+    fn capture_output(&mut self, source: &str) -> Result<(String, u32), ShellError> {
+        if self.substitutions == parser::MAX_DEPTH {
+            return Err(parser::ParseError::too_deep(source).into());
+        }
+        let list = Parser::new(source).parse()?;
+
+        self.substitutions += 1;
+        let captured = self.capture(false, |shell| shell.execute_subshell(&list));
+        self.substitutions -= 1;
+        let (results, mut output, _) = captured?;
+        let status = match results {
+            Ok(code) => code,
+            Err(err) => {
+                self.report_error(&err);
+                err.status() as u32
+            }
+        };
+
+        output.truncate(output.trim_end_matches('\n').len());
+        Ok((output, status))
+    }
+
+    /// Executes the command substitutions of `words` and starts their process substitutions in the order they appear,
+    /// returning their outputs and paths and the status of the last command substitution if there is any
+    fn substitute<'w, 'a: 'w>(
+        &mut self,
+        words: impl IntoIterator<Item = &'w Word<'a>>,
+    ) -> Result<(expand::Substitutions, Option<u32>), ShellError> {
+        let mut outputs = expand::Substitutions::new();
+        let mut last_status = None;
+        for part in words.into_iter().flat_map(|word| &word.parts) {
+            match part {
+                WordPart::Command { source, .. } => {
+                    let (output, status) = self.capture_output(source)?;
+                    outputs.push_back(output);
+                    last_status = Some(status);
+                }
+                WordPart::Process { source } => {
+                    let mut command = self.child_shell(source);
+                    command
+                        .stdin(self.streams.stdin.to_stdio()?)
+                        .stderr(self.streams.stderr.to_stdio()?);
+                    let substitution = ProcessSubstitution::start(command)?;
+                    outputs.push_back(substitution.path());
+                    self.process_substitutions.push(substitution);
+                }
+                _ => {}
+            }
+        }
+        Ok((outputs, last_status))
+    }
+
+    /// Executes the commands of a pipeline concurrently, connecting each command's stdout to the next one's stdin
+    ///
+    /// Builtins and compound commands run inside the shell one after another,
+    /// so they block if they write more than a pipe can hold before the next command has started
+    fn execute_piped(
+        &mut self,
+        commands: &[parser::Command],
+    ) -> Result<Vec<Result<u32, ShellError>>, ShellError> {
+        let mut started = Vec::with_capacity(commands.len());
+        let mut stdin = self.streams.stdin.try_clone()?;
+
+        for (i, command) in commands.iter().enumerate() {
+            let (next_stdin, stdout) = if i == commands.len() - 1 {
+                (None, self.streams.stdout.try_clone()?)
+            } else {
+                let (reader, writer) = std::io::pipe()?;
+                (Some(Input::Pipe(reader)), Output::Pipe(writer))
+            };
+
+            let streams = Streams {
+                stdin,
+                stdout,
+                stderr: self.streams.stderr.try_clone()?,
+            };
+            let previous_streams = std::mem::replace(&mut self.streams, streams);
+            started.push(self.start_command(command));
+            // drops our copies of the pipe ends so the commands see EOF once their peers exit
+            self.streams = previous_streams;
+
+            stdin = next_stdin.unwrap_or_default();
+        }
+
+        Ok(started.into_iter().map(Started::wait).collect())
+    }
+
+    fn execute_pipeline(&mut self, pipeline: &Pipeline) -> Result<u32, ShellError> {
+        let substitutions = self.process_substitutions.len();
+        let all_results = match pipeline.commands.as_slice() {
+            [command] => Ok(vec![self.start_command(command).wait()]),
+            commands => self.execute_piped(commands),
+        };
+        // the process substitutions of the pipeline are no longer read from
+        ProcessSubstitution::finish_all(self.process_substitutions.drain(substitutions..));
+        let mut all_results = all_results?;
+
+        let pipestatus = all_results
+            .iter()
+            .map(|results| match results {
+                Ok(code) => *code as isize,
+                Err(err) => err.status(),
+            })
+            .map(|status| status.to_string())
+            .collect::<Vec<_>>();
+        self.vars.set_array("PIPESTATUS", pipestatus);
+
+        let results = all_results
+            .pop()
+            .expect("pipelines have at least one command");
+        for err in all_results
+            .iter()
+            .filter_map(|results| results.as_ref().err())
+        {
+            self.report_error(err);
+        }
+
+        let results = if pipeline.negated && !unwinding(&results) {
+            if let Err(err) = &results {
+                self.report_error(err);
+            }
+            Ok(if succeeded(&results) { 1 } else { 0 })
+        } else {
+            results
+        };
+
+        let (status, status_name) = match &results {
+            Ok(0) => (0, "Success".to_string()),
+            Ok(code) => (*code as isize, "Failure".to_string()),
+            Err(err) => (err.status(), err.status_name()),
+        };
+        self.vars.set("?", status.to_string());
+        self.vars.set("STATUS_NAME", status_name);
+        results
+    }
+
+    fn execute_and_or(&mut self, and_or: &AndOr) -> Result<u32, ShellError> {
+        let mut tested = !and_or.rest.is_empty() || and_or.first.negated;
+        let mut results = self.execute_condition(&and_or.first, tested);
+
+        for (i, (connector, pipeline)) in and_or.rest.iter().enumerate() {
+            if unwinding(&results) {
+                return results;
+            }
+            let should_run = match connector {
+                Connector::And => succeeded(&results),
+                Connector::Or => !succeeded(&results),
+            };
+
+            if should_run {
+                if let Err(err) = &results {
+                    self.report_error(err);
+                }
+                tested = i + 1 < and_or.rest.len() || pipeline.negated;
+                results = self.execute_condition(pipeline, tested);
+            }
+        }
+
+        // `set -e` only stops at the failures which aren't tested, such as those of `false` in `false && true`
+        if !self.errexit
+            || tested
+            || self.conditions > 0
+            || succeeded(&results)
+            || unwinding(&results)
+        {
+            return results;
+        }
+        let status = match results {
+            Ok(code) => code,
+            Err(err) => {
+                self.report_error(&err);
+                err.status() as u32
+            }
+        };
+        Err(ShellError::Errexit(status))
+    }
+
+    /// Executes `pipeline`, as a condition of `&&`, `||` or `!` whose failures don't stop `set -e` if `tested` is set
+    fn execute_condition(&mut self, pipeline: &Pipeline, tested: bool) -> Result<u32, ShellError> {
+        self.conditions += tested as usize;
+        let results = self.execute_pipeline(pipeline);
+        self.conditions -= tested as usize;
+        results
+    }
+
+    fn execute_list(&mut self, list: &List) -> Result<u32, ShellError> {
+        self.execute_items(list, false)
+    }
+
+    /// Executes the items of `list`, appending them to the command log if `log` is set,
+    /// which is only done for the commands of the input so those they run aren't logged too
+    fn execute_items(&mut self, list: &List, log: bool) -> Result<u32, ShellError> {
+        let mut results = Ok(0);
+
+        for and_or in list {
+            if let Err(err) = &results {
+                self.report_error(err);
+            }
+
+            // only looked up for the log, they cost a system call each for every command of a script otherwise
+            let log = log && self.vars.get(command_log::LOG_VARIABLE).is_some();
+            let started = log.then(|| {
+                (
+                    SystemTime::now(),
+                    std::env::current_dir().unwrap_or_default(),
+                )
+            });
+            results = if and_or.background {
+                self.start_background(and_or.source)
+            } else {
+                self.execute_and_or(and_or)
+            };
+            if let Some((started, cwd)) = started {
+                self.log_command(and_or, started, &cwd.to_string_lossy(), &results);
+            }
+            if unwinding(&results) {
+                break;
+            }
+        }
+
+        results
+    }
+
+    /// Appends the command `and_or` to the log at `$SAFA_SHELL_LOG` if it is set, see [`command_log`]
+    fn log_command(
+        &mut self,
+        and_or: &AndOr,
+        started: SystemTime,
+        cwd: &str,
+        results: &Result<u32, ShellError>,
+    ) {
+        let Some(path) = self.vars.get(command_log::LOG_VARIABLE) else {
+            return;
+        };
+
+        let status = match results {
+            Ok(_) if and_or.background => "&".to_string(),
+            Ok(code) => code.to_string(),
+            Err(err) => err.status().to_string(),
+        };
+        // a log that can't be written to doesn't stop the commands
+        _ = command_log::record(&path, started, cwd, &status, and_or.source);
+    }
+
+    /// Returns the command running `source` in a child shell which is given the variables and functions of the shell
+    fn child_shell(&self, source: &str) -> Command {
+        let mut script = String::new();
+        for (name, value) in self.vars.unexported() {
+            // the commands of child shells are logged by the shell that started them
+            if parser::is_name(name) && name != command_log::LOG_VARIABLE {
+                script.push_str(&format!("{name}={}\n", expand::quote(value)));
+            }
+        }
+        for (name, elements) in self.vars.arrays() {
+            let elements = elements.iter().map(|element| expand::quote(element));
+            script.push_str(&format!(
+                "{name}=({})\n",
+                elements.collect::<Vec<_>>().join(" ")
+            ));
+        }
+        let readonly = self.vars.readonly_names().collect::<Vec<_>>();
+        if !readonly.is_empty() {
+            script.push_str(&format!("readonly {}\n", readonly.join(" ")));
+        }
+        for (name, body) in &self.functions {
+            script.push_str(&format!("{name}() {body}\n"));
+        }
+        script.push_str(source);
+
+        let name = self.vars.get("0").unwrap_or_default().into_owned();
+        let mut command = Command::new(shell_program());
+        command
+            .arg("-c")
+            .arg(script)
+            .arg(name)
+            .args(self.vars.positional())
+            .env_remove(command_log::LOG_VARIABLE);
+        self.limits.apply(&mut command);
+        self.descriptors.pass_to(&mut command);
+        command
+    }
+
+    /// Starts `source` in a child shell without waiting for it, as a job the `wait` builtin can wait for
+    fn start_background(&mut self, source: &str) -> Result<u32, ShellError> {
+        // background commands don't read the user's input, which belongs to the shell
+        let stdin = match &self.streams.stdin {
+            Input::Inherit => Stdio::null(),
+            stdin => stdin.to_stdio()?,
+        };
+        let mut command = self.child_shell(source);
+        command
+            .stdin(stdin)
+            .stdout(self.job_stdio(&self.streams.stdout)?)
+            .stderr(self.job_stdio(&self.streams.stderr)?);
+        system::own_process_group(&mut command);
+
+        let child = command.spawn()?;
+        let pid = child.id();
+        let id = self.jobs.add(source, child);
+        self.vars.set("!", pid.to_string());
+        if self.interactive {
+            writeln!(self.streams.stderr, "[{id}] {pid}")?;
+        }
+        Ok(0)
+    }
+
+    /// Returns what a job started in the background is given to write to `output`
+    fn job_stdio(&self, output: &Output) -> io::Result<Stdio> {
+        match (output, &self.job_output) {
+            (Output::Forwarded(_), Some(job_output)) => Ok(job_output.try_clone()?.into()),
+            (output, _) => output.to_stdio(),
+        }
+    }
+
+    /// Starts `source` in a child shell as a job with its stdin and stdout connected to the shell through pipes,
+    /// the descriptors reading its output and writing to its input are set as the array `name` and its process ID as
+    /// `name_PID`
+    fn start_coproc(&mut self, name: &str, source: &str) -> Result<u32, ShellError> {
+        let pid_name = format!("{name}_PID");
+        self.check_writable(name)?;
+        self.check_writable(&pid_name)?;
+
+        let (output, coproc_stdout) = io::pipe()?;
+        let (coproc_stdin, input) = io::pipe()?;
+        let mut command = self.child_shell(source);
+        command
+            .stdin(coproc_stdin)
+            .stdout(coproc_stdout)
+            .stderr(self.job_stdio(&self.streams.stderr)?);
+        system::own_process_group(&mut command);
+        let child = command.spawn()?;
+        // only the coprocess holds its ends of the pipes now, so each side sees the end of the input once the other closes its end
+        drop(command);
+
+        // a coprocess started under the same name replaces the previous one, whose descriptors can no longer be referred to
+        for number in self.vars.array(name) {
+            if let Ok(number) = number.parse() {
+                self.descriptors.close(number);
+            }
+        }
+        let read = self
+            .descriptors
+            .open(Descriptor::Input(Input::Pipe(output)));
+        let write = self
+            .descriptors
+            .open(Descriptor::Output(Output::Pipe(input)));
+        self.vars
+            .set_array(name, vec![read.to_string(), write.to_string()]);
+
+        let pid = child.id();
+        let id = self.jobs.add(&format!("coproc {source}"), child);
+        self.vars.set(&pid_name, pid.to_string());
+        self.vars.set("!", pid.to_string());
+        if self.interactive {
+            writeln!(self.streams.stderr, "[{id}] {pid}")?;
+        }
+        Ok(0)
+    }
+
+    /// Parses and executes `input`
+    pub fn execute(&mut self, input: &str) -> Result<u32, ShellError> {
+        let list = Parser::new(input).parse()?;
+        self.execute_items(&list, true)
+    }
+
+    /// Executes the script at `path` with the positional parameters set to `args`
+    pub fn execute_script(&mut self, path: &str, args: &[String]) -> Result<u32, ShellError> {
+        let script = std::fs::read_to_string(path)?;
+        self.vars.set_positional(path, args);
+        self.execute(&script)
+    }
+
+    /// Sets `$0` and the positional parameters seen by the commands executed afterwards
+    pub fn set_positional(&mut self, name: &str, args: &[String]) {
+        self.vars.set_positional(name, args);
+    }
+}
@@ -0,0 +1,840 @@
+//! Expands the words of a command into the arguments passed to it
+//!
+//! [`argv`] maps the words of a simple command to the arguments a program receives:
+//! - words are separated by unquoted blanks and a `#` at the start of a word starts a comment
+//! - unquoted braces are expanded first as described in [`braces`], so `a{b,c}` results in two arguments
+//! - `'...'` is taken as is, `"..."` is taken as is except for variables and the `\"`, `\$` and `\\` escapes,
+//!   and `\` outside of quotes takes the next character as is
+//! - `$name`, `${name}` and special parameters such as `$?` are replaced by their value, unset variables are empty,
+//!   `$(command)` and `<(command)` are rejected since expanding them means executing `command`
+//! - `${arr[i]}` is an element of the array `arr`, counting from the end if `i` is negative, `${#arr[@]}` is its length
+//!   and `${arr[@]}` is all of its elements, each one a field of its own even inside double quotes, as are the
+//!   positional parameters with `$@`, while `$*` and `${arr[*]}` join them with the first character of `IFS`
+//! - `${#name}` is the length of the value, `${name#pattern}` and `${name%pattern}` remove the shortest prefix or suffix
+//!   matching `pattern` (`##` and `%%` the longest) and `${name/pattern/replacement}` replaces the first match
+//!   (`//` every match, `/#` and `/%` a match at the start or end), patterns being those of [`pattern`]
+//! - the values of unquoted variables are split into several arguments on the characters of `IFS` (space, tab and line break by default),
+//!   an unquoted variable that is empty or unset results in no argument while `""` results in an empty one
+//!
+//! `tests/expansion-vectors.json` lists inputs along with the variables they are expanded with and the expected arguments,
+//! so other implementations can check that they agree with the shell, the shell itself is checked against them by the
+//! `vectors` tests
+
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use crate::{
+    braces,
+    lexer::{Lexer, Token, Word, WordPart},
+    pattern,
+    vars::Variables,
+};
+
+/// The value of `IFS` when it isn't set
+const DEFAULT_IFS: &str = " \t\n";
+
+/// Builds the fields a word expands to, splitting the results of unquoted expansions on `IFS`
+struct FieldBuilder<'i> {
+    ifs: &'i str,
+    fields: Vec<String>,
+    current: String,
+    /// Whether `current` is a field even if it is empty, which is the case for `""`
+    has_current: bool,
+    /// Whether the last field was ended by IFS whitespace,
+    /// in which case a following non-whitespace IFS character doesn't start an empty field
+    after_whitespace: bool,
+}
+
+impl<'i> FieldBuilder<'i> {
+    fn new(ifs: &'i str, capacity: usize) -> Self {
+        Self {
+            ifs,
+            fields: Vec::with_capacity(capacity),
+            current: String::new(),
+            has_current: false,
+            after_whitespace: false,
+        }
+    }
+
+    fn push_str(&mut self, str: &str) {
+        self.current.push_str(str);
+        self.has_current = true;
+        self.after_whitespace = false;
+    }
+
+    fn end_field(&mut self) {
+        self.fields.push(std::mem::take(&mut self.current));
+        self.has_current = false;
+    }
+
+    fn push_split(&mut self, str: &str) {
+        for c in str.chars() {
+            if !self.ifs.contains(c) {
+                self.current.push(c);
+                self.has_current = true;
+                self.after_whitespace = false;
+            } else if c.is_whitespace() {
+                if self.has_current {
+                    self.end_field();
+                    self.after_whitespace = true;
+                }
+            } else {
+                if self.has_current || !self.after_whitespace {
+                    self.end_field();
+                }
+                self.after_whitespace = false;
+            }
+        }
+    }
+
+    /// Ends the word being expanded, the next one starts a new field
+    fn end_word(&mut self) {
+        if self.has_current {
+            self.end_field();
+        }
+        self.after_whitespace = false;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.end_word();
+        self.fields
+    }
+}
+
+/// The outputs of the command substitutions of the words being expanded, in the order the substitutions appear
+pub type Substitutions = VecDeque<String>;
+
+/// Returns the index `subscript` refers to in an array of `len` elements,
+/// `subscript` is a number or a variable holding one, with negative numbers counting from the end
+fn array_index(subscript: &str, len: usize, vars: &Variables) -> Option<usize> {
+    let subscript = subscript.trim();
+    let index = match subscript.parse::<isize>() {
+        Ok(index) => index,
+        Err(_) => {
+            let name = subscript.strip_prefix('$').unwrap_or(subscript);
+            vars.get(name)?.trim().parse::<isize>().ok()?
+        }
+    };
+
+    if index < 0 {
+        len.checked_sub(index.unsigned_abs())
+    } else {
+        Some(index as usize)
+    }
+}
+
+/// Returns the length of the name of the parameter `expr` starts with, including an array subscript such as `[1]`
+fn name_len(expr: &str) -> usize {
+    let mut len = match expr.chars().next() {
+        Some('?' | '$' | '#' | '!' | '@' | '*' | '-') => 1,
+        _ => expr
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(expr.len()),
+    };
+    let subscript = expr[len..].strip_prefix('[');
+    if let Some(close) = subscript.and_then(|subscript| subscript.find(']')) {
+        len += close + 2;
+    }
+    len
+}
+
+fn is_parameter(expr: &str) -> bool {
+    !expr.is_empty() && name_len(expr) == expr.len()
+}
+
+/// Joins `elements` into a single value separated by the first character of `IFS`, as `$*` and `${arr[*]}` are
+fn join_fields(elements: &[String], vars: &Variables) -> String {
+    let ifs = vars.get("IFS");
+    let separator = ifs.as_deref().unwrap_or(DEFAULT_IFS).chars().next();
+    elements.join(&separator.map(String::from).unwrap_or_default())
+}
+
+/// Returns the values of the parameter `name`, several for `@` and `arr[@]` and a single one otherwise
+fn values(name: &str, vars: &Variables) -> Vec<String> {
+    let Some((array, subscript)) = name.strip_suffix(']').and_then(|name| name.split_once('['))
+    else {
+        // the positional parameters are the array `@`, see [`Variables::set_positional`]
+        return match name {
+            "@" => vars.array("@"),
+            "*" => vec![join_fields(&vars.array("@"), vars)],
+            name => vec![vars.get(name).unwrap_or_default().into_owned()],
+        };
+    };
+
+    let elements = vars.array(array);
+    match subscript {
+        "@" => elements,
+        "*" => vec![join_fields(&elements, vars)],
+        subscript => {
+            let element = array_index(subscript, elements.len(), vars)
+                .and_then(|index| elements.into_iter().nth(index));
+            vec![element.unwrap_or_default()]
+        }
+    }
+}
+
+/// Returns the value of the parameter `name`, which may be an element of an array such as `arr[1]`
+pub fn value(name: &str, vars: &Variables) -> String {
+    values(name, vars).join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplaceMode {
+    /// `${name/pattern/replacement}`
+    First,
+    /// `${name//pattern/replacement}`
+    All,
+    /// `${name/#pattern/replacement}`, only matches at the start of the value
+    Prefix,
+    /// `${name/%pattern/replacement}`, only matches at the end of the value
+    Suffix,
+}
+
+/// An operation applied to the value of a parameter, its pattern and replacement are expanded when it is applied
+#[derive(Debug, Clone, Copy)]
+enum Operation<'a> {
+    /// `${name#pattern}` and `${name##pattern}`, removes the shortest or the longest prefix matching `pattern`
+    RemovePrefix { pattern: &'a str, longest: bool },
+    /// `${name%pattern}` and `${name%%pattern}`, removes the shortest or the longest suffix matching `pattern`
+    RemoveSuffix { pattern: &'a str, longest: bool },
+    /// `${name/pattern/replacement}`, replaces the longest match of `pattern`
+    Replace {
+        pattern: &'a str,
+        replacement: &'a str,
+        mode: ReplaceMode,
+    },
+}
+
+/// Returns the offsets in `value` a match can start or end at
+fn boundaries(value: &str) -> Vec<usize> {
+    value
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([value.len()])
+        .collect()
+}
+
+fn replace(value: &str, pattern: &str, replacement: &str, mode: ReplaceMode) -> String {
+    let boundaries = boundaries(value);
+    // empty matches are only replaced at the start or the end, so `${name/#/prefix}` prepends `prefix`
+    let longest_match = |start: usize| {
+        boundaries
+            .iter()
+            .rev()
+            .copied()
+            .take_while(|&end| end > start)
+            .find(|&end| pattern::matches(pattern, &value[start..end]))
+    };
+
+    match mode {
+        ReplaceMode::Prefix => {
+            match longest_match(0).or_else(|| pattern::matches(pattern, "").then_some(0)) {
+                Some(end) => format!("{replacement}{}", &value[end..]),
+                None => value.to_string(),
+            }
+        }
+        ReplaceMode::Suffix => {
+            let start = boundaries
+                .iter()
+                .copied()
+                .find(|&start| pattern::matches(pattern, &value[start..]));
+            match start {
+                Some(start) => format!("{}{replacement}", &value[..start]),
+                None => value.to_string(),
+            }
+        }
+        ReplaceMode::First | ReplaceMode::All => {
+            let mut results = String::new();
+            let mut replaced = false;
+            let mut i = 0;
+            while let Some(c) = value[i..].chars().next() {
+                let end = longest_match(i).filter(|_| mode == ReplaceMode::All || !replaced);
+                match end {
+                    Some(end) => {
+                        results.push_str(replacement);
+                        replaced = true;
+                        i = end;
+                    }
+                    None => {
+                        results.push(c);
+                        i += c.len_utf8();
+                    }
+                }
+            }
+            results
+        }
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Parses the operation following the name of a parameter, `None` if it isn't one
+    fn parse(operation: &'a str) -> Option<Self> {
+        let (first, rest) = operation.split_at_checked(1)?;
+        let doubled = rest.starts_with(first);
+        let pattern = if doubled { &rest[1..] } else { rest };
+
+        match first {
+            "#" => Some(Self::RemovePrefix {
+                pattern,
+                longest: doubled,
+            }),
+            "%" => Some(Self::RemoveSuffix {
+                pattern,
+                longest: doubled,
+            }),
+            "/" => {
+                let (mode, rest) = match rest.split_at_checked(1) {
+                    Some(("/", rest)) => (ReplaceMode::All, rest),
+                    Some(("#", rest)) => (ReplaceMode::Prefix, rest),
+                    Some(("%", rest)) => (ReplaceMode::Suffix, rest),
+                    _ => (ReplaceMode::First, rest),
+                };
+                let (pattern, replacement) = rest.split_once('/').unwrap_or((rest, ""));
+                Some(Self::Replace {
+                    pattern,
+                    replacement,
+                    mode,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn apply(&self, value: &str, vars: &Variables) -> String {
+        // command substitutions can't appear in the operands since they aren't executed before expanding
+        let expand_operand =
+            |operand| expand_pattern(&Lexer::word(operand), vars, &mut Substitutions::new());
+
+        match *self {
+            Operation::RemovePrefix { pattern, longest } => {
+                let pattern = expand_operand(pattern);
+                let mut ends = boundaries(value)
+                    .into_iter()
+                    .filter(|&end| pattern::matches(&pattern, &value[..end]));
+                let end = if longest {
+                    ends.next_back()
+                } else {
+                    ends.next()
+                };
+                value[end.unwrap_or(0)..].to_string()
+            }
+            Operation::RemoveSuffix { pattern, longest } => {
+                let pattern = expand_operand(pattern);
+                let mut starts = boundaries(value)
+                    .into_iter()
+                    .filter(|&start| pattern::matches(&pattern, &value[start..]));
+                let start = if longest {
+                    starts.next()
+                } else {
+                    starts.next_back()
+                };
+                value[..start.unwrap_or(value.len())].to_string()
+            }
+            Operation::Replace {
+                pattern,
+                replacement,
+                mode,
+            } => {
+                let pattern = expand_operand(pattern);
+                let replacement =
+                    expand_string(&Lexer::word(replacement), vars, &mut Substitutions::new());
+                replace(value, &pattern, &replacement, mode)
+            }
+        }
+    }
+}
+
+/// Returns the values `${expr}` expands to, several for `${arr[@]}` and a single one otherwise
+fn parameter(expr: &str, vars: &Variables) -> Vec<String> {
+    // `${#name}` is the length of the value, `${#arr[@]}` the number of elements of the array
+    if let Some(name) = expr.strip_prefix('#').filter(|name| is_parameter(name)) {
+        let length = match name
+            .strip_suffix("[@]")
+            .or_else(|| name.strip_suffix("[*]"))
+        {
+            Some(array) => vars.array(array).len(),
+            None => values(name, vars).concat().chars().count(),
+        };
+        return vec![length.to_string()];
+    }
+
+    let (name, operation) = expr.split_at(name_len(expr));
+    if operation.is_empty() {
+        return values(name, vars);
+    }
+    match Operation::parse(operation) {
+        // applies to each element of `${arr[@]}`
+        Some(operation) => values(name, vars)
+            .iter()
+            .map(|value| operation.apply(value, vars))
+            .collect(),
+        None => vec![vars.get(expr).unwrap_or_default().into_owned()],
+    }
+}
+
+/// Returns the values of a parameter or the output of a command substitution and whether it was quoted,
+/// `None` for literal text
+fn expansion(
+    part: &WordPart,
+    vars: &Variables,
+    outputs: &mut Substitutions,
+) -> Option<(Vec<String>, bool)> {
+    match part {
+        WordPart::Literal(_) | WordPart::Quoted(_) => None,
+        WordPart::Var { name, quoted } => Some((parameter(name, vars), *quoted)),
+        WordPart::Command { quoted, .. } => {
+            Some((vec![outputs.pop_front().unwrap_or_default()], *quoted))
+        }
+        // the path of a process substitution is never split
+        WordPart::Process { .. } => Some((vec![outputs.pop_front().unwrap_or_default()], true)),
+    }
+}
+
+/// Expands `word` into zero or more fields,
+/// unquoted variables and command substitutions are split on the characters of `IFS` while everything else is kept as is
+pub fn expand_word(word: &Word, vars: &Variables, outputs: &mut Substitutions) -> Vec<String> {
+    let ifs = vars.get("IFS");
+    let mut builder = FieldBuilder::new(ifs.as_deref().unwrap_or(DEFAULT_IFS), 1);
+    push_word(&mut builder, word, vars, outputs);
+    builder.finish()
+}
+
+/// Expands `word` into the fields of `builder` which may already hold those of the previous words
+fn push_word(
+    builder: &mut FieldBuilder,
+    word: &Word,
+    vars: &Variables,
+    outputs: &mut Substitutions,
+) {
+    for part in &word.parts {
+        let (values, quoted) = match (part, expansion(part, vars, outputs)) {
+            (WordPart::Literal(str) | WordPart::Quoted(str), _) => {
+                builder.push_str(str);
+                continue;
+            }
+            (_, Some(expanded)) => expanded,
+            (_, None) => continue,
+        };
+
+        // the values of `${arr[@]}` are separate fields
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 && (quoted || builder.has_current) {
+                builder.end_field();
+            }
+            if quoted {
+                builder.push_str(value);
+            } else {
+                builder.push_split(value);
+            }
+        }
+    }
+    builder.end_word();
+}
+
+#[derive(Debug, Error)]
+pub enum ArgvError {
+    #[error("`{0}` isn't allowed in a simple command")]
+    Operator(&'static str),
+    #[error("command substitutions can't be expanded without a shell")]
+    CommandSubstitution,
+    #[error("process substitutions can't be expanded without a shell")]
+    ProcessSubstitution,
+}
+
+/// Expands the words of the simple command `command` into the arguments a program receives as described in the [module documentation](self),
+/// only depends on `vars` if they are [`Variables::isolated`]
+pub fn argv(command: &str, vars: &Variables) -> Result<Vec<String>, ArgvError> {
+    let ifs = vars.get("IFS");
+    let mut argv = FieldBuilder::new(ifs.as_deref().unwrap_or(DEFAULT_IFS), 0);
+    let mut outputs = Substitutions::new();
+    for token in Lexer::new(command) {
+        match token {
+            Token::Word(word) => {
+                for part in &word.parts {
+                    match part {
+                        WordPart::Command { .. } => return Err(ArgvError::CommandSubstitution),
+                        WordPart::Process { .. } => return Err(ArgvError::ProcessSubstitution),
+                        _ => {}
+                    }
+                }
+                match braces::expand(word.raw) {
+                    Some(words) => {
+                        for word in &words {
+                            push_word(&mut argv, &Lexer::word(word), vars, &mut outputs);
+                        }
+                    }
+                    None => push_word(&mut argv, &word, vars, &mut outputs),
+                }
+            }
+            Token::Op(op) => return Err(ArgvError::Operator(op.as_str())),
+        }
+    }
+    Ok(argv.finish())
+}
+
+/// Expands all of `words` into a list of arguments
+pub fn expand_words(words: &[Word], vars: &Variables, outputs: &mut Substitutions) -> Vec<String> {
+    // `IFS` is looked up once and the fields of all the words go in a single vector, which is the command's argv
+    let ifs = vars.get("IFS");
+    let mut builder = FieldBuilder::new(ifs.as_deref().unwrap_or(DEFAULT_IFS), words.len());
+    for word in words {
+        push_word(&mut builder, word, vars, outputs);
+    }
+    builder.finish()
+}
+
+/// Expands `word` into a single string without field splitting,
+/// used where only one value makes sense such as assignments and redirection targets
+pub fn expand_string(word: &Word, vars: &Variables, outputs: &mut Substitutions) -> String {
+    let mut results = String::new();
+    for part in &word.parts {
+        match (part, expansion(part, vars, outputs)) {
+            (WordPart::Literal(str) | WordPart::Quoted(str), _) => results.push_str(str),
+            (_, Some((values, _))) => results.push_str(&values.join(" ")),
+            (_, None) => {}
+        }
+    }
+    results
+}
+
+/// Pushes `str` to `pattern` with the characters special to patterns escaped
+fn push_escaped(pattern: &mut String, str: &str) {
+    for c in str.chars() {
+        if pattern::SPECIAL.contains(&c) {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+}
+
+/// Expands `word` into a [`pattern`], quoted text and quoted expansions are escaped so they only match themselves
+pub fn expand_pattern(word: &Word, vars: &Variables, outputs: &mut Substitutions) -> String {
+    let mut results = String::new();
+    for part in &word.parts {
+        match (part, expansion(part, vars, outputs)) {
+            (WordPart::Literal(str), _) => results.push_str(str),
+            (WordPart::Quoted(str), _) => push_escaped(&mut results, str),
+            (_, Some((values, true))) => push_escaped(&mut results, &values.join(" ")),
+            (_, Some((values, false))) => results.push_str(&values.join(" ")),
+            (_, None) => {}
+        }
+    }
+    results
+}
+
+/// Quotes `field` if needed so the shell reads it back as a single word with the same value
+pub fn quote(field: &str) -> String {
+    let is_plain = |c: char| c.is_alphanumeric() || "_-+=/.,:@%^".contains(c);
+    if !field.is_empty() && field.chars().all(is_plain) {
+        return field.to_string();
+    }
+    format!("'{}'", field.replace('\'', r"'\''"))
+}
+
+/// Replaces the history designators of `line` outside of single quotes by the commands of `history` they refer to:
+/// `!!` is the last command, `!n` the `n`th one as numbered by `history` and `!-n` the `n`th one from the end,
+/// designators referring to no command are kept as is
+pub fn expand_designators(line: &str, history: &[&str]) -> String {
+    let mut results = String::new();
+    let mut chars = line.char_indices().peekable();
+    let (mut quoted, mut escaped) = (false, false);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if !quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            '!' if !quoted => {
+                let rest = &line[i + 1..];
+                let (index, len) = if rest.starts_with('!') {
+                    (history.len().checked_sub(1), 1)
+                } else {
+                    let from_end = rest.starts_with('-');
+                    let digits = rest[from_end as usize..]
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .count();
+                    let n = rest[from_end as usize..from_end as usize + digits]
+                        .parse::<usize>()
+                        .ok();
+                    let index = match from_end {
+                        true => n.and_then(|n| history.len().checked_sub(n)),
+                        false => n.and_then(|n| n.checked_sub(1)),
+                    };
+                    (index, from_end as usize + digits)
+                };
+
+                if let Some(command) = index.and_then(|index| history.get(index)) {
+                    results.push_str(command);
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        results.push(c);
+    }
+    results
+}
+
+/// Returns the fields `word` results in once its braces, variables and globs are expanded,
+/// `None` if it has none of them
+fn expand_line_word(word: &Word, vars: &Variables) -> Option<Vec<String>> {
+    let braced = braces::expand(word.raw);
+    let words = match &braced {
+        Some(words) => words.iter().map(|word| Lexer::word(word)).collect(),
+        None => vec![word.clone()],
+    };
+
+    let mut expanded = braced.is_some();
+    let mut fields = Vec::new();
+    for word in &words {
+        let has_vars = word
+            .parts
+            .iter()
+            .any(|part| matches!(part, WordPart::Var { .. }));
+        let has_wildcards = word.parts.iter().any(|part| match part {
+            WordPart::Literal(str) => pattern::has_wildcards(str),
+            _ => false,
+        });
+
+        if has_wildcards {
+            let paths = pattern::glob(&expand_pattern(word, vars, &mut Substitutions::new()));
+            if !paths.is_empty() {
+                expanded = true;
+                fields.extend(paths);
+                continue;
+            }
+        }
+        expanded |= has_vars;
+        fields.extend(expand_word(word, vars, &mut Substitutions::new()));
+    }
+    expanded.then_some(fields)
+}
+
+/// Expands the history designators of `line` as [`expand_designators`] does, then replaces the words with braces,
+/// variables or globs matching files by the arguments they expand to, quoted so the line runs the same,
+/// words with command substitutions are kept as is since expanding them would execute them
+pub fn expand_line(line: &str, vars: &Variables, history: &[&str]) -> String {
+    let line = expand_designators(line, history);
+    let mut results = String::new();
+    let mut copied = 0;
+
+    for token in Lexer::new(&line) {
+        let Token::Word(word) = token else {
+            continue;
+        };
+        let has_commands = word
+            .parts
+            .iter()
+            .any(|part| matches!(part, WordPart::Command { .. } | WordPart::Process { .. }));
+        if has_commands {
+            continue;
+        }
+        let Some(fields) = expand_line_word(&word, vars) else {
+            continue;
+        };
+
+        let start = word.raw.as_ptr() as usize - line.as_ptr() as usize;
+        results.push_str(&line[copied..start]);
+        results.push_str(
+            &fields
+                .iter()
+                .map(|field| quote(field))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        copied = start + word.raw.len();
+    }
+
+    results.push_str(&line[copied..]);
+    results
+}
+
+/// Checks the shell agrees with `tests/expansion-vectors.json`
+#[cfg(test)]
+mod vectors {
+    use super::argv;
+    use crate::{json::Value, vars::Variables};
+
+    #[test]
+    fn expansion_vectors() {
+        let vectors = Value::parse(include_str!("../tests/expansion-vectors.json"))
+            .expect("the vectors aren't valid JSON");
+        let vectors = vectors
+            .get("vectors")
+            .and_then(Value::as_array)
+            .expect("the vectors are missing");
+
+        for vector in vectors {
+            let field = |name| vector.get(name).expect("a field of the vector is missing");
+            let description = field("description").as_str().unwrap();
+            let input = field("input").as_str().unwrap();
+            let Value::Object(vars) = field("vars") else {
+                panic!("{description}: `vars` isn't an object");
+            };
+            let vars = vars
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str().unwrap()));
+            let expected = field("argv")
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|arg| arg.as_str().unwrap())
+                .collect::<Vec<_>>();
+
+            let got = argv(input, &Variables::isolated(vars))
+                .unwrap_or_else(|err| panic!("{description}: {err}"));
+            assert_eq!(got, expected, "{description}: `{input}`");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArgvError, Substitutions, argv, expand_pattern, expand_string, quote};
+    use crate::{lexer::Lexer, vars::Variables};
+
+    fn vars() -> Variables {
+        let mut vars = Variables::isolated([("v", "a  b"), ("IFS", " ")]);
+        vars.set_array("arr", ["x y", "", "z"].map(String::from).to_vec());
+        vars.set_positional("name", &["p q", "r"].map(String::from));
+        vars
+    }
+
+    #[test]
+    fn arrays() {
+        let vars = vars();
+        assert_eq!(argv("\"${arr[@]}\"", &vars).unwrap(), ["x y", "", "z"]);
+        assert_eq!(argv("${arr[@]}", &vars).unwrap(), ["x", "y", "z"]);
+        assert_eq!(argv("\"${arr[*]}\"", &vars).unwrap(), ["x y  z"]);
+        assert_eq!(
+            argv("${arr[-1]} ${#arr[@]} $arr", &vars).unwrap(),
+            ["z", "3", "x", "y"]
+        );
+    }
+
+    #[test]
+    fn positional() {
+        let vars = vars();
+        assert_eq!(argv("\"$@\"", &vars).unwrap(), ["p q", "r"]);
+        assert_eq!(argv("x\"$@\"y", &vars).unwrap(), ["xp q", "ry"]);
+        assert_eq!(
+            argv("\"$*\" $# $0 $1", &vars).unwrap(),
+            ["p q r", "2", "name", "p", "q"]
+        );
+    }
+
+    #[test]
+    fn argv_errors() {
+        let vars = vars();
+        assert!(matches!(
+            argv("a | b", &vars),
+            Err(ArgvError::Operator("|"))
+        ));
+        assert!(matches!(
+            argv("$(a)", &vars),
+            Err(ArgvError::CommandSubstitution)
+        ));
+        assert!(matches!(
+            argv("<(a)", &vars),
+            Err(ArgvError::ProcessSubstitution)
+        ));
+    }
+
+    #[test]
+    fn patterns() {
+        let vars = Variables::isolated([("star", "*")]);
+        let pattern = |word| expand_pattern(&Lexer::word(word), &vars, &mut Substitutions::new());
+        assert_eq!(pattern("a*'?'"), "a*\\?");
+        assert_eq!(pattern("$star\"$star\""), "*\\*");
+    }
+
+    #[test]
+    fn strings() {
+        let vars = vars();
+        let string = |word| expand_string(&Lexer::word(word), &vars, &mut Substitutions::new());
+        assert_eq!(string("$v-'$v'"), "a  b-$v");
+    }
+
+    #[test]
+    fn quoting() {
+        let vars = Variables::new();
+        for field in ["plain/path.rs", "", "a b", "it's", "$v", "*"] {
+            assert_eq!(argv(&quote(field), &vars).unwrap(), [field], "{field}");
+        }
+        assert_eq!(quote("plain/path.rs"), "plain/path.rs");
+    }
+}
+
+/// Checks each kind of expansion Alt+E does
+#[cfg(test)]
+mod line {
+    use super::{expand_designators, expand_line};
+    use crate::vars::Variables;
+
+    #[test]
+    fn variables() {
+        let vars = Variables::isolated([("name", "a b"), ("empty", "")]);
+        assert_eq!(
+            expand_line("echo $name \"$name\"", &vars, &[]),
+            "echo a b 'a b'"
+        );
+        assert_eq!(
+            expand_line("echo $(echo $name)", &vars, &[]),
+            "echo $(echo $name)"
+        );
+    }
+
+    #[test]
+    fn braces() {
+        let vars = Variables::isolated([("x", "1")]);
+        assert_eq!(expand_line("echo a{b,c} d", &vars, &[]), "echo ab ac d");
+        assert_eq!(expand_line("echo {$x,2}", &vars, &[]), "echo 1 2");
+    }
+
+    #[test]
+    fn history_designators() {
+        let history = ["ls", "cd src", "make"];
+        assert_eq!(expand_designators("sudo !!", &history), "sudo make");
+        assert_eq!(expand_designators("!1 -l", &history), "ls -l");
+        assert_eq!(expand_designators("!-2", &history), "cd src");
+        assert_eq!(
+            expand_designators("echo '!!' \\!! ! x", &history),
+            "echo '!!' \\!! ! x"
+        );
+        assert_eq!(expand_designators("!9", &history), "!9");
+        assert_eq!(expand_designators("!!", &[]), "!!");
+    }
+
+    #[test]
+    fn globs() {
+        let dir = std::env::temp_dir().join(format!("safa-glob-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.rs", "b.rs", "c.txt", ".hidden.rs", "sub/d.rs"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let dir_str = dir.to_str().unwrap();
+        let vars = Variables::isolated([("dir", dir_str)]);
+
+        assert_eq!(
+            expand_line("ls $dir/*.rs", &vars, &[]),
+            format!("ls {dir_str}/a.rs {dir_str}/b.rs")
+        );
+        assert_eq!(
+            expand_line("ls $dir/*/?.rs", &vars, &[]),
+            format!("ls {dir_str}/sub/d.rs")
+        );
+        // quoted wildcards and patterns matching nothing are kept
+        assert_eq!(
+            expand_line("ls \"$dir/*.rs\"", &vars, &[]),
+            format!("ls '{dir_str}/*.rs'")
+        );
+        assert_eq!(expand_line("ls *.none", &vars, &[]), "ls *.none");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
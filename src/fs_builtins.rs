@@ -0,0 +1,343 @@
+//! Minimal file utilities for SafaOS images that don't ship any, enabled by the `fs-builtins` feature
+//!
+//! They are only used when no program with the same name is found in `PATH`
+//!
+//! `rm`, `cp` and `mv` ask before deleting or overwriting files when given `-i`, or by default if `$SAFA_INTERACTIVE_RM`
+//! is set to `1` since SafaOS's ramdisk has no trash to recover them from, `-f` doesn't ask
+
+use std::{
+    fs::{self, File},
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use phf::phf_map;
+
+use crate::{
+    Shell, ShellError,
+    builtin::{self, BuiltInCommandList},
+    i18n::{self, Message},
+};
+
+pub static FS_BUILTINS: BuiltInCommandList = phf_map! {
+    "ls" => ls,
+    "cat" => cat,
+    "mkdir" => mkdir,
+    "rm" => rm,
+    "cp" => cp,
+    "mv" => mv,
+    "touch" => touch,
+};
+
+/// Splits `args` into the single character flags given before the operands and the operands,
+/// fails with the first flag that isn't in `allowed`
+fn split_flags<'a>(args: &'a [&'a str], allowed: &str) -> Result<(Vec<char>, &'a [&'a str]), char> {
+    let mut flags = Vec::new();
+    let mut operands = args;
+
+    while let Some((arg, rest)) = operands.split_first() {
+        if *arg == "--" {
+            operands = rest;
+            break;
+        }
+
+        let Some(arg_flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+            break;
+        };
+
+        for flag in arg_flags.chars() {
+            if !allowed.contains(flag) {
+                return Err(flag);
+            }
+            flags.push(flag);
+        }
+        operands = rest;
+    }
+
+    Ok((flags, operands))
+}
+
+/// Runs `f` on every operand of the builtin `name`, reporting failures to stderr without stopping,
+/// fails if any of them failed
+fn for_each(
+    shell: &mut Shell,
+    name: &str,
+    operands: &[&str],
+    mut f: impl FnMut(&mut Shell, &str) -> io::Result<()>,
+) -> Result<(), ShellError> {
+    let mut failed = false;
+    for operand in operands {
+        if let Err(err) = f(shell, operand) {
+            writeln!(shell.streams.stderr, "{name}: {operand}: {err}")?;
+            failed = true;
+        }
+    }
+
+    if failed {
+        Err(ShellError::BuiltinError)
+    } else {
+        Ok(())
+    }
+}
+
+/// Parses the flags of the builtin `name`, printing its usage if an unknown flag was given
+fn parse_flags<'a>(
+    shell: &mut Shell,
+    name: &str,
+    usage: &str,
+    args: &'a [&'a str],
+    allowed: &str,
+) -> Result<(Vec<char>, &'a [&'a str]), ShellError> {
+    split_flags(args, allowed).or_else(|flag| {
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            i18n::format(Message::UnknownFlag, &[&name, &flag])
+        )?;
+        writeln!(
+            shell.streams.stderr,
+            "{}",
+            builtin::usage(&format!("{name} {usage}"))
+        )?;
+        Err(ShellError::BuiltinError)
+    })
+}
+
+/// Returns whether to ask before deleting or overwriting files, the last of `-i` and `-f` wins
+fn is_interactive(shell: &Shell, flags: &[char]) -> bool {
+    match flags.iter().rev().find(|flag| matches!(flag, 'i' | 'f')) {
+        Some(flag) => *flag == 'i',
+        None => shell
+            .vars
+            .get("SAFA_INTERACTIVE_RM")
+            .is_some_and(|interactive| interactive == "1"),
+    }
+}
+
+/// Asks `question` and reads the answer from stdin, returns whether it is yes
+fn confirm(shell: &mut Shell, question: &str) -> io::Result<bool> {
+    write!(shell.streams.stderr, "{question} [y/N] ")?;
+    shell.streams.stderr.flush()?;
+
+    // the terminal is in raw mode in interactive shells, so the answer ends with a carriage return and isn't echoed
+    let mut answer = Vec::new();
+    let mut byte = [0];
+    while shell.streams.stdin.read(&mut byte)? == 1 && !matches!(byte[0], b'\n' | b'\r') {
+        answer.push(byte[0]);
+    }
+    let confirmed = matches!(answer.trim_ascii_start().first(), Some(b'y' | b'Y'));
+
+    let line_break = if io::stdin().is_terminal() {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let echo = if confirmed { "y" } else { "n" };
+    write!(shell.streams.stderr, "{echo}{line_break}")?;
+    Ok(confirmed)
+}
+
+/// Asks whether to overwrite `to` if it exists and the builtin `name` is interactive, returns whether to go on
+fn confirm_overwrite(
+    shell: &mut Shell,
+    name: &str,
+    to: &Path,
+    interactive: bool,
+) -> io::Result<bool> {
+    if !interactive || fs::symlink_metadata(to).is_err() {
+        return Ok(true);
+    }
+    confirm(shell, &format!("{name}: overwrite '{}'?", to.display()))
+}
+
+fn ls(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (flags, operands) = parse_flags(shell, "ls", "[-a] [paths...]", args, "a")?;
+    let all = flags.contains(&'a');
+    let operands = if operands.is_empty() {
+        &["."]
+    } else {
+        operands
+    };
+    let show_headers = operands.len() > 1;
+
+    for_each(shell, "ls", operands, |shell, operand| {
+        let path = Path::new(operand);
+        if !path.is_dir() {
+            fs::metadata(path)?;
+            return writeln!(shell.streams.stdout, "{operand}");
+        }
+
+        let mut names = fs::read_dir(path)?
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    name + "/"
+                } else {
+                    name
+                }
+            })
+            .filter(|name| all || !name.starts_with('.'))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        if show_headers {
+            writeln!(shell.streams.stdout, "{operand}:")?;
+        }
+        for name in names {
+            writeln!(shell.streams.stdout, "{name}")?;
+        }
+        Ok(())
+    })
+}
+
+fn cat(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let operands = if args.is_empty() { &["-"] } else { args };
+
+    for_each(shell, "cat", operands, |shell, operand| {
+        let mut contents = Vec::new();
+        if operand == "-" {
+            shell.streams.stdin.read_to_end(&mut contents)?;
+        } else {
+            File::open(operand)?.read_to_end(&mut contents)?;
+        }
+        shell.streams.stdout.write_all(&contents)?;
+        shell.streams.stdout.flush()
+    })
+}
+
+fn mkdir(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (flags, operands) = parse_flags(shell, "mkdir", "[-p] <dirs...>", args, "p")?;
+    let parents = flags.contains(&'p');
+
+    for_each(shell, "mkdir", operands, |_, operand| {
+        if parents {
+            fs::create_dir_all(operand)
+        } else {
+            fs::create_dir(operand)
+        }
+    })
+}
+
+fn rm(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (flags, operands) = parse_flags(shell, "rm", "[-rfi] <paths...>", args, "rRfi")?;
+    let recursive = flags.contains(&'r') || flags.contains(&'R');
+    let force = flags.contains(&'f');
+    let interactive = is_interactive(shell, &flags);
+
+    for_each(shell, "rm", operands, |shell, operand| {
+        let metadata = fs::symlink_metadata(operand);
+        if let (true, Ok(metadata)) = (interactive, &metadata) {
+            let question = if metadata.is_dir() && recursive {
+                format!("rm: remove directory '{operand}' and its contents?")
+            } else {
+                format!("rm: remove '{operand}'?")
+            };
+            if !confirm(shell, &question)? {
+                return Ok(());
+            }
+        }
+
+        let results = match metadata {
+            Ok(metadata) if metadata.is_dir() && recursive => fs::remove_dir_all(operand),
+            Ok(metadata) if metadata.is_dir() => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "is a directory, use `-r` to remove it",
+            )),
+            Ok(_) => fs::remove_file(operand),
+            Err(err) => Err(err),
+        };
+
+        match results {
+            Err(err) if force && err.kind() == io::ErrorKind::NotFound => Ok(()),
+            results => results,
+        }
+    })
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Returns where `source` ends up when copied or moved to `dest`, inside of it if it is a directory
+fn destination(source: &str, dest: &str) -> PathBuf {
+    let dest = Path::new(dest);
+    match Path::new(source).file_name() {
+        Some(name) if dest.is_dir() => dest.join(name),
+        _ => dest.to_path_buf(),
+    }
+}
+
+/// Splits the operands of `cp` and `mv` into the sources and the destination, printing the usage if there aren't enough
+fn sources_and_dest<'a>(
+    shell: &mut Shell,
+    name: &str,
+    usage: &str,
+    operands: &'a [&'a str],
+) -> Result<(&'a [&'a str], &'a str), ShellError> {
+    match operands.split_last() {
+        Some((dest, sources)) if !sources.is_empty() => Ok((sources, dest)),
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                builtin::usage(&format!("{name} {usage}"))
+            )?;
+            Err(ShellError::BuiltinError)
+        }
+    }
+}
+
+fn cp(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let usage = "[-rfi] <sources...> <dest>";
+    let (flags, operands) = parse_flags(shell, "cp", usage, args, "rRfi")?;
+    let recursive = flags.contains(&'r') || flags.contains(&'R');
+    let interactive = is_interactive(shell, &flags);
+    let (sources, dest) = sources_and_dest(shell, "cp", usage, operands)?;
+
+    for_each(shell, "cp", sources, |shell, source| {
+        let to = destination(source, dest);
+        if Path::new(source).is_dir() && !recursive {
+            return Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "is a directory, use `-r` to copy it",
+            ));
+        }
+        if !confirm_overwrite(shell, "cp", &to, interactive)? {
+            return Ok(());
+        }
+        copy_recursive(Path::new(source), &to)
+    })
+}
+
+fn mv(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let usage = "[-fi] <sources...> <dest>";
+    let (flags, operands) = parse_flags(shell, "mv", usage, args, "fi")?;
+    let interactive = is_interactive(shell, &flags);
+    let (sources, dest) = sources_and_dest(shell, "mv", usage, operands)?;
+
+    for_each(shell, "mv", sources, |shell, source| {
+        let to = destination(source, dest);
+        if !confirm_overwrite(shell, "mv", &to, interactive)? {
+            return Ok(());
+        }
+        fs::rename(source, to)
+    })
+}
+
+fn touch(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    for_each(shell, "touch", args, |_, operand| {
+        let file = File::options().create(true).append(true).open(operand)?;
+        file.set_modified(SystemTime::now())
+    })
+}
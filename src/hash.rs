@@ -0,0 +1,113 @@
+//! Remembers where commands were found in `PATH` so running them again doesn't search it,
+//! which is slow on SafaOS's ramdisk
+//!
+//! The entries are forgotten when `PATH` changes, and with `set -o autorehash` when a program is added to
+//! or removed from one of its directories, so newly installed programs are found without running `hash -r`
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::MULTI_PATH_SEP;
+
+/// What changes when programs are added to or removed from a directory
+#[derive(Debug, PartialEq, Eq)]
+enum DirState {
+    Modified(SystemTime),
+    /// The number of entries, for systems that don't record when directories are modified such as SafaOS
+    Entries(usize),
+    Missing,
+}
+
+impl DirState {
+    fn of(dir: &str) -> Self {
+        let Ok(metadata) = std::fs::metadata(dir) else {
+            return DirState::Missing;
+        };
+        match metadata.modified() {
+            Ok(modified) => DirState::Modified(modified),
+            Err(_) => std::fs::read_dir(dir).map_or(DirState::Missing, |entries| {
+                DirState::Entries(entries.count())
+            }),
+        }
+    }
+}
+
+/// Returns where `program` is in `search_path`, searching it every time
+pub fn search(program: &str, search_path: &str) -> Option<PathBuf> {
+    search_path
+        .split(MULTI_PATH_SEP)
+        .map(|dir| Path::new(dir).join(program))
+        .find(|path| path.exists())
+}
+
+#[derive(Debug, Default)]
+pub struct CommandHash {
+    /// The search path the entries were found in, they are forgotten when it changes
+    search_path: String,
+    entries: BTreeMap<String, PathBuf>,
+    /// Whether the entries are also forgotten when the directories of the search path change
+    autorehash: bool,
+    /// The state of each directory of the search path when the entries started being remembered
+    dirs: Vec<DirState>,
+}
+
+impl CommandHash {
+    /// Returns where `program` is in `search_path`, only searching it the first time `program` is looked up
+    pub fn lookup(&mut self, program: &str, search_path: &str) -> Option<PathBuf> {
+        self.forget_if_changed(search_path);
+
+        // a remembered program may have been removed since
+        if let Some(path) = self.entries.get(program) {
+            if path.exists() {
+                return Some(path.clone());
+            }
+            self.entries.remove(program);
+        }
+
+        let path = search(program, search_path)?;
+        self.entries.insert(program.to_string(), path.clone());
+        Some(path)
+    }
+
+    /// Sets whether the entries are forgotten when a directory of the search path changes
+    pub fn set_autorehash(&mut self, autorehash: bool) {
+        if autorehash && !self.autorehash {
+            self.clear();
+        }
+        self.autorehash = autorehash;
+    }
+
+    fn dir_states(&self) -> Vec<DirState> {
+        self.search_path
+            .split(MULTI_PATH_SEP)
+            .map(DirState::of)
+            .collect()
+    }
+
+    fn forget_if_changed(&mut self, search_path: &str) {
+        if self.search_path != search_path {
+            self.search_path = search_path.to_string();
+            self.clear();
+        } else if self.autorehash && self.dirs != self.dir_states() {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if self.autorehash {
+            self.dirs = self.dir_states();
+        }
+    }
+
+    /// Returns the remembered programs that are still valid for `search_path`
+    pub fn entries(&mut self, search_path: &str) -> impl Iterator<Item = (&str, &Path)> {
+        self.forget_if_changed(search_path);
+        self.entries
+            .iter()
+            .map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+}
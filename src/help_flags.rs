@@ -0,0 +1,121 @@
+//! Flag completion for programs without completion definitions, learned by parsing their `--help` output when
+//! `$SAFA_HELP_COMPLETION` is set to `1`
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    process::{Command, Stdio},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::vars::Variables;
+
+/// How long a program may take to print its help before it is killed
+const HELP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The flags learned so far for each program, programs are only asked once per session
+fn cache() -> &'static Mutex<HashMap<String, Vec<String>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Vec<String>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Whether learning flags from `--help` is enabled, which takes setting `$SAFA_HELP_COMPLETION` to `1`
+/// as it runs the programs being completed
+pub fn enabled(vars: &Variables) -> bool {
+    vars.get("SAFA_HELP_COMPLETION")
+        .is_some_and(|value| value == "1")
+}
+
+/// Extracts the flags documented at the start of the lines of `help`,
+/// such as `-a` and `--all` in `  -a, --all  do not ignore entries starting with .`
+pub fn parse_flags(help: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+
+    for line in help.lines() {
+        let mut rest = line.trim_start();
+        while rest.starts_with('-') {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, ',' | '=' | '[' | '<'))
+                .unwrap_or(rest.len());
+            let flag = &rest[..end];
+
+            let name = flag.trim_start_matches('-');
+            if !name.is_empty()
+                && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !flags.iter().any(|known| known == flag)
+            {
+                flags.push(flag.to_string());
+            }
+
+            // `-a, --all` and `-o FILE, --output=FILE` document several spellings of a flag
+            let Some(next) = rest[end..].find(", -") else {
+                break;
+            };
+            rest = &rest[end + next + 2..];
+        }
+    }
+
+    flags
+}
+
+/// Runs `program --help`, returns what it printed or `None` if it didn't finish in time
+fn run_help(program: &str) -> Option<String> {
+    let mut child = Command::new(program)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let mut stdout = child.stdout.take()?;
+    let mut stderr = child.stderr.take()?;
+    let readers = [
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            _ = stdout.read_to_string(&mut output);
+            output
+        }),
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            _ = stderr.read_to_string(&mut output);
+            output
+        }),
+    ];
+
+    let deadline = Instant::now() + HELP_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            _ = child.kill();
+            _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // some programs print their help to stderr
+    let output = readers
+        .into_iter()
+        .filter_map(|reader| reader.join().ok())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(output)
+}
+
+/// Returns the flags of `program` starting with `prefix`
+pub fn complete(program: &str, prefix: &str) -> Vec<String> {
+    let mut cache = cache().lock().unwrap();
+    let flags = cache
+        .entry(program.to_string())
+        .or_insert_with(|| run_help(program).map_or(Vec::new(), |help| parse_flags(&help)));
+
+    let mut candidates = flags
+        .iter()
+        .filter(|flag| flag.starts_with(prefix))
+        .cloned()
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,190 @@
+//! Colors the line being edited: command names by whether they exist, strings and variables
+
+use std::fmt::Write;
+
+use crate::{
+    colors::{Colors, Role},
+    lexer::{Lexer, Operator, Token, Word, WordPart},
+};
+
+/// Words that start or end a compound command rather than naming a command
+const KEYWORDS: &[&str] = &["{", "}", "case", "esac", "in", "coproc"];
+
+fn default_color(role: Role) -> &'static str {
+    match role {
+        Role::Command => "32",
+        Role::UnknownCommand => "31",
+        Role::String => "33",
+        Role::Variable => "36",
+        _ => "",
+    }
+}
+
+/// Returns whether `word` is a `NAME=value` assignment given before a command
+fn is_assignment(word: &Word) -> bool {
+    match word
+        .as_literal()
+        .and_then(|literal| literal.split_once('='))
+    {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
+
+/// Returns the length of the quoted string starting with the quote at the start of `raw`, including the quotes
+fn quoted_len(raw: &str) -> usize {
+    let bytes = raw.as_bytes();
+    let quote = bytes[0];
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quote == b'"' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    raw.len()
+}
+
+/// Highlights the line, one color per byte of the line
+struct Highlighter<'a> {
+    line: &'a str,
+    roles: Vec<Option<Role>>,
+}
+
+impl<'a> Highlighter<'a> {
+    fn offset_of(&self, slice: &str) -> usize {
+        slice.as_ptr() as usize - self.line.as_ptr() as usize
+    }
+
+    fn paint(&mut self, start: usize, end: usize, role: Role) {
+        let end = end.min(self.roles.len());
+        self.roles[start..end].fill(Some(role));
+    }
+
+    fn paint_word(&mut self, word: &Word<'a>, command: Option<Role>) {
+        let start = self.offset_of(word.raw);
+        if let Some(role) = command {
+            self.paint(start, start + word.raw.len(), role);
+        }
+
+        let raw = word.raw.as_bytes();
+        let mut i = 0;
+        while i < raw.len() {
+            match raw[i] {
+                b'\\' => i += 2,
+                b'\'' | b'"' => {
+                    let len = quoted_len(&word.raw[i..]);
+                    self.paint(start + i, start + i + len, Role::String);
+                    i += len;
+                }
+                _ => i += 1,
+            }
+        }
+
+        for part in &word.parts {
+            let WordPart::Var { name, .. } = part else {
+                continue;
+            };
+            let name_start = self.offset_of(name);
+            let name_end = name_start + name.len();
+            // `${name}` has a brace on each side of the name
+            if self.line[..name_start].ends_with("${") {
+                self.paint(name_start - 2, name_end + 1, Role::Variable);
+            } else {
+                self.paint(name_start - 1, name_end, Role::Variable);
+            }
+        }
+    }
+
+    fn render(&self, colors: &Colors) -> String {
+        let mut output = String::new();
+        let mut current = None;
+
+        for (i, c) in self.line.char_indices() {
+            let role = self.roles[i];
+            if role != current {
+                match role {
+                    Some(role) => write!(
+                        output,
+                        "\x1b[0;{}m",
+                        colors.foreground(role, default_color(role))
+                    )
+                    .unwrap(),
+                    None => output.push_str("\x1b[0m"),
+                }
+                current = role;
+            }
+            output.push(c);
+        }
+
+        if current.is_some() {
+            output.push_str("\x1b[0m");
+        }
+        output
+    }
+}
+
+/// Returns `line` with escape sequences coloring it, `is_command` tells whether a command name can be run
+pub fn highlight(line: &str, colors: &Colors, mut is_command: impl FnMut(&str) -> bool) -> String {
+    let mut highlighter = Highlighter {
+        line,
+        roles: vec![None; line.len()],
+    };
+    // whether the next word names a command
+    let mut command_position = true;
+    // whether the next word is the target of a redirection
+    let mut redirect_target = false;
+
+    for token in Lexer::new(line) {
+        match token {
+            Token::Op(op) => {
+                let redirect = matches!(
+                    op,
+                    Operator::Less
+                        | Operator::Great
+                        | Operator::DGreat
+                        | Operator::LessAnd
+                        | Operator::GreatAnd
+                );
+                redirect_target = redirect;
+                command_position = match op {
+                    _ if redirect => command_position,
+                    // `;;` is followed by a pattern of a `case`
+                    Operator::DSemi => false,
+                    _ => true,
+                };
+            }
+            Token::Word(word) => {
+                let literal = word.as_literal();
+                let role = if redirect_target || !command_position {
+                    None
+                } else if literal.is_some_and(|literal| KEYWORDS.contains(&literal)) {
+                    // `case word in` is followed by a pattern, `{` and `coproc` by a command
+                    command_position = matches!(literal, Some("{" | "coproc"));
+                    highlighter.paint_word(&word, None);
+                    continue;
+                } else if is_assignment(&word) {
+                    None
+                } else {
+                    command_position = false;
+                    let name = literal.unwrap_or(word.raw);
+                    Some(if is_command(name) {
+                        Role::Command
+                    } else {
+                        Role::UnknownCommand
+                    })
+                };
+
+                redirect_target = false;
+                highlighter.paint_word(&word, role);
+            }
+        }
+    }
+
+    highlighter.render(colors)
+}
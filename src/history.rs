@@ -0,0 +1,348 @@
+//! The lines executed by the shell along with when they ran, for how long and how they exited
+//!
+//! `history export` writes the history as JSON in the following format, which `history import` reads back:
+//! ```json
+//! {
+//!     "version": 1,
+//!     "entries": [
+//!         { "command": "ls ram:/", "timestamp": 1718000000, "duration_ms": 12, "status": 0 }
+//!     ]
+//! }
+//! ```
+//! `timestamp` is the number of seconds since the unix epoch at which the command started,
+//! `duration_ms` and `status` may be `null` if they aren't known
+//!
+//! Interactive shells also keep the history in `$HISTFILE`, `$HOME/.safa_history` by default, holding one entry
+//! object per line. Each command is appended to it as soon as it finishes so a crash loses at most the line being
+//! written, which is skipped when the file is loaded again. The file is synced and cut down to its last
+//! `$HISTFILESIZE` entries when the shell exits, while `$HISTSIZE` limits the entries kept in memory.
+//!
+//! `$HISTCONTROL` is a colon separated list of which lines aren't recorded: `ignorespace` skips the lines starting
+//! with a space, `ignoredups` the lines repeating the previous one and `ignoreboth` both
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use thiserror::Error;
+
+use crate::json::{self, Value};
+
+/// The version of the exported format, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+
+/// The history file in `$HOME` used when `$HISTFILE` isn't set
+pub const DEFAULT_FILE: &str = ".safa_history";
+/// How many entries are kept in memory and in the history file when `$HISTSIZE` or `$HISTFILESIZE` isn't set
+pub const DEFAULT_SIZE: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub command: String,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub duration: Option<Duration>,
+    pub status: Option<isize>,
+}
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::JsonError),
+    #[error("unsupported history format version {0}")]
+    UnsupportedVersion(f64),
+    #[error("entry {0} is missing `{1}`")]
+    MissingField(usize, &'static str),
+}
+
+/// The file each recorded entry is appended to
+#[derive(Debug)]
+struct HistoryFile {
+    path: PathBuf,
+    file: File,
+    /// How many entries the file holds
+    len: usize,
+}
+
+#[derive(Debug)]
+pub struct History {
+    entries: Vec<Entry>,
+    file: Option<HistoryFile>,
+    /// How many entries are kept in memory
+    size: usize,
+    /// How many entries the file is cut down to
+    file_size: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            file: None,
+            size: DEFAULT_SIZE,
+            file_size: DEFAULT_SIZE,
+        }
+    }
+}
+
+impl History {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Sets how many entries are kept in memory and in the history file, dropping the oldest entries past `size`
+    pub fn set_limits(&mut self, size: usize, file_size: usize) {
+        self.size = size;
+        self.file_size = file_size;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.size);
+        self.entries.drain(..excess);
+    }
+
+    /// Returns whether `line` shouldn't be recorded according to the `$HISTCONTROL` options in `control`
+    pub fn ignores(&self, line: &str, control: &str) -> bool {
+        let (mut ignore_space, mut ignore_dups) = (false, false);
+        for option in control.split(':') {
+            match option {
+                "ignorespace" => ignore_space = true,
+                "ignoredups" => ignore_dups = true,
+                "ignoreboth" => (ignore_space, ignore_dups) = (true, true),
+                _ => {}
+            }
+        }
+
+        (ignore_space && line.starts_with([' ', '\t']))
+            || (ignore_dups
+                && self
+                    .entries
+                    .last()
+                    .is_some_and(|last| last.command == line.trim()))
+    }
+
+    /// Records that `command` started at `started`, taking `duration` and exiting with `status`,
+    /// appending it to the history file if there is one
+    pub fn record(
+        &mut self,
+        command: &str,
+        started: SystemTime,
+        duration: Duration,
+        status: isize,
+    ) -> io::Result<()> {
+        let timestamp = started
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        let entry = Entry {
+            command: command.to_string(),
+            timestamp,
+            duration: Some(duration),
+            status: Some(status),
+        };
+        let line = format!("{}\n", entry_to_json(&entry));
+        self.entries.push(entry);
+        self.truncate();
+
+        match &mut self.file {
+            // a single write so a crash can only cut the last line short
+            Some(file) => {
+                file.len += 1;
+                file.file.write_all(line.as_bytes())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn extend(&mut self, entries: impl IntoIterator<Item = Entry>) {
+        self.entries.extend(entries);
+        self.truncate();
+    }
+
+    /// Loads the entries kept in the history file at `path` and appends the entries recorded from now on to it
+    pub fn open_file(&mut self, path: PathBuf) -> io::Result<()> {
+        let mut entries = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|line| entry_from_json(&Value::parse(line).ok()?).ok())
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let excess = entries.len().saturating_sub(self.file_size);
+        entries.drain(..excess);
+        // rewritten so the file doesn't keep growing past its limit when the shell isn't exited normally
+        if excess > 0 {
+            write_entries(&path, &entries)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.file = Some(HistoryFile {
+            path,
+            file,
+            len: entries.len(),
+        });
+
+        self.extend(entries);
+        Ok(())
+    }
+
+    /// Cuts the history file down to its last `$HISTFILESIZE` entries and makes sure it is written to storage,
+    /// called when the shell exits
+    pub fn sync(&mut self) -> io::Result<()> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+
+        if file.len > self.file_size {
+            // the entries recorded by other shells since this one started are lost, like with `$HISTSIZE` in memory
+            let start = self.entries.len().saturating_sub(self.file_size);
+            write_entries(&file.path, &self.entries[start..])?;
+            file.len = self.entries.len() - start;
+            file.file = OpenOptions::new().append(true).open(&file.path)?;
+        }
+        file.file.sync_all()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entries = self.entries.iter().map(entry_to_json).collect();
+
+        [
+            ("version", Value::from(FORMAT_VERSION)),
+            ("entries", Value::Array(entries)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// Parses entries exported by [`History::to_json`]
+    pub fn parse_json(input: &str) -> Result<Vec<Entry>, HistoryError> {
+        let value = Value::parse(input)?;
+        let version = value.get("version").and_then(Value::as_f64).unwrap_or(0.);
+        if version != FORMAT_VERSION as f64 {
+            return Err(HistoryError::UnsupportedVersion(version));
+        }
+
+        let entries = value
+            .get("entries")
+            .and_then(Value::as_array)
+            .unwrap_or_default();
+
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                entry_from_json(entry).map_err(|field| HistoryError::MissingField(i, field))
+            })
+            .collect()
+    }
+}
+
+fn entry_to_json(entry: &Entry) -> Value {
+    [
+        ("command", Value::from(entry.command.as_str())),
+        ("timestamp", Value::from(entry.timestamp)),
+        (
+            "duration_ms",
+            Value::from(entry.duration.map(|duration| duration.as_millis() as u64)),
+        ),
+        ("status", Value::from(entry.status)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Returns the entry described by `entry` or the name of the field it is missing
+fn entry_from_json(entry: &Value) -> Result<Entry, &'static str> {
+    let number = |key| entry.get(key).and_then(Value::as_f64);
+    Ok(Entry {
+        command: entry
+            .get("command")
+            .and_then(Value::as_str)
+            .ok_or("command")?
+            .to_string(),
+        timestamp: number("timestamp").ok_or("timestamp")? as u64,
+        duration: number("duration_ms").map(|ms| Duration::from_millis(ms as u64)),
+        status: number("status").map(|status| status as isize),
+    })
+}
+
+/// Replaces the history file at `path` with `entries`, through a temporary file so a crash leaves either the old
+/// or the new file
+fn write_entries(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let contents = entries
+        .iter()
+        .map(|entry| format!("{}\n", entry_to_json(entry)))
+        .collect::<String>();
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{History, HistoryError};
+
+    #[test]
+    fn export_import() {
+        let mut history = History::default();
+        let started = SystemTime::UNIX_EPOCH + Duration::from_secs(1718000000);
+        history
+            .record("ls \"ram:/\"", started, Duration::from_millis(12), 0)
+            .unwrap();
+        history
+            .record("cd missing", started, Duration::ZERO, 1)
+            .unwrap();
+
+        let entries = History::parse_json(&history.to_json().to_string()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "ls \"ram:/\"");
+        assert_eq!(entries[0].timestamp, 1718000000);
+        assert_eq!(entries[0].duration, Some(Duration::from_millis(12)));
+        assert_eq!(entries[0].status, Some(0));
+        assert_eq!(entries[1].status, Some(1));
+    }
+
+    #[test]
+    fn import_missing_timing() {
+        let entries = History::parse_json(
+            r#"{ "version": 1, "entries": [{ "command": "ls", "timestamp": 5, "duration_ms": null }] }"#,
+        )
+        .unwrap();
+        assert_eq!(entries[0].timestamp, 5);
+        assert_eq!(entries[0].duration, None);
+        assert_eq!(entries[0].status, None);
+    }
+
+    #[test]
+    fn import_errors() {
+        assert!(matches!(
+            History::parse_json(r#"{ "version": 2, "entries": [] }"#),
+            Err(HistoryError::UnsupportedVersion(2.))
+        ));
+        assert!(matches!(
+            History::parse_json(r#"{ "entries": [] }"#),
+            Err(HistoryError::UnsupportedVersion(0.))
+        ));
+        assert!(matches!(
+            History::parse_json(
+                r#"{ "version": 1, "entries": [{ "command": "ls", "timestamp": 1 }, { "command": "ls" }] }"#
+            ),
+            Err(HistoryError::MissingField(1, "timestamp"))
+        ));
+        assert!(matches!(
+            History::parse_json("{"),
+            Err(HistoryError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! The shell's user-facing messages and their translations, loaded from JSON catalogs named after the locale
+//! such as `sys:/share/safa-shell/locale/fr.json`:
+//! ```json
+//! {
+//!     "command-not-found": "commande introuvable : {0}",
+//!     "did-you-mean": "vouliez-vous dire : {0} ?"
+//! }
+//! ```
+//! The locale is taken from `LC_ALL`, `LC_MESSAGES` or `LANG`, `fr_FR.UTF-8` looks for `fr_FR.json` then `fr.json`,
+//! `{0}`, `{1}`... are replaced by the message's arguments and messages missing from the catalog are shown in English
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use crate::json::Value;
+
+/// Where the catalogs are looked up, unless `$SAFA_LOCALE_DIR` is set
+const LOCALE_DIR: &str = "sys:/share/safa-shell/locale";
+
+macro_rules! messages {
+    ($($(#[$doc:meta])* $variant:ident = $key:literal => $default:expr,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Message {
+            $($(#[$doc])* $variant,)*
+        }
+
+        impl Message {
+            /// The key of the message in the catalogs
+            pub const fn key(&self) -> &'static str {
+                match self {
+                    $(Message::$variant => $key,)*
+                }
+            }
+
+            /// The message in English
+            pub const fn default_text(&self) -> &'static str {
+                match self {
+                    $(Message::$variant => $default,)*
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    /// The text shown by `safa --help` and after invalid arguments
+    Usage = "usage" => "usage: safa [-ilpq] [-c command [name [args...]] | script [args...]]
+       safa --serve <socket|->
+       safa --help | --version [--json]
+
+  -i, --interactive   read commands with the line editor even if stdin isn't a terminal, otherwise the script read
+                      from it runs without a prompt
+  -l, --login         start a login shell, which shows the message of the day from `sys:/etc/motd` or `$SAFA_MOTD`,
+                      or the welcome banner if there is no such file
+  -p, --privileged    ignore the inherited shell variables such as `IFS` and `PS1` and the configuration file,
+                      for services and other privileged contexts
+  -q, --quiet, --no-banner
+                      don't show the message of the day even in a login shell, as does setting `SAFA_SHELL_QUIET=1`
+      --no-color      don't draw the prompt, the line being edited and the banner in color, as does setting `NO_COLOR`
+  -c                  execute `command` then exit, `name` and `args` become `$0`, `$1`...
+      --serve         serve commands on a socket, or on stdin and stdout if `-` is given
+      --version       show the version, commit, build date, target and features of the shell, as JSON with `--json`
+  --                  stop parsing options, the next argument is the script",
+    /// The welcome text shown under the logo in interactive shells
+    Banner = "banner" => "| Welcome to SafaOS!
+| you are currently in ram:/, a playground
+| init ramdisk has been mounted at sys:/
+| sys:/bin is available in your PATH check it out for some binaries
+| the command `help` will provide a list of builtin commands and some terminal usage guide
+| to start GUI type opal-wm, ctrl+shift+T will open a terminal, to drag a window hold ctrl and the window.",
+    ShellLevelExceeded = "shell-level-exceeded" => "maximum shell nesting level ({0}) exceeded, possible recursive shell invocation",
+    CommandNotFound = "command-not-found" => "command not found: {0}",
+    NotExecutable = "not-executable" => "permission denied: {0}",
+    ReadonlyVariable = "readonly-variable" => "{0}: readonly variable",
+    TimedOut = "timed-out" => "timed out: {0}",
+    BadDescriptor = "bad-descriptor" => "bad file descriptor: {0}",
+    TooDeep = "too-deep" => "{0}: functions and sourced files nested more than {1} levels deep",
+    DidYouMean = "did-you-mean" => "did you mean: {0}?",
+    TerminatedBySignal = "terminated-by-signal" => "terminated by signal {0}",
+    IoError = "io-error" => "Failed with an IO error: {0}",
+    SyntaxError = "syntax-error" => "Syntax error: {0}",
+    /// Shown by `jobs` for a background job that is still running
+    JobRunning = "job-running" => "Running",
+    /// Shown when a background job exits successfully
+    JobDone = "job-done" => "Done",
+    /// Shown when a background job exits with a failure status
+    JobExited = "job-exited" => "Exit {0}",
+    CommandTook = "command-took" => "`{0}` took {1}",
+    PastedLines = "pasted-lines" => "pasted {0} lines:",
+    ExecutePasted = "execute-pasted" => "execute them? [y/N] ",
+    /// Shown by builtins given invalid arguments, followed by how to call them
+    BuiltinUsage = "builtin-usage" => "usage: {0}",
+    NotEnoughArguments = "not-enough-arguments" => "{0}: Not enough arguments",
+    NumericArgumentRequired = "numeric-argument-required" => "{0}: {1}: numeric argument required",
+    NotAValidName = "not-a-valid-name" => "{0}: {1}: not a valid name",
+    NotFound = "not-found" => "{0}: {1}: not found",
+    UnknownFlag = "unknown-flag" => "{0}: unknown flag `-{1}`",
+    OnlyInFunction = "only-in-function" => "{0}: can only be used in a function",
+    OnlyInFunctionOrSource = "only-in-function-or-source" => "{0}: can only be used in a function or a sourced file",
+    NoSuchOption = "no-such-option" => "{0}: {1}: no such option",
+    NoSuchJob = "no-such-job" => "{0}: {1}: no such job",
+    NoCurrentJob = "no-current-job" => "{0}: no current job",
+    SettingNotSaved = "setting-not-saved" => "{0}: setting applied but failed to save it: {1}",
+    UnknownTheme = "unknown-theme" => "{0}: unknown theme `{1}`",
+    UnknownGlyphs = "unknown-glyphs" => "{0}: unknown glyphs mode `{1}`",
+    UnknownSegment = "unknown-segment" => "{0}: unknown segment `{1}`",
+    InvalidDirs = "invalid-dirs" => "{0}: expected `all` or a positive number of directories, got `{1}`",
+    LimitsUnsupported = "limits-unsupported" => "{0}: setting limits isn't supported on this system",
+    InvalidLimit = "invalid-limit" => "{0}: invalid limit `{1}`",
+    PermissionsUnsupported = "permissions-unsupported" => "{0}: file permissions aren't supported on this system",
+    InvalidMask = "invalid-mask" => "{0}: invalid mask `{1}`",
+    ElevationUnsupported = "elevation-unsupported" => "SafaOS doesn't provide a way to elevate privileges yet",
+    UnknownAction = "unknown-action" => "{0}: unknown action `{1}`",
+    UnknownError = "unknown-error" => "{0}: unknown error `{1}`",
+    HelpBuiltins = "help-builtins" => "Builtin commands:",
+    HelpTerminal = "help-terminal" => "Terminal usage:
+- PageUp/PageDown: scroll up/down
+- Ctrl+Shift+C: clear screen",
+}
+
+/// Returns the locale the messages are shown in, such as `fr_FR`, `None` for English
+fn locale() -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+
+    // `fr_FR.UTF-8@euro` is `fr_FR` in the `UTF-8` encoding with the `euro` modifier
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    match locale {
+        "" | "C" | "POSIX" => None,
+        locale => Some(locale.to_string()),
+    }
+}
+
+/// Loads the catalog of `locale`, falling back to the catalog of its language
+fn load_catalog(locale: &str) -> HashMap<String, String> {
+    let dir = std::env::var("SAFA_LOCALE_DIR").unwrap_or_else(|_| LOCALE_DIR.to_string());
+    let language = locale.split(['_', '-']).next().unwrap_or(locale);
+
+    let Some(Value::Object(messages)) = [locale, language]
+        .into_iter()
+        .filter_map(|name| {
+            std::fs::read_to_string(Path::new(&dir).join(format!("{name}.json"))).ok()
+        })
+        .find_map(|contents| Value::parse(&contents).ok())
+    else {
+        return HashMap::new();
+    };
+
+    messages
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.as_str()?.to_string())))
+        .collect()
+}
+
+/// The translated messages, loaded the first time a message is shown
+fn catalog() -> &'static HashMap<String, String> {
+    static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        locale()
+            .map(|locale| load_catalog(&locale))
+            .unwrap_or_default()
+    })
+}
+
+/// Returns `message` in the user's language
+pub fn text(message: Message) -> &'static str {
+    catalog()
+        .get(message.key())
+        .map_or(message.default_text(), String::as_str)
+}
+
+/// Returns `message` in the user's language with `{0}`, `{1}`... replaced by `args`
+pub fn format(message: Message, args: &[&dyn Display]) -> String {
+    let mut text = text(message);
+    let mut formatted = String::with_capacity(text.len());
+    // the arguments are inserted as is, so one containing `{1}` doesn't have it replaced in turn
+    while let Some(open) = text.find('{') {
+        formatted.push_str(&text[..open]);
+        text = &text[open..];
+        let argument = text.find('}').and_then(|close| {
+            let arg = args.get(text[1..close].parse::<usize>().ok()?)?;
+            Some((close, arg))
+        });
+        match argument {
+            Some((close, arg)) => {
+                _ = write!(formatted, "{arg}");
+                text = &text[close + 1..];
+            }
+            None => {
+                formatted.push('{');
+                text = &text[1..];
+            }
+        }
+    }
+    formatted.push_str(text);
+    formatted
+}
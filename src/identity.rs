@@ -0,0 +1,62 @@
+//! Who the shell runs as and on which machine, for `$USER`, `$HOSTNAME`, prompts and `whoami`
+
+/// The name used when the system doesn't say who the user is, SafaOS doesn't have users yet
+const DEFAULT_USER: &str = "root";
+/// The name used when the system doesn't name the machine
+const DEFAULT_HOSTNAME: &str = "safaos";
+
+/// Returns the name of the user the shell runs as
+pub fn user() -> String {
+    ["USER", "LOGNAME"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty()))
+        .or_else(system_user)
+        .unwrap_or_else(|| DEFAULT_USER.to_string())
+}
+
+/// Returns the name of the machine the shell runs on
+pub fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|hostname| !hostname.is_empty())
+        .or_else(system_hostname)
+        .unwrap_or_else(|| DEFAULT_HOSTNAME.to_string())
+}
+
+#[cfg(target_os = "safaos")]
+fn system_user() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "safaos")]
+fn system_hostname() -> Option<String> {
+    None
+}
+
+/// Looks the user up in `/etc/passwd` using the real uid from `/proc/self/status`
+#[cfg(not(target_os = "safaos"))]
+fn system_user() -> Option<String> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let uid = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .next()?
+        .to_string();
+
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)? == uid).then(|| name.to_string())
+    })
+}
+
+#[cfg(not(target_os = "safaos"))]
+fn system_hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .into_iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+}
@@ -0,0 +1,252 @@
+//! Commands started in the background with `&`, which the shell keeps track of until they are waited for
+//!
+//! Each job is waited for by a thread of its own, so the shell can tell which jobs are done without blocking.
+//! An interactive shell hangs up the jobs still running when it exits, except those removed from the table with `disown`
+
+use std::{
+    io::{self, Write},
+    process::{Child, ExitStatus},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    i18n::{self, Message},
+    system,
+};
+
+#[derive(Debug)]
+pub struct Job {
+    /// The number the job is referred to with as `%id`
+    pub id: usize,
+    pub pid: u32,
+    /// The command as it was typed
+    pub command: String,
+    waiter: JoinHandle<io::Result<ExitStatus>>,
+    /// Set once the user has been told the job is done
+    notified: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn is_done(&self) -> bool {
+        self.waiter.is_finished()
+    }
+
+    /// Waits for the job to exit
+    fn wait(self) -> io::Result<ExitStatus> {
+        self.waiter.join().expect("job waiter panicked")
+    }
+}
+
+/// Describes how the job `id` running `command` ended, such as `[1] Done  sleep 1`
+fn notification(id: usize, command: &str, status: &ExitStatus) -> String {
+    let status = match (status.code(), crate::terminating_signal(status)) {
+        (Some(0), _) => i18n::text(Message::JobDone).to_string(),
+        (_, Some(signal)) => i18n::format(Message::TerminatedBySignal, &[&signal]),
+        (code, None) => i18n::format(Message::JobExited, &[&code.unwrap_or(-1)]),
+    };
+    format!("[{id}] {status}  {command}")
+}
+
+#[derive(Debug, Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    /// Whether jobs tell the user they are done as soon as they are rather than before the next prompt
+    notify: Arc<AtomicBool>,
+}
+
+impl Jobs {
+    /// Keeps track of `child` which runs `command`, returns the number of the new job
+    pub fn add(&mut self, command: &str, mut child: Child) -> usize {
+        // numbers are reused once all the jobs after them have been waited for
+        let id = self.jobs.last().map_or(1, |job| job.id + 1);
+        let pid = child.id();
+        let notified = Arc::new(AtomicBool::new(false));
+
+        let waiter = {
+            let command = command.to_string();
+            let notify = self.notify.clone();
+            let notified = notified.clone();
+            std::thread::spawn(move || {
+                let status = child.wait()?;
+                if notify.load(Ordering::Relaxed) && !notified.swap(true, Ordering::Relaxed) {
+                    // the terminal is in raw mode while the line editor waits for input
+                    let notification = notification(id, &command, &status);
+                    _ = write!(io::stderr(), "\r\n{notification}\r\n");
+                }
+                Ok(status)
+            })
+        };
+
+        self.jobs.push(Job {
+            id,
+            pid,
+            command: command.to_string(),
+            waiter,
+            notified,
+        });
+        id
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    /// Sets whether jobs tell the user they are done as soon as they are, rather than when [`Jobs::take_done`] is called
+    pub fn set_notify(&self, notify: bool) {
+        self.notify.store(notify, Ordering::Relaxed);
+    }
+
+    /// Returns the job `+` or `-` is shown next to, the current job being the last one started and the previous one the job before it
+    pub fn mark(&self, id: usize) -> char {
+        let mut jobs = self.jobs.iter().rev().map(|job| job.id);
+        match (jobs.next(), jobs.next()) {
+            (Some(current), _) if current == id => '+',
+            (_, Some(previous)) if previous == id => '-',
+            _ => ' ',
+        }
+    }
+
+    /// Returns the number of the job `spec` refers to, which is either the process ID of the job or a designator:
+    /// `%N` for the job `N`, `%+` or `%%` for the current job, `%-` for the previous one,
+    /// `%name` for the only job whose command starts with `name` and `%?text` for the only one whose command contains `text`
+    pub fn find(&self, spec: &str) -> Option<usize> {
+        let Some(designator) = spec.strip_prefix('%') else {
+            let pid = spec.parse::<u32>().ok()?;
+            return self
+                .jobs
+                .iter()
+                .find(|job| job.pid == pid)
+                .map(|job| job.id);
+        };
+
+        let job = match designator {
+            "" | "+" | "%" => self.jobs.last(),
+            "-" => self.jobs.iter().rev().nth(1),
+            _ => match designator.parse::<usize>() {
+                Ok(id) => self.jobs.iter().find(|job| job.id == id),
+                Err(_) => {
+                    let mut matching =
+                        self.jobs
+                            .iter()
+                            .filter(|job| match designator.strip_prefix('?') {
+                                Some(text) => job.command.contains(text),
+                                None => job.command.starts_with(designator),
+                            });
+                    // an ambiguous designator refers to no job
+                    match (matching.next(), matching.next()) {
+                        (Some(job), None) => Some(job),
+                        _ => None,
+                    }
+                }
+            },
+        };
+        job.map(|job| job.id)
+    }
+
+    /// Waits for the job `id` to exit then forgets it, returns its status
+    pub fn wait(&mut self, id: usize) -> Option<io::Result<ExitStatus>> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index).wait())
+    }
+
+    /// Removes the job `id` from the table, it keeps running but is no longer listed, waited for or hung up
+    pub fn disown(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    /// Hangs up the jobs that are still running, done when an interactive shell exits
+    pub fn hang_up(&self) {
+        for job in self.jobs.iter().filter(|job| !job.is_done()) {
+            // the job may have exited since it was checked
+            _ = system::hang_up(job.pid);
+        }
+    }
+
+    /// Forgets the jobs that are done, returns the notifications of those the user hasn't been told about yet
+    pub fn take_done(&mut self) -> Vec<String> {
+        let (done, running) = std::mem::take(&mut self.jobs)
+            .into_iter()
+            .partition::<Vec<_>, _>(Job::is_done);
+        self.jobs = running;
+
+        done.into_iter()
+            .filter(|job| !job.notified.load(Ordering::Relaxed))
+            .filter_map(|job| {
+                let (id, command) = (job.id, job.command.clone());
+                let status = job.wait().ok()?;
+                Some(notification(id, &command, &status))
+            })
+            .collect()
+    }
+}
+
+/// Runs `sh` to have processes to keep track of
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::Command;
+
+    use super::Jobs;
+
+    fn add(jobs: &mut Jobs, script: &str) -> usize {
+        let child = Command::new("sh").args(["-c", script]).spawn().unwrap();
+        jobs.add(script, child)
+    }
+
+    #[test]
+    fn designators() {
+        let mut jobs = Jobs::default();
+        let sleep = add(&mut jobs, "sleep 1");
+        let first = add(&mut jobs, "exit 1");
+        let second = add(&mut jobs, "exit 2");
+        assert_eq!((sleep, first, second), (1, 2, 3));
+
+        assert_eq!((jobs.mark(3), jobs.mark(2), jobs.mark(1)), ('+', '-', ' '));
+        for (spec, id) in [
+            ("%", 3),
+            ("%%", 3),
+            ("%+", 3),
+            ("%-", 2),
+            ("%1", 1),
+            ("%sl", 1),
+            ("%?2", 3),
+        ] {
+            assert_eq!(jobs.find(spec), Some(id), "{spec}");
+        }
+        // ambiguous and unknown designators
+        assert_eq!(jobs.find("%exit"), None);
+        assert_eq!(jobs.find("%9"), None);
+        let pid = jobs.iter().next().unwrap().pid;
+        assert_eq!(jobs.find(&pid.to_string()), Some(1));
+
+        assert_eq!(jobs.wait(2).unwrap().unwrap().code(), Some(1));
+        assert_eq!(jobs.wait(3).unwrap().unwrap().code(), Some(2));
+        assert!(jobs.wait(3).is_none());
+        // the numbers of the jobs waited for are reused
+        assert_eq!(add(&mut jobs, "exit 0"), 2);
+
+        assert!(jobs.disown(1).is_some());
+        assert_eq!(jobs.find("%sl"), None);
+    }
+
+    #[test]
+    fn done() {
+        let mut jobs = Jobs::default();
+        add(&mut jobs, "exit 0");
+        while !jobs.iter().all(|job| job.is_done()) {
+            std::thread::yield_now();
+        }
+
+        let done = jobs.take_done();
+        assert_eq!(done.len(), 1);
+        assert!(
+            done[0].starts_with("[1] ") && done[0].ends_with("  exit 0"),
+            "{done:?}"
+        );
+        assert_eq!(jobs.iter().count(), 0);
+    }
+}
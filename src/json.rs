@@ -0,0 +1,309 @@
+//! A minimal JSON value with a writer and a parser, enough for the formats the shell imports and exports
+
+use std::{collections::BTreeMap, fmt::Display, iter::Peekable, str::Chars};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+#[derive(Debug, Error)]
+pub enum JsonError {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("unexpected character `{0}`")]
+    UnexpectedChar(char),
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("invalid escape sequence")]
+    InvalidEscape,
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(str) => Some(str),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, JsonError> {
+        let mut parser = JsonParser {
+            chars: input.chars().peekable(),
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(JsonError::UnexpectedChar(c)),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::Number(value as f64)
+            }
+        })*
+    };
+}
+
+impl_from_number!(u32, u64, i32, isize, usize, f64);
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Object(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+fn write_escaped(f: &mut std::fmt::Formatter<'_>, str: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in str.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(bool) => write!(f, "{bool}"),
+            Value::Number(number) if number.is_finite() => write!(f, "{number}"),
+            Value::Number(_) => write!(f, "null"),
+            Value::String(str) => write_escaped(f, str),
+            Value::Array(array) => {
+                write!(f, "[")?;
+                for (i, value) in array.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(JsonError::UnexpectedChar(c)),
+            None => Err(JsonError::UnexpectedEof),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str, value: Value) -> Result<Value, JsonError> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            None => Err(JsonError::UnexpectedEof),
+            Some('n') => self.expect_keyword("null", Value::Null),
+            Some('t') => self.expect_keyword("true", Value::Bool(true)),
+            Some('f') => self.expect_keyword("false", Value::Bool(false)),
+            Some('"') => self.parse_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(JsonError::UnexpectedChar(c)),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value, JsonError> {
+        let mut number = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            number.push(c);
+        }
+
+        number
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| JsonError::InvalidNumber(number))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.chars.next().ok_or(JsonError::UnexpectedEof)?;
+            code = code * 16 + digit.to_digit(16).ok_or(JsonError::InvalidEscape)?;
+        }
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut str = String::new();
+
+        loop {
+            match self.chars.next().ok_or(JsonError::UnexpectedEof)? {
+                '"' => return Ok(str),
+                '\\' => str.push(match self.chars.next().ok_or(JsonError::UnexpectedEof)? {
+                    '"' => '"',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let mut code = self.parse_hex4()?;
+                        // a surrogate pair encodes a character outside the basic multilingual plane
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex4()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00));
+                        }
+                        char::from_u32(code).ok_or(JsonError::InvalidEscape)?
+                    }
+                    _ => return Err(JsonError::InvalidEscape),
+                }),
+                c => str.push(c),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, JsonError> {
+        self.expect('[')?;
+        let mut array = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(Value::Array(array));
+        }
+
+        loop {
+            array.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(array)),
+                Some(c) => return Err(JsonError::UnexpectedChar(c)),
+                None => return Err(JsonError::UnexpectedEof),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, JsonError> {
+        self.expect('{')?;
+        let mut map = BTreeMap::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(Value::Object(map));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            map.insert(key, self.parse_value()?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(map)),
+                Some(c) => return Err(JsonError::UnexpectedChar(c)),
+                None => return Err(JsonError::UnexpectedEof),
+            }
+        }
+    }
+}
@@ -1,26 +1,166 @@
-use std::{borrow::Cow, str::CharIndices};
+use std::{fmt::Display, iter::Peekable, str::CharIndices};
 
 pub struct Lexer<'a> {
     input_raw: &'a str,
-    chars: CharIndices<'a>,
+    chars: Peekable<CharIndices<'a>>,
+    /// The offset of the first quote or substitution that isn't closed before the end of the input, and how it was opened
+    unterminated: Option<(usize, &'static str)>,
+    /// Whether blanks and operators are part of words rather than ending them, see [`Lexer::word`]
+    whole_word: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `;`
+    Semicolon,
+    /// `;;`, ends an item of a `case`
+    DSemi,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+    /// `<`
+    Less,
+    /// `>`
+    Great,
+    /// `>>`
+    DGreat,
+    /// `<&`, reads from a descriptor
+    LessAnd,
+    /// `>&`, writes to a descriptor
+    GreatAnd,
+    /// `|`
+    Pipe,
+    /// `&`, runs the command before it in the background
+    Amp,
+    /// A line break, which ends a command like `;`
+    Newline,
+}
+
+impl Operator {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Semicolon => ";",
+            Self::DSemi => ";;",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::LParen => "(",
+            Self::RParen => ")",
+            Self::Less => "<",
+            Self::Great => ">",
+            Self::DGreat => ">>",
+            Self::LessAnd => "<&",
+            Self::GreatAnd => ">&",
+            Self::Pipe => "|",
+            Self::Amp => "&",
+            Self::Newline => "newline",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordPart<'a> {
+    /// Unquoted text
+    Literal(&'a str),
+    /// Quoted or escaped text, taken as is
+    Quoted(&'a str),
+    /// A `$name` or `${name}` variable, `quoted` if it appeared inside double quotes
+    Var { name: &'a str, quoted: bool },
+    /// A `$(source)` command substitution, `quoted` if it appeared inside double quotes
+    Command { source: &'a str, quoted: bool },
+    /// A `<(source)` process substitution, replaced by the path of a file the output of `source` can be read from
+    Process { source: &'a str },
+}
+
+/// A word made of the adjacent parts it was written as, `a"b c"$d` is one word with three parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word<'a> {
+    /// The word as it appears in the input
+    pub raw: &'a str,
+    pub parts: Vec<WordPart<'a>>,
+}
+
+impl<'a> Word<'a> {
+    /// Returns the word's text if it is made of a single unquoted literal
+    pub fn as_literal(&self) -> Option<&'a str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(literal)] => Some(literal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token<'a> {
-    Word(&'a str),
-    Str(&'a str),
-    Var(&'a str),
+    Word(Word<'a>),
+    Op(Operator),
 }
 
-impl<'a> Token<'a> {
-    pub fn as_str(&self) -> Cow<'a, str> {
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::Word(str) | Token::Str(str) => Cow::Borrowed(str),
-            Token::Var(name) => Cow::Owned(std::env::var(name).unwrap_or_default()),
+            Token::Word(word) => write!(f, "{}", word.raw),
+            Token::Op(op) => write!(f, "{}", op.as_str()),
         }
     }
 }
 
+/// The tokens of a [`Lexer`] with a token of lookahead, keeping track of where the next token starts
+pub struct Tokens<'a> {
+    lexer: Lexer<'a>,
+    /// The next token and its offset, `None` if it hasn't been lexed yet
+    peeked: Option<Option<(usize, Token<'a>)>>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            peeked: None,
+        }
+    }
+
+    fn peek_spanned(&mut self) -> Option<&(usize, Token<'a>)> {
+        self.peeked
+            .get_or_insert_with(|| self.lexer.next_spanned())
+            .as_ref()
+    }
+
+    pub fn peek(&mut self) -> Option<&Token<'a>> {
+        self.peek_spanned().map(|(_, token)| token)
+    }
+
+    pub fn next(&mut self) -> Option<Token<'a>> {
+        match self.peeked.take() {
+            Some(peeked) => peeked.map(|(_, token)| token),
+            None => self.lexer.next(),
+        }
+    }
+
+    pub fn next_if(&mut self, f: impl FnOnce(&Token<'a>) -> bool) -> Option<Token<'a>> {
+        if self.peek().is_some_and(f) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    /// The offset and opening of the first quote or substitution lexed so far that isn't closed
+    pub fn unterminated(&self) -> Option<(usize, &'static str)> {
+        self.lexer.unterminated
+    }
+
+    /// The offset of the next token, or the end of the input if there are no more tokens
+    pub fn offset(&mut self) -> usize {
+        let end = self.lexer.input_raw.len();
+        self.peek_spanned().map_or(end, |(offset, _)| *offset)
+    }
+}
+
 impl<'a> Iterator for Lexer<'a> {
     type Item = Token<'a>;
 
@@ -29,45 +169,459 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
+/// What the characters of a substitution's source are nested in, see [`Lexer::eat_command_source`]
+enum Nesting {
+    Parenthesis,
+    /// Double quotes, where only `$(` nests further
+    Quote,
+    /// A `$(` substitution inside double quotes, starting at the given offset
+    Substitution(usize),
+}
+
+/// Characters that end a word even if they aren't surrounded by whitespace
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '(' | ')' | '|' | '&' | '<' | '>')
+}
+
+/// Parameters whose name is a single special character, such as `$?`
+fn is_special_param(c: char) -> bool {
+    matches!(c, '?' | '$' | '#' | '!' | '@' | '*' | '-') || c.is_ascii_digit()
+}
+
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             input_raw: input,
-            chars: input.char_indices(),
+            chars: input.char_indices().peekable(),
+            unterminated: None,
+            whole_word: false,
         }
     }
 
-    pub fn next(&mut self) -> Option<Token<'a>> {
-        match self.chars.next()? {
-            (_, c) if c.is_whitespace() => self.next(),
-            (start, quote) if quote == '"' || quote == '\'' => {
-                let start = start + 1;
-                let mut end = start;
-
-                while !self.chars.next().is_none_or(|(_, c)| c == quote) {
-                    end += 1;
+    /// Lexes all of `input` as a single word in which blanks and operators are literal text,
+    /// such as the pattern of `${name#pattern}`
+    pub fn word(input: &'a str) -> Word<'a> {
+        let mut lexer = Self::new(input);
+        lexer.whole_word = true;
+        lexer.lex_word()
+    }
+
+    fn is_boundary(&self, c: char) -> bool {
+        !self.whole_word && is_word_boundary(c)
+    }
+
+    /// The offset of the next character, or the end of the input
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.input_raw.len(), |&(i, _)| i)
+    }
+
+    /// Consumes characters while `f` returns true, returns the consumed slice
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.offset();
+        while self.chars.next_if(|&(_, c)| f(c)).is_some() {}
+        &self.input_raw[start..self.offset()]
+    }
+
+    /// Consumes the single character at the current offset, returns it as a slice
+    fn eat_char(&mut self) -> Option<&'a str> {
+        let (i, c) = self.chars.next()?;
+        Some(&self.input_raw[i..i + c.len_utf8()])
+    }
+
+    /// Records that the quote or substitution opened with `opening` at `offset` reached the end of the input
+    fn unterminated_at(&mut self, offset: usize, opening: &'static str) {
+        self.unterminated.get_or_insert((offset, opening));
+    }
+
+    /// Consumes the closing `quote` of a quoted string, recording the string as unterminated if the input ended instead
+    fn eat_closing(&mut self, quote: char, offset: usize, opening: &'static str) {
+        if self.chars.next_if(|&(_, c)| c == quote).is_none() {
+            self.unterminated_at(offset, opening);
+        }
+    }
+
+    /// Consumes the source of a `$(...)` or `<(...)` substitution and its closing parenthesis, the `opening` has already been consumed,
+    /// parentheses inside quotes and nested substitutions don't end it
+    fn eat_command_source(&mut self, opening: &'static str) -> &'a str {
+        let start = self.offset();
+        // kept on a stack rather than by recursion so substitutions nested without end can't overflow it
+        let mut nesting = Vec::new();
+
+        while let Some((i, c)) = self.chars.next() {
+            match (nesting.last(), c) {
+                (Some(Nesting::Quote), '"') => _ = nesting.pop(),
+                (Some(Nesting::Quote), '$') if self.chars.next_if(|&(_, c)| c == '(').is_some() => {
+                    nesting.push(Nesting::Substitution(i))
                 }
+                (Some(Nesting::Quote), '\\') => _ = self.chars.next(),
+                (Some(Nesting::Quote), _) => {}
+                (None, ')') => return &self.input_raw[start..i],
+                (_, ')') => _ = nesting.pop(),
+                (_, '(') => nesting.push(Nesting::Parenthesis),
+                (_, '\\') => _ = self.chars.next(),
+                (_, '\'') => {
+                    self.eat_while(|c| c != '\'');
+                    self.chars.next();
+                }
+                (_, '"') => nesting.push(Nesting::Quote),
+                _ => {}
+            }
+        }
+
+        // an unterminated substitution runs until the end of the input, the innermost one is reported
+        let unterminated = nesting.iter().rev().find_map(|nesting| match nesting {
+            Nesting::Substitution(offset) => Some((*offset, "$(")),
+            _ => None,
+        });
+        let (offset, opening) = unterminated.unwrap_or((start - opening.len(), opening));
+        self.unterminated_at(offset, opening);
+        &self.input_raw[start..]
+    }
 
-                Some(Token::Str(&self.input_raw[start..end]))
+    /// Lexes a variable reference or a command substitution, the `$` has already been consumed
+    fn lex_var(&mut self, quoted: bool) -> WordPart<'a> {
+        match self.chars.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let source = self.eat_command_source("$(");
+                WordPart::Command { source, quoted }
+            }
+            Some(&(i, '{')) => {
+                self.chars.next();
+                let name = self.eat_while(|c| c != '}');
+                self.eat_closing('}', i - 1, "${");
+                WordPart::Var { name, quoted }
             }
-            (start, '$') => {
-                let start = start + 1;
-                let mut end = start;
+            Some(&(_, c)) if c == '_' || c.is_ascii_alphabetic() => {
+                let name = self.eat_while(|c| c == '_' || c.is_ascii_alphanumeric());
+                WordPart::Var { name, quoted }
+            }
+            Some(&(_, c)) if is_special_param(c) => {
+                let name = self.eat_char().unwrap();
+                WordPart::Var { name, quoted }
+            }
+            _ => WordPart::Literal("$"),
+        }
+    }
 
-                while !self.chars.next().is_none_or(|(_, c)| c.is_whitespace()) {
-                    end += 1;
+    /// Lexes the inside of a double quoted string, the opening quote at `start` has already been consumed
+    fn lex_double_quoted(&mut self, start: usize, parts: &mut Vec<WordPart<'a>>) {
+        let parts_before = parts.len();
+
+        loop {
+            let text = self.eat_while(|c| !matches!(c, '"' | '$' | '\\'));
+            if !text.is_empty() {
+                parts.push(WordPart::Quoted(text));
+            }
+
+            match self.chars.next() {
+                Some((_, '"')) => break,
+                None => {
+                    self.unterminated_at(start, "\"");
+                    break;
                 }
+                Some((_, '$')) => parts.push(match self.lex_var(true) {
+                    WordPart::Literal(literal) => WordPart::Quoted(literal),
+                    var => var,
+                }),
+                Some((i, '\\')) => match self.chars.peek() {
+                    Some((_, '"' | '$' | '\\')) => {
+                        parts.push(WordPart::Quoted(self.eat_char().unwrap()))
+                    }
+                    _ => parts.push(WordPart::Quoted(&self.input_raw[i..i + 1])),
+                },
+                Some(_) => unreachable!(),
+            }
+        }
+
+        // `""` is still an (empty) word
+        if parts.len() == parts_before {
+            parts.push(WordPart::Quoted(""));
+        }
+    }
+
+    fn lex_word(&mut self) -> Word<'a> {
+        let start = self.offset();
+        let mut parts = Vec::new();
 
-                Some(Token::Var(&self.input_raw[start..end]))
+        while let Some(&(i, c)) = self.chars.peek() {
+            match c {
+                '<' if self.input_raw[i..].starts_with("<(") => {
+                    self.chars.nth(1);
+                    let source = self.eat_command_source("<(");
+                    parts.push(WordPart::Process { source });
+                }
+                c if self.is_boundary(c) => break,
+                '\'' => {
+                    self.chars.next();
+                    parts.push(WordPart::Quoted(self.eat_while(|c| c != '\'')));
+                    self.eat_closing('\'', i, "'");
+                }
+                '"' => {
+                    self.chars.next();
+                    self.lex_double_quoted(i, &mut parts);
+                }
+                '$' => {
+                    self.chars.next();
+                    parts.push(self.lex_var(false));
+                }
+                '\\' => {
+                    self.chars.next();
+                    parts.push(WordPart::Quoted(self.eat_char().unwrap_or_default()));
+                }
+                _ => {
+                    let whole_word = self.whole_word;
+                    parts.push(WordPart::Literal(self.eat_while(|c| {
+                        (whole_word || !is_word_boundary(c))
+                            && !matches!(c, '\'' | '"' | '$' | '\\')
+                    })))
+                }
             }
-            (start, _) => {
-                let mut end = start;
-                while !self.chars.next().is_none_or(|(_, c)| c.is_whitespace()) {
-                    end += 1;
+        }
+
+        Word {
+            raw: &self.input_raw[start..self.offset()],
+            parts,
+        }
+    }
+
+    /// Skips the blanks and comments before the next token, line breaks are tokens of their own
+    ///
+    /// A loop rather than recursion so generated input with long runs of blanks can't overflow the stack
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some((_, '#')) => _ = self.eat_while(|c| c != '\n'),
+                Some((_, c)) if c.is_whitespace() && *c != '\n' => {
+                    self.eat_while(|c| c.is_whitespace() && c != '\n');
                 }
+                _ => break,
+            }
+        }
+    }
+
+    /// Returns the next token along with the offset it starts at
+    fn next_spanned(&mut self) -> Option<(usize, Token<'a>)> {
+        self.skip_blanks();
+        let offset = self.offset();
+        self.next().map(|token| (offset, token))
+    }
+
+    pub fn next(&mut self) -> Option<Token<'a>> {
+        self.skip_blanks();
+        match self.chars.peek()? {
+            (_, '\n') => {
+                self.chars.next();
+                Some(Token::Op(Operator::Newline))
+            }
+            (_, ';') => {
+                self.chars.next();
+                if self.chars.next_if(|&(_, c)| c == ';').is_some() {
+                    Some(Token::Op(Operator::DSemi))
+                } else {
+                    Some(Token::Op(Operator::Semicolon))
+                }
+            }
+            (_, '(') => {
+                self.chars.next();
+                Some(Token::Op(Operator::LParen))
+            }
+            (_, ')') => {
+                self.chars.next();
+                Some(Token::Op(Operator::RParen))
+            }
+            (i, '<') if self.input_raw[*i..].starts_with("<(") => {
+                Some(Token::Word(self.lex_word()))
+            }
+            (_, '|' | '&' | '<' | '>') => Some(Token::Op(self.lex_operator())),
+            _ => Some(Token::Word(self.lex_word())),
+        }
+    }
 
-                Some(Token::Word(&self.input_raw[start..end + 1]))
+    /// Lexes an operator made of `|`, `&`, `<` and `>`, preferring the longest one so `&&` isn't read as two `&`
+    fn lex_operator(&mut self) -> Operator {
+        let (_, first) = self.chars.next().expect("an operator character was peeked");
+        let doubled = first != '<' && self.chars.next_if(|&(_, c)| c == first).is_some();
+        let ampersand = !doubled
+            && matches!(first, '<' | '>')
+            && self.chars.next_if(|&(_, c)| c == '&').is_some();
+
+        match (first, doubled, ampersand) {
+            ('|', false, _) => Operator::Pipe,
+            ('|', true, _) => Operator::Or,
+            ('&', false, _) => Operator::Amp,
+            ('&', true, _) => Operator::And,
+            ('>', _, true) => Operator::GreatAnd,
+            ('>', false, _) => Operator::Great,
+            ('>', true, _) => Operator::DGreat,
+            (_, _, true) => Operator::LessAnd,
+            _ => Operator::Less,
+        }
+    }
+}
+
+/// Lexes and parses random and pathological inputs, checking the lexer always makes progress, never panics and
+/// doesn't recurse deeper the longer the input is
+#[cfg(test)]
+mod fuzz {
+    use super::{Lexer, Token, WordPart};
+    use crate::parser::{MAX_DEPTH, ParseErrorKind, Parser};
+
+    /// The characters the random inputs are made of, those special to the lexer and a few of more than one byte
+    const ALPHABET: &[char] = &[
+        ' ', '\t', '\n', '#', ';', '&', '|', '<', '>', '(', ')', '{', '}', '$', '"', '\'', '\\',
+        '!', '=', '*', ',', '[', ']', '-', '?', '@', 'a', 'b', '_', '0', '1', 'é', '→', '🦀',
+    ];
+
+    /// Small enough that lexing recursively once per character of the inputs below would overflow it
+    const STACK_SIZE: usize = 256 * 1024;
+
+    /// A xorshift generator, seeded so failures can be reproduced
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn input(&mut self, max_len: usize) -> String {
+            (0..self.below(max_len + 1))
+                .map(|_| ALPHABET[self.below(ALPHABET.len())])
+                .collect()
+        }
+    }
+
+    /// Whether `slice` was taken from `input`
+    fn is_within(slice: &str, input: &str) -> bool {
+        let start = slice.as_ptr() as usize;
+        let input_start = input.as_ptr() as usize;
+        start >= input_start && start + slice.len() <= input_start + input.len()
+    }
+
+    /// Lexes all of `input` checking each word is a slice of the input following the previous one
+    fn check_lexer(input: &str) {
+        let mut lexer = Lexer::new(input);
+        let mut end = input.as_ptr() as usize;
+        let mut tokens = 0;
+        while let Some(token) = lexer.next() {
+            tokens += 1;
+            assert!(
+                tokens <= input.len(),
+                "more tokens than characters in {input:?}"
+            );
+
+            let Token::Word(word) = token else {
+                continue;
+            };
+            assert!(
+                is_within(word.raw, input),
+                "{word:?} isn't part of {input:?}"
+            );
+            assert!(
+                word.raw.as_ptr() as usize >= end,
+                "{word:?} overlaps the previous word in {input:?}"
+            );
+            end = word.raw.as_ptr() as usize + word.raw.len();
+            for part in &word.parts {
+                let (WordPart::Literal(text)
+                | WordPart::Quoted(text)
+                | WordPart::Var { name: text, .. }
+                | WordPart::Command { source: text, .. }
+                | WordPart::Process { source: text }) = part;
+                // a lone `$` and the empty text of a trailing `\` aren't slices of the input
+                let is_static = matches!(*text, "" | "$");
+                assert!(
+                    is_static || is_within(text, input),
+                    "{part:?} isn't part of {input:?}"
+                );
             }
         }
     }
+
+    /// Parses `input`, which may fail but not panic, errors point inside the input
+    fn check_parser(input: &str) {
+        if let Err(err) = Parser::new(input).parse() {
+            assert!(
+                err.offset <= input.len(),
+                "{err:?} points past the end of {input:?}"
+            );
+        }
+    }
+
+    /// Runs `f` on a thread with a stack of [`STACK_SIZE`]
+    fn with_small_stack(f: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(f)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(0x5afa_5afa_5afa_5afa);
+        for _ in 0..20_000 {
+            let input = random.input(48);
+            check_lexer(&input);
+            check_parser(&input);
+        }
+    }
+
+    #[test]
+    fn long_runs() {
+        let pathological = [
+            " ".repeat(1_000_000),
+            "\t \t".repeat(300_000) + "echo end",
+            "#".repeat(1_000_000),
+            "# comment\n".repeat(100_000),
+            " # a\n".repeat(100_000),
+            "\n".repeat(1_000_000),
+            ";".repeat(1_000_000),
+            "$(".repeat(100_000),
+            "<(".repeat(100_000),
+            "${".repeat(100_000),
+            "\"$(".repeat(100_000),
+            "'".repeat(100_001),
+            "\\".repeat(100_001),
+            "a".repeat(1_000_000),
+            "é".repeat(500_000),
+        ];
+        with_small_stack(move || {
+            for input in &pathological {
+                check_lexer(input);
+                check_parser(input);
+            }
+        });
+    }
+
+    /// The parser does recurse into nested commands, [`MAX_DEPTH`] levels have to fit in the default stack of a thread
+    #[test]
+    fn deep_nesting() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}echo{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        for (open, close) in [
+            ("(", ")"),
+            ("{ ", "; }"),
+            ("case x in x) ", ";; esac "),
+            ("coproc ", ""),
+            ("f() { ", "; }"),
+        ] {
+            assert!(Parser::new(&nested(open, close, MAX_DEPTH)).parse().is_ok());
+
+            let err = Parser::new(&nested(open, close, 100_000))
+                .parse()
+                .unwrap_err();
+            assert!(matches!(err.kind, ParseErrorKind::TooDeep), "{open}: {err}");
+        }
+    }
 }
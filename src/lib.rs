@@ -0,0 +1,886 @@
+//! SafaShell, the shell of SafaOS
+//!
+//! The [`Shell`] can also be embedded by programs that want to host the shell loop on their own streams,
+//! see [`Shell::run_with_io`]
+
+const MULTI_PATH_SEP: &str = if cfg!(any(target_os = "windows", target_os = "safaos")) {
+    ";"
+} else {
+    ":"
+};
+
+/// The search path used when `PATH` isn't set, such as early in boot,
+/// SafaOS images can change it by setting `SAFA_DEFAULT_PATH` when building the shell
+const DEFAULT_PATH: &str = match option_env!("SAFA_DEFAULT_PATH") {
+    Some(path) => path,
+    None => "sys:/bin",
+};
+
+/// Returns `PATH`, or [`DEFAULT_PATH`] if it isn't set
+fn search_path() -> String {
+    std::env::var("PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string())
+}
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, IsTerminal, PipeWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::mpsc::Sender,
+    time::{Duration, Instant, SystemTime},
+};
+
+use thiserror::Error;
+#[cfg(feature = "env-bootstrap")]
+pub mod bootstrap;
+mod braces;
+mod builtin;
+mod colors;
+mod command_log;
+pub mod completion;
+mod completion_defs;
+mod config;
+#[cfg(feature = "cwd-events")]
+mod cwd_events;
+mod descriptors;
+mod drives;
+mod editor;
+mod errors;
+pub mod events;
+mod exec;
+pub mod expand;
+#[cfg(feature = "fs-builtins")]
+mod fs_builtins;
+mod hash;
+mod help_flags;
+mod highlight;
+mod history;
+pub mod i18n;
+mod identity;
+mod jobs;
+mod json;
+mod lexer;
+mod limits;
+mod motd;
+#[cfg(feature = "net-utils")]
+mod net_builtins;
+mod parser;
+mod pattern;
+mod printf;
+mod privilege;
+mod process_substitution;
+mod prompt;
+mod readline;
+mod server;
+mod service;
+mod stdio;
+mod suggest;
+mod system;
+mod terminal;
+mod time;
+mod umask;
+pub mod vars;
+pub mod version;
+mod width;
+
+pub use readline::enter_raw_mode;
+
+use cfg_if::cfg_if;
+
+use crate::{
+    colors::{Colors, Role},
+    config::Config,
+    descriptors::Descriptors,
+    editor::{CompletionStyle, Editor},
+    events::Event,
+    hash::CommandHash,
+    history::History,
+    i18n::Message,
+    jobs::Jobs,
+    limits::Limits,
+    parser::ParseError,
+    process_substitution::ProcessSubstitution,
+    prompt::Prompt,
+    readline::IOWrapper,
+    stdio::{Input, Output, SharedOutput, Streams},
+    vars::Variables,
+};
+
+// There is kinda of no need to use this, but it's nice to have
+// support on both the host system and SafaOS
+cfg_if! {
+    if #[cfg(target_os = "safaos")] {
+        use safa_api::errors::ErrorStatus;
+        pub enum OSReturn {
+            Known(ErrorStatus),
+            Unknown(isize),
+        }
+
+        impl From<ExitStatus> for OSReturn {
+            fn from(status: ExitStatus) -> Self {
+                let code = status.code().unwrap_or(0);
+                if code.is_positive() || code == 0 || status.success() {
+                    return OSReturn::Unknown(code as isize);
+                }
+
+                let error_status = ErrorStatus::try_from((-code) as u16);
+                match error_status {
+                    Ok(err) => OSReturn::Known(err),
+                    Err(()) => OSReturn::Unknown(code as isize),
+                }
+            }
+        }
+
+        impl From<io::Error> for OSReturn {
+            fn from(err: io::Error) -> Self {
+                OSReturn::Known(safa_api::errors::err_from_io_error_kind(err.kind()))
+            }
+        }
+    } else {
+        use std::convert::Infallible;
+        pub enum OSReturn {
+            Known(Infallible),
+            Unknown(isize),
+        }
+
+        impl From<ExitStatus> for OSReturn {
+            fn from(status: ExitStatus) -> Self {
+                assert!(!status.success());
+                Self::Unknown(exit_status_code(&status))
+            }
+        }
+
+        impl From<io::Error> for OSReturn {
+            fn from(err: io::Error) -> Self {
+                Self::Unknown(1)
+            }
+        }
+    }
+}
+
+/// Returns the signal that killed the process with `status`, if any
+pub fn terminating_signal(status: &ExitStatus) -> Option<i32> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            std::os::unix::process::ExitStatusExt::signal(status)
+        } else {
+            _ = status;
+            None
+        }
+    }
+}
+
+/// Returns the name of the signal `signal` such as `SIGINT`, using the linux numbering
+pub fn signal_name(signal: i32) -> String {
+    const NAMES: [&str; 31] = [
+        "HUP", "INT", "QUIT", "ILL", "TRAP", "ABRT", "BUS", "FPE", "KILL", "USR1", "SEGV", "USR2",
+        "PIPE", "ALRM", "TERM", "STKFLT", "CHLD", "CONT", "STOP", "TSTP", "TTIN", "TTOU", "URG",
+        "XCPU", "XFSZ", "VTALRM", "PROF", "WINCH", "IO", "PWR", "SYS",
+    ];
+
+    match usize::try_from(signal - 1).ok().and_then(|i| NAMES.get(i)) {
+        Some(name) => format!("SIG{name}"),
+        None => format!("SIG{signal}"),
+    }
+}
+
+/// The numeric status of a process as `$?` reports it, `128 + N` if it was killed by the signal `N`
+pub fn exit_status_code(status: &ExitStatus) -> isize {
+    match (status.code(), terminating_signal(status)) {
+        (Some(code), _) => code as isize,
+        (None, Some(signal)) => 128 + signal as isize,
+        (None, None) => 1,
+    }
+}
+
+impl Display for OSReturn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OSReturn::Known(err) => write!(f, "{err:?}"),
+            OSReturn::Unknown(code) => write!(f, "{code}"),
+        }
+    }
+}
+
+/// What a prompt callback given to [`Shell::run_with_io`] renders the prompt from
+pub struct PromptContext<'a> {
+    pub cwd: &'a Path,
+    /// The status of the last command if it failed
+    pub last_return: Option<&'a OSReturn>,
+    /// How long the last command took, `None` before the first one
+    pub duration: Option<Duration>,
+}
+
+pub struct Shell {
+    io: IOWrapper,
+    editor: Editor,
+    /// A line that failed to parse along with where, given back to the line editor so it can be fixed
+    rejected_line: Option<(String, usize)>,
+    last_command_return: Option<OSReturn>,
+    last_command_duration: Option<Duration>,
+    /// The streams the currently executing command should use
+    streams: Streams,
+    config: Config,
+    prompt: Prompt,
+    vars: Variables,
+    /// The resource limits set using `ulimit`
+    limits: Limits,
+    /// Where events about the executed lines are sent, see [`Shell::events`]
+    events: Option<Sender<Event>>,
+    history: History,
+    /// Whether interactive sessions keep the history in `$HISTFILE`, privileged shells only keep it in memory
+    history_file: bool,
+    /// Where the commands run so far were found, see `hash`
+    command_hash: CommandHash,
+    /// The source of the `{ list; }` body of each defined function
+    functions: HashMap<String, String>,
+    /// The rules registered using the `complete` builtin by command name
+    completion_rules: HashMap<String, completion::Rule>,
+    /// Set by `exec` without a command so the redirections of the command being executed aren't undone
+    keep_redirections: bool,
+    /// `None` if disabled by the `events.cwd` setting
+    #[cfg(feature = "cwd-events")]
+    cwd_notifier: Option<cwd_events::CwdNotifier>,
+    /// The commands started in the background with `&`
+    jobs: Jobs,
+    /// Where jobs started in the background write instead of the output being forwarded to an embedder,
+    /// see [`Output::Forwarded`]
+    job_output: Option<PipeWriter>,
+    /// Whether lines are read from a user, who is told about the jobs started in the background
+    interactive: bool,
+    /// Set once `exit` ran outside of a subshell, the loops reading lines stop and the shell exits with this status
+    exiting: Option<u32>,
+    /// The `<(command)` substitutions of the pipelines being executed
+    process_substitutions: Vec<ProcessSubstitution>,
+    /// The descriptors `<&N` and `>&N` refer to, such as the pipes connected to coprocesses
+    descriptors: Descriptors,
+    /// Set by `set -e` so the commands being executed stop once one fails
+    errexit: bool,
+    /// Set by `set -o autorehash` so programs installed in `PATH` are found right away, see [`CommandHash`]
+    autorehash: bool,
+    /// Set by `set -o menu-complete` so Tab cycles through the candidates instead of listing them
+    menu_complete: bool,
+    /// Set by `set -o menu-select` so the candidates are also shown in a menu while cycling through them
+    menu_select: bool,
+    /// How many `&&`, `||` and `!` conditions are being executed, `set -e` ignores the commands failing in them
+    conditions: usize,
+    /// How many functions and sourced files are being executed, which `return` can leave
+    frames: usize,
+    /// How many command substitutions are nested in one another, see [`parser::MAX_DEPTH`]
+    substitutions: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum ShellError {
+    #[error("Failed with an IO error: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Exited with status {0}")]
+    ExitError(ExitStatus),
+    #[error("Syntax error: {0}")]
+    ParseError(#[from] ParseError),
+    // TODO: handle this better
+    #[error("Builtin error")]
+    BuiltinError,
+    #[error("command not found: {0}")]
+    CommandNotFound(String),
+    #[error("permission denied: {0}")]
+    NotExecutable(String),
+    #[error("{0}: readonly variable")]
+    ReadonlyVariable(String),
+    #[error("timed out: {0}")]
+    TimedOut(String),
+    #[error("bad file descriptor: {0}")]
+    BadDescriptor(String),
+    /// Executing the function or sourced file would nest them more than [`parser::MAX_DEPTH`] levels deep
+    #[error("{0}: functions and sourced files nested too deep")]
+    TooDeep(String),
+    /// A builtin failing with the status of the commands it executed, such as those of the file `source` executed
+    #[error("failed with status {0}")]
+    Status(u32),
+    /// Not an error but `return` leaving the function or sourced file being executed with the given status
+    #[error("returned {0}")]
+    Return(u32),
+    /// Not an error but `set -e` stopping the commands being executed after one failed with the given status
+    #[error("stopped by set -e with status {0}")]
+    Errexit(u32),
+    /// Not an error but `exit` leaving the shell, or the subshell being executed, with the given status
+    #[error("exited with status {0}")]
+    Exit(u32),
+}
+
+impl ShellError {
+    /// The numeric exit status a command failing with this error reports
+    pub fn status(&self) -> isize {
+        match self {
+            ShellError::ExitError(status) => exit_status_code(status),
+            ShellError::ParseError(_) => 2,
+            ShellError::IoError(_)
+            | ShellError::BuiltinError
+            | ShellError::ReadonlyVariable(_)
+            | ShellError::BadDescriptor(_)
+            | ShellError::TooDeep(_) => 1,
+            ShellError::CommandNotFound(_) => 127,
+            ShellError::NotExecutable(_) => 126,
+            ShellError::TimedOut(_) => 124,
+            ShellError::Status(code)
+            | ShellError::Return(code)
+            | ShellError::Errexit(code)
+            | ShellError::Exit(code) => *code as isize,
+        }
+    }
+
+    /// The symbolic name of the error as `$STATUS_NAME` reports it,
+    /// such as SafaOS's `ErrorStatus` name or the name of the signal that killed the command
+    pub fn status_name(&self) -> String {
+        let os_name = |os_return| match os_return {
+            OSReturn::Known(err) => Some(format!("{err:?}")),
+            OSReturn::Unknown(_) => None,
+        };
+
+        match self {
+            ShellError::ExitError(status) => match terminating_signal(status) {
+                Some(signal) => signal_name(signal),
+                None => os_name(OSReturn::from(*status))
+                    .unwrap_or_else(|| exit_status_code(status).to_string()),
+            },
+            ShellError::IoError(err) => os_name(OSReturn::from(io::Error::from(err.kind())))
+                .unwrap_or_else(|| format!("{:?}", err.kind())),
+            ShellError::ParseError(_) => "SyntaxError".to_string(),
+            ShellError::BuiltinError => "Failure".to_string(),
+            ShellError::CommandNotFound(_) => "CommandNotFound".to_string(),
+            ShellError::NotExecutable(_) => "NotExecutable".to_string(),
+            ShellError::ReadonlyVariable(_) => "ReadonlyVariable".to_string(),
+            ShellError::TimedOut(_) => "TimedOut".to_string(),
+            ShellError::BadDescriptor(_) => "BadDescriptor".to_string(),
+            ShellError::TooDeep(_) => "TooDeep".to_string(),
+            ShellError::Return(0) | ShellError::Exit(0) => "Success".to_string(),
+            ShellError::Status(_)
+            | ShellError::Return(_)
+            | ShellError::Errexit(_)
+            | ShellError::Exit(_) => "Failure".to_string(),
+        }
+    }
+}
+
+impl From<ShellError> for OSReturn {
+    fn from(err: ShellError) -> Self {
+        match err {
+            ShellError::IoError(err) => OSReturn::from(err),
+            ShellError::ExitError(status) => OSReturn::from(status),
+            ShellError::ParseError(_) => OSReturn::Unknown(2),
+            ShellError::BuiltinError => OSReturn::Unknown(-1),
+            ShellError::CommandNotFound(_) => OSReturn::Unknown(127),
+            ShellError::NotExecutable(_) => OSReturn::Unknown(126),
+            ShellError::ReadonlyVariable(_) => OSReturn::Unknown(1),
+            ShellError::TimedOut(_) => OSReturn::Unknown(124),
+            ShellError::BadDescriptor(_) | ShellError::TooDeep(_) => OSReturn::Unknown(1),
+            ShellError::Status(code)
+            | ShellError::Return(code)
+            | ShellError::Errexit(code)
+            | ShellError::Exit(code) => OSReturn::Unknown(code as isize),
+        }
+    }
+}
+
+impl Shell {
+    pub fn new() -> Shell {
+        Self::with_config(Config::load())
+    }
+
+    /// Creates a shell for services and other privileged contexts, which ignores the variables and the configuration
+    /// file inherited from whoever started it
+    pub fn privileged() -> Shell {
+        privilege::sanitize_environment();
+        let mut shell = Self::with_config(Config::empty());
+        shell.history_file = false;
+        shell
+    }
+
+    fn with_config(config: Config) -> Shell {
+        let prompt = Prompt::from_config(&config);
+
+        let mut vars = Variables::new();
+        if vars.get("USER").is_none() {
+            vars.set("USER", identity::user());
+        }
+        if vars.get("HOSTNAME").is_none() {
+            vars.set("HOSTNAME", identity::hostname());
+        }
+
+        Shell {
+            io: IOWrapper::new(),
+            editor: Editor::new(),
+            rejected_line: None,
+            last_command_return: None,
+            last_command_duration: None,
+            streams: Streams::default(),
+            prompt,
+            vars,
+            limits: Limits::default(),
+            events: None,
+            history: History::default(),
+            history_file: true,
+            command_hash: CommandHash::default(),
+            functions: HashMap::new(),
+            completion_rules: HashMap::new(),
+            keep_redirections: false,
+            jobs: Jobs::default(),
+            job_output: None,
+            interactive: false,
+            exiting: None,
+            process_substitutions: Vec::new(),
+            descriptors: Descriptors::default(),
+            errexit: false,
+            autorehash: false,
+            menu_complete: false,
+            menu_select: false,
+            conditions: 0,
+            frames: 0,
+            substitutions: 0,
+            #[cfg(feature = "cwd-events")]
+            cwd_notifier: (config.get("events.cwd") != Some("off"))
+                .then(cwd_events::CwdNotifier::default),
+            config,
+        }
+    }
+
+    /// Reads a line using the line editor, returns `None` if the input can no longer be read from
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        let (initial, cursor) = self.rejected_line.take().unwrap_or_default();
+        let colors = self.colors();
+        self.editor.suggestion_color = colors.foreground(Role::Suggestion, "2");
+        self.editor.external_editor = ["VISUAL", "EDITOR"]
+            .into_iter()
+            .find_map(|name| self.vars.get(name))
+            .map(|editor| editor.into_owned());
+        let functions = &self.functions;
+        let command_hash = &mut self.command_hash;
+        let search_path = search_path();
+        // the line is highlighted after each key, names are only looked up once per line
+        let mut known_commands = HashMap::new();
+        let mut is_command = |name: &str| {
+            *known_commands.entry(name.to_string()).or_insert_with(|| {
+                functions.contains_key(name)
+                    || builtin::BUILTIN_COMMANDS.contains_key(name)
+                    || builtin::fallback(name).is_some()
+                    || (!name.contains('/') && command_hash.lookup(name, &search_path).is_some())
+                    || Path::new(name).exists()
+            })
+        };
+
+        let terminal = terminal::detect();
+        self.editor.line_break_glyph = if terminal.unicode { "↵" } else { "^J" };
+        self.editor.columns = terminal::size().map(|(columns, _)| columns);
+        self.editor.completion_style = if self.menu_select {
+            CompletionStyle::Menu
+        } else if self.menu_complete {
+            CompletionStyle::Cycle
+        } else {
+            CompletionStyle::List
+        };
+        // terminals mark pasted text so its lines are inserted in the line rather than executed one by one
+        if terminal.escapes {
+            _ = write!(self.io.stdout, "\x1b[?2004h");
+        }
+
+        let vars = &self.vars;
+        let rules = &self.completion_rules;
+        let history = &self.history;
+        let line = self
+            .editor
+            .readline(
+                prompt,
+                &mut self.io,
+                (&initial, cursor),
+                |line| highlight::highlight(line, &colors, &mut is_command),
+                |line| {
+                    let commands = history
+                        .entries()
+                        .iter()
+                        .map(|entry| entry.command.as_str())
+                        .collect::<Vec<_>>();
+                    expand::expand_line(line, vars, &commands)
+                },
+                |line, cursor| {
+                    let candidates =
+                        completion::complete_with_rules(line, cursor, vars, rules, functions);
+                    (completion::word_start(line, cursor), candidates)
+                },
+            )
+            .ok()
+            .flatten();
+
+        if terminal.escapes {
+            _ = write!(self.io.stdout, "\x1b[?2004l");
+            _ = self.io.stdout.flush();
+        }
+        line
+    }
+
+    fn prompt(&mut self) -> Option<String> {
+        #[cfg(feature = "cwd-events")]
+        if let Some(notifier) = &mut self.cwd_notifier {
+            notifier.update();
+        }
+
+        // with `$SAFA_NOTIFY` set to `1` jobs tell the user they are done right away, even while a line is being edited
+        let notify = self
+            .vars
+            .get("SAFA_NOTIFY")
+            .is_some_and(|notify| notify == "1");
+        self.jobs.set_notify(notify);
+        self.command_hash.set_autorehash(self.autorehash);
+        self.report_done_jobs();
+        self.update_window_size();
+        // puts back the title a command changed now that it has completed
+        self.show_title(None);
+
+        let cwd = std::env::current_dir().expect("Failed to get current directory");
+        let context = PromptContext {
+            cwd: &cwd,
+            last_return: self.last_command_return.as_ref(),
+            duration: self.last_command_duration,
+        };
+        let prompt = match self.vars.get("PS1") {
+            Some(template) => prompt::expand_escapes(&template, &context),
+            None => {
+                let columns = self
+                    .vars
+                    .get("COLUMNS")
+                    .and_then(|columns| columns.parse().ok());
+                self.prompt.render(&context, &self.colors(), columns)
+            }
+        };
+
+        let line = self.read_line(&prompt)?;
+        let pasted = self.io.take_pending_lines();
+        if pasted.is_empty() {
+            return Some(line);
+        }
+
+        let lines = [line].into_iter().chain(pasted).collect::<Vec<_>>();
+        if self.confirm_paste(&lines) {
+            Some(lines.join("\n"))
+        } else {
+            Some(String::new())
+        }
+    }
+
+    /// Sets `$COLUMNS` and `$LINES` to the size of the terminal, which is checked before each prompt to notice resizes
+    fn update_window_size(&mut self) {
+        if let Some((columns, lines)) = terminal::size() {
+            self.vars.set("COLUMNS", columns.to_string());
+            self.vars.set("LINES", lines.to_string());
+        }
+    }
+
+    /// Tells the user about the background jobs that are done since the last prompt
+    fn report_done_jobs(&mut self) {
+        for notification in self.jobs.take_done() {
+            // the terminal is in raw mode, line breaks need a carriage return
+            _ = write!(self.io.stdout, "{notification}\r\n");
+        }
+        _ = self.io.stdout.flush();
+    }
+
+    /// Shows the lines pasted at once and asks whether to execute them,
+    /// unless the confirmation was disabled by setting `$SAFA_CONFIRM_PASTE` to `0`
+    fn confirm_paste(&mut self, lines: &[String]) -> bool {
+        // input piped into the shell arrives all at once too
+        if !io::stdin().is_terminal()
+            || self
+                .vars
+                .get("SAFA_CONFIRM_PASTE")
+                .is_some_and(|confirm| confirm == "0")
+        {
+            return true;
+        }
+
+        // the terminal is in raw mode, line breaks need a carriage return
+        let notice = self.colors().foreground(Role::Notice, "33");
+        let stdout = &mut self.io.stdout;
+        let pasted = i18n::format(Message::PastedLines, &[&lines.len()]);
+        _ = write!(stdout, "\r\n\x1b[{notice}m{pasted}\x1b[0m\r\n");
+        for line in lines {
+            _ = write!(stdout, "  {line}\r\n");
+        }
+        _ = write!(stdout, "{}", i18n::text(Message::ExecutePasted));
+        _ = stdout.flush();
+
+        let answer = self.io.read_byte();
+        let confirmed = matches!(answer, Some(b'y' | b'Y'));
+        _ = write!(self.io.stdout, "{}\r\n", if confirmed { "y" } else { "n" });
+        _ = self.io.stdout.flush();
+        confirmed
+    }
+
+    /// Executes a line read from the user, reporting errors and keeping track of the status for the prompt,
+    /// returns the numeric status of the line
+    fn execute_line(&mut self, line: &str) -> isize {
+        let started = SystemTime::now();
+        let start = Instant::now();
+
+        let status = match self.events.clone() {
+            None => self.report_line(line),
+            Some(events) => match self.execute_line_with_events(line, events) {
+                Ok(status) => status,
+                Err(err) => {
+                    let err = ShellError::from(err);
+                    self.report_error(&err);
+                    err.status()
+                }
+            },
+        };
+
+        let command = line.trim();
+        if !command.is_empty() {
+            let duration = start.elapsed();
+            self.last_command_duration = Some(duration);
+            self.vars
+                .set("CMD_DURATION", duration.as_millis().to_string());
+            self.report_time(command, duration);
+            self.record_history(line, started, duration, status);
+        }
+        status
+    }
+
+    /// Records `line` in the history unless `$HISTCONTROL` says otherwise, within the `$HISTSIZE` and
+    /// `$HISTFILESIZE` limits
+    fn record_history(
+        &mut self,
+        line: &str,
+        started: SystemTime,
+        duration: Duration,
+        status: isize,
+    ) {
+        let limit = |name| {
+            self.vars
+                .get(name)
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(history::DEFAULT_SIZE)
+        };
+        let (size, file_size) = (limit("HISTSIZE"), limit("HISTFILESIZE"));
+        self.history.set_limits(size, file_size);
+
+        let control = self.vars.get("HISTCONTROL").unwrap_or_default();
+        if !self.history.ignores(line, &control) {
+            let recorded = self.history.record(line.trim(), started, duration, status);
+            if let Err(err) = recorded {
+                _ = writeln!(self.streams.stderr, "safa: history: {err}");
+            }
+        }
+        self.sync_editor_history();
+    }
+
+    /// Lets the line editor recall the lines in the history
+    pub(crate) fn sync_editor_history(&mut self) {
+        self.editor.set_history(
+            self.history
+                .entries()
+                .iter()
+                .map(|entry| entry.command.as_str()),
+        );
+    }
+
+    /// Loads the history kept in `$HISTFILE`, or `$HOME/.safa_history`, and appends the executed lines to it
+    fn open_history_file(&mut self) {
+        let path = match (self.vars.get("HISTFILE"), self.vars.get("HOME")) {
+            (Some(path), _) if path.is_empty() => return,
+            (Some(path), _) => PathBuf::from(&*path),
+            (None, Some(home)) => Path::new(&*home).join(history::DEFAULT_FILE),
+            (None, None) => return,
+        };
+
+        match self.history.open_file(path) {
+            Ok(()) => self.sync_editor_history(),
+            Err(err) => _ = writeln!(self.streams.stderr, "safa: history: {err}"),
+        }
+    }
+
+    /// Syncs the history file before the shell exits
+    pub(crate) fn close_history(&mut self) {
+        if let Err(err) = self.history.sync() {
+            _ = writeln!(self.streams.stderr, "safa: history: {err}");
+        }
+    }
+
+    /// Syncs the history file and, if the shell is interactive, hangs up the jobs still running before the shell exits
+    pub(crate) fn prepare_exit(&mut self) {
+        self.close_history();
+        if self.interactive {
+            self.jobs.hang_up();
+        }
+    }
+
+    /// Prints how long `command` took if it took at least `$REPORTTIME` seconds
+    fn report_time(&mut self, command: &str, duration: Duration) {
+        let threshold = self
+            .vars
+            .get("REPORTTIME")
+            .and_then(|seconds| seconds.parse::<f64>().ok())
+            .filter(|seconds| *seconds >= 0.);
+
+        if threshold.is_some_and(|threshold| duration.as_secs_f64() >= threshold) {
+            let duration = time::format_duration(duration);
+            let message = i18n::format(Message::CommandTook, &[&command, &duration]);
+            _ = writeln!(self.streams.stderr, "safa: {message}");
+        }
+    }
+
+    /// Executes `line`, reports its errors and records its status for the prompt
+    fn report_line(&mut self, line: &str) -> isize {
+        match self.execute(line) {
+            Err(ShellError::Exit(code)) => {
+                self.exiting = Some(code);
+                self.last_command_return = (code > 0).then_some(OSReturn::Unknown(code as isize));
+                code as isize
+            }
+            Err(err) => {
+                // the line editor only edits a single line
+                if let (ShellError::ParseError(parse_error), false) = (&err, line.contains('\n')) {
+                    self.rejected_line = Some((line.to_string(), parse_error.offset));
+                }
+                self.report_error(&err);
+                let status = err.status();
+                self.last_command_return = Some(err.into());
+                status
+            }
+            Ok(code) => {
+                self.last_command_return = (code > 0).then_some(OSReturn::Unknown(code as isize));
+                code as isize
+            }
+        }
+    }
+
+    /// Executes a line like [`Self::execute_line`] but forwards everything written to stdout and stderr to `output`
+    fn execute_line_into(
+        &mut self,
+        line: &str,
+        mut output: impl Write + Clone + Send + 'static,
+    ) -> io::Result<isize> {
+        let (mut reader, writer) = std::io::pipe()?;
+        self.streams.stdout = Output::Forwarded(writer.try_clone()?);
+        self.streams.stderr = Output::Forwarded(writer);
+
+        // jobs outlive the line, their output is forwarded until the last of them exits without anyone waiting for it
+        let (mut job_reader, job_writer) = std::io::pipe()?;
+        self.job_output = Some(job_writer);
+        let mut job_output = output.clone();
+        std::thread::spawn(move || {
+            _ = io::copy(&mut job_reader, &mut job_output);
+            _ = job_output.flush();
+        });
+
+        let forwarder = std::thread::spawn(move || {
+            io::copy(&mut reader, &mut output)?;
+            Ok::<_, io::Error>(output)
+        });
+
+        let status = self.execute_line(line);
+
+        // drops the shell's ends of the pipes, the forwarder finishes once the commands' copies are gone too
+        self.streams.stdout = Output::Stdout;
+        self.streams.stderr = Output::Stderr;
+        self.job_output = None;
+        let mut output = forwarder.join().expect("output forwarder panicked")?;
+        output.flush()?;
+        Ok(status)
+    }
+
+    /// Runs the shell loop on the process's terminal until the input ends, or exits with the status given to `exit`
+    pub fn run(mut self) {
+        self.interactive = true;
+        if self.history_file {
+            self.open_history_file();
+        }
+        system::catch_hang_up();
+        system::catch_resize();
+        while let Some(line) = self.prompt() {
+            // reading from the closed terminal ends the input too, unless the line was read before it was closed
+            if system::hung_up() {
+                break;
+            }
+            if !line.trim().is_empty() {
+                self.show_title(Some(&line));
+            }
+            self.execute_line(&line);
+            if self.exiting.is_some() {
+                break;
+            }
+        }
+        self.prepare_exit();
+        if let Some(code) = self.exiting {
+            std::process::exit(code as i32);
+        }
+    }
+
+    /// The colors things are drawn in, read again each time so changes to the variables show up right away
+    fn colors(&self) -> Colors {
+        Colors::load(&self.vars, &self.config)
+    }
+
+    /// Prints the message of the day, see [`motd`]
+    pub fn show_motd(&mut self) {
+        let motd = motd::render(&self.vars, &self.colors());
+        _ = write!(self.streams.stdout, "{motd}");
+        _ = self.streams.stdout.flush();
+    }
+
+    /// Sets the terminal's title to `command` and the current directory, or to the shell and the current directory
+    /// if `command` is `None`, when enabled by `prompt title on`
+    fn show_title(&mut self, command: Option<&str>) {
+        if !self.prompt.title || !terminal::detect().title {
+            return;
+        }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        self.write_title(&prompt::title(command, &cwd));
+    }
+
+    /// Sets the terminal's title to `title`, an empty title lets the terminal show its own
+    pub(crate) fn write_title(&mut self, title: &str) {
+        _ = write!(self.io.stdout, "{}", terminal::title_sequence(title));
+        _ = self.io.stdout.flush();
+    }
+
+    /// Runs the shell loop reading lines from `input` instead of the process's terminal until `input` ends or `exit` runs,
+    /// `render_prompt` builds the prompt shown before each line
+    ///
+    /// Everything the executed commands write to stdout and stderr is forwarded to `output`,
+    /// the commands are given an empty stdin since `input` belongs to the line editor
+    pub fn run_with_io(
+        mut self,
+        input: impl Read + 'static,
+        output: impl Write + Send + 'static,
+        mut render_prompt: impl FnMut(&PromptContext) -> String,
+    ) -> io::Result<()> {
+        let output = SharedOutput::new(output);
+        self.io = IOWrapper::with_streams(input, output.clone());
+        self.streams.stdin = Input::Null;
+        self.interactive = true;
+
+        loop {
+            self.report_done_jobs();
+            let cwd = std::env::current_dir()?;
+            let prompt = render_prompt(&PromptContext {
+                cwd: &cwd,
+                last_return: self.last_command_return.as_ref(),
+                duration: self.last_command_duration,
+            });
+            let Some(line) = self.read_line(&prompt) else {
+                return Ok(());
+            };
+
+            self.execute_line_into(&line, output.clone())?;
+            if self.exiting.take().is_some() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
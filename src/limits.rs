@@ -0,0 +1,166 @@
+//! Resource limits applied to the programs spawned by the shell, configured using the `ulimit` builtin
+
+use std::{fmt::Display, process::Command};
+
+/// Whether this system lets the shell limit the resources of the programs it spawns,
+/// on other systems the limits can only be listed
+pub const SUPPORTED: bool = cfg!(target_os = "safaos");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// The maximum amount of memory in KiB
+    Memory,
+    /// The maximum number of open resource handles
+    Handles,
+    /// The scheduling priority
+    Priority,
+}
+
+impl Resource {
+    pub const ALL: [Resource; 3] = [Resource::Memory, Resource::Handles, Resource::Priority];
+
+    pub const fn flag(&self) -> char {
+        match self {
+            Resource::Memory => 'm',
+            Resource::Handles => 'n',
+            Resource::Priority => 'p',
+        }
+    }
+
+    pub fn from_flag(flag: char) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|resource| resource.flag() == flag)
+    }
+
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Resource::Memory => "max memory size (KiB)",
+            Resource::Handles => "open handles",
+            Resource::Priority => "scheduling priority",
+        }
+    }
+
+    /// The name of the resource in the host's `/proc/self/limits`
+    #[cfg(not(target_os = "safaos"))]
+    const fn host_name(&self) -> &'static str {
+        match self {
+            Resource::Memory => "Max address space",
+            Resource::Handles => "Max open files",
+            Resource::Priority => "Max nice priority",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Unlimited,
+    Value(u64),
+    /// The system doesn't say
+    Unknown,
+}
+
+impl Limit {
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "unlimited" => Some(Limit::Unlimited),
+            _ => str.parse().ok().map(Limit::Value),
+        }
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Unlimited => write!(f, "unlimited"),
+            Limit::Value(value) => write!(f, "{value}"),
+            Limit::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The limits set using `ulimit`, resources without a limit are inherited from the shell
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    memory: Option<Limit>,
+    handles: Option<Limit>,
+    priority: Option<Limit>,
+}
+
+impl Limits {
+    fn slot(&mut self, resource: Resource) -> &mut Option<Limit> {
+        match resource {
+            Resource::Memory => &mut self.memory,
+            Resource::Handles => &mut self.handles,
+            Resource::Priority => &mut self.priority,
+        }
+    }
+
+    fn configured(&self, resource: Resource) -> Option<Limit> {
+        match resource {
+            Resource::Memory => self.memory,
+            Resource::Handles => self.handles,
+            Resource::Priority => self.priority,
+        }
+    }
+
+    pub fn set(&mut self, resource: Resource, limit: Limit) {
+        *self.slot(resource) = Some(limit);
+    }
+
+    /// Returns the limit spawned programs get for `resource`
+    pub fn get(&self, resource: Resource) -> Limit {
+        self.configured(resource)
+            .unwrap_or_else(|| inherited(resource))
+    }
+
+    /// Applies the limits to a program that is about to be spawned
+    pub fn apply(&self, command: &mut Command) {
+        if !SUPPORTED {
+            return;
+        }
+
+        // SafaOS's process spawning doesn't take limits, the child is handed them through its environment
+        for resource in Resource::ALL {
+            if let Some(limit) = self.configured(resource) {
+                command.env(env_name(resource), limit.to_string());
+            }
+        }
+    }
+}
+
+/// The environment variable a limit is passed down to spawned programs in
+fn env_name(resource: Resource) -> String {
+    format!("SAFA_LIMIT_{}", resource.flag().to_ascii_uppercase())
+}
+
+/// Returns the limit the shell itself runs with
+#[cfg(target_os = "safaos")]
+fn inherited(resource: Resource) -> Limit {
+    std::env::var(env_name(resource))
+        .ok()
+        .and_then(|limit| Limit::parse(&limit))
+        .unwrap_or(Limit::Unlimited)
+}
+
+/// Returns the limit the shell itself runs with
+#[cfg(not(target_os = "safaos"))]
+fn inherited(resource: Resource) -> Limit {
+    let Ok(limits) = std::fs::read_to_string("/proc/self/limits") else {
+        return Limit::Unknown;
+    };
+
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix(resource.host_name()))
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(|soft| match soft {
+            "unlimited" => Limit::Unlimited,
+            soft => match (resource, soft.parse::<u64>()) {
+                (Resource::Memory, Ok(bytes)) => Limit::Value(bytes / 1024),
+                (_, Ok(value)) => Limit::Value(value),
+                (_, Err(_)) => Limit::Unknown,
+            },
+        })
+        .unwrap_or(Limit::Unknown)
+}
@@ -1,296 +1,156 @@
-const MULTI_PATH_SEP: &str = if cfg!(any(target_os = "windows", target_os = "safaos")) {
-    ";"
-} else {
-    ":"
-};
-
-use std::{
-    fmt::Display,
-    io::{self},
-    path::Path,
-    process::{Command, ExitStatus},
-};
-
-use lexer::Lexer;
-use noline::{
-    builder::EditorBuilder, history::UnboundedHistory, line_buffer::UnboundedBuffer,
-    sync_editor::Editor,
-};
-use thiserror::Error;
-mod builtin;
-mod lexer;
-mod readline;
-
-use cfg_if::cfg_if;
+/// The default maximum value `SHLVL` can reach before the shell refuses to start,
+/// can be overridden using the `SAFA_SHELL_MAX_SHLVL` environment variable
+const DEFAULT_MAX_SHLVL: usize = 64;
 
-use crate::readline::IOWrapper;
+mod args;
 
-// There is kinda of no need to use this, but it's nice to have
-// support on both the host system and SafaOS
-cfg_if! {
-    if #[cfg(target_os = "safaos")] {
-        use safa_api::errors::ErrorStatus;
-        pub enum OSReturn {
-            Known(ErrorStatus),
-            Unknown(isize),
-        }
-
-        impl From<ExitStatus> for OSReturn {
-            fn from(status: ExitStatus) -> Self {
-                let code = status.code().unwrap_or(0);
-                if code.is_positive() || code == 0 || status.success() {
-                    return OSReturn::Unknown(code as isize);
-                }
-
-                let error_status = ErrorStatus::try_from((-code) as u16);
-                match error_status {
-                    Ok(err) => OSReturn::Known(err),
-                    Err(()) => OSReturn::Unknown(code as isize),
-                }
-            }
-        }
+#[cfg(not(target_os = "safaos"))]
+use std::io::{IsTerminal, Read};
 
-        impl From<io::Error> for OSReturn {
-            fn from(err: io::Error) -> Self {
-                OSReturn::Known(safa_api::errors::err_from_io_error_kind(err.kind()))
-            }
-        }
-    } else {
-        use std::convert::Infallible;
-        pub enum OSReturn {
-            Known(Infallible),
-            Unknown(isize),
-        }
-
-        impl From<ExitStatus> for OSReturn {
-            fn from(status: ExitStatus) -> Self {
-                assert!(!status.success());
-                let code = status.code().unwrap_or(0);
-                Self::Unknown(code as isize)
-            }
-        }
+use args::{Args, Mode};
+use safa::{
+    Shell, ShellError, completion,
+    i18n::{self, Message},
+    vars::Variables,
+    version,
+};
 
-        impl From<io::Error> for OSReturn {
-            fn from(err: io::Error) -> Self {
-                Self::Unknown(1)
-            }
-        }
+/// Increments `SHLVL` for the current shell and its children,
+/// fails if the new level exceeds the configured ceiling which usually means a script is recursively invoking the shell
+fn enter_shell_level(program: &str) -> Result<(), ()> {
+    let parse_var = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+    };
+
+    let level = parse_var("SHLVL").unwrap_or(0) + 1;
+    let max_level = parse_var("SAFA_SHELL_MAX_SHLVL").unwrap_or(DEFAULT_MAX_SHLVL);
+
+    if level > max_level {
+        let message = i18n::format(Message::ShellLevelExceeded, &[&max_level]);
+        eprintln!("{program}: {message}");
+        return Err(());
     }
-}
 
-impl Display for OSReturn {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            OSReturn::Known(err) => write!(f, "{err:?}"),
-            OSReturn::Unknown(code) => write!(f, "{code}"),
-        }
+    unsafe {
+        std::env::set_var("SHLVL", level.to_string());
     }
+    Ok(())
 }
 
-struct Shell {
-    io: IOWrapper,
-    editor: Editor<UnboundedBuffer, UnboundedHistory>,
-    last_command_return: Option<OSReturn>,
-}
-
-#[derive(Debug, Error)]
-pub enum ShellError {
-    #[error("Failed with an IO error: {0}")]
-    IoError(#[from] io::Error),
-    #[error("Exited with status {0}")]
-    ExitError(ExitStatus),
-    // TODO: handle this better
-    #[error("Builtin error")]
-    BuiltinError,
-}
-
-impl From<ShellError> for OSReturn {
-    fn from(err: ShellError) -> Self {
-        match err {
-            ShellError::IoError(err) => OSReturn::from(err),
-            ShellError::ExitError(status) => OSReturn::from(status),
-            ShellError::BuiltinError => OSReturn::Unknown(-1),
-        }
+fn main() {
+    #[cfg(feature = "env-bootstrap")]
+    safa::bootstrap::environment();
+    unsafe {
+        std::env::set_var("SHELL", "sys:/bin/safa");
     }
-}
-
-impl Shell {
-    fn new() -> Shell {
-        let mut io = IOWrapper::new();
 
-        let editor = EditorBuilder::new_unbounded()
-            .with_unbounded_history()
-            .build_sync(&mut io)
-            .unwrap();
+    let mut args = std::env::args();
+    let program = args.next().expect("no program name passed");
 
-        Shell {
-            io,
-            editor,
-            last_command_return: None,
-        }
+    if enter_shell_level(&program).is_err() {
+        std::process::exit(1);
     }
 
-    fn prompt(&mut self) -> String {
-        use std::fmt::Write;
-        let cwd = std::env::current_dir().expect("Failed to get current directory");
-
-        let mut prompt = String::new();
-        write!(prompt, "\x1b[35m{}\x1b[0m ", cwd.display()).unwrap();
-        if let Some(code) = &self.last_command_return {
-            write!(prompt, "\x1b[31m[{code}]\x1b[0m ").unwrap();
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{program}: {err}");
+            eprintln!("{}", i18n::text(Message::Usage));
+            std::process::exit(2);
         }
-        write!(prompt, "# ").unwrap();
-
-        self.editor
-            .readline(&*prompt, &mut self.io)
-            .expect("Failed to readline")
-            .to_string()
-    }
-
-    fn execute_program(&self, program: &str, args: &[&str]) -> Result<u32, ShellError> {
-        let path = std::env::var("PATH").expect("Failed to get the PATH Environment variable");
-        let cwd = std::env::current_dir().expect("Failed to get CWD");
-
-        let handle_child = |mut child: std::process::Child| {
-            let results = child.wait()?;
-            if !results.success() {
-                Err(ShellError::ExitError(results))
-            } else {
-                Ok(results.code().unwrap_or(0) as u32)
-            }
-        };
-
-        let path = path.split(MULTI_PATH_SEP);
-        let path = path.map(|p| Path::new(p));
-        let path = path.chain([cwd.as_path()].into_iter());
-
-        for dir in path {
-            let program_path = dir.join(program);
-            if !program_path.exists() {
-                continue;
-            }
-
-            let command = Command::new(program_path).args(args).spawn();
-            match command {
-                Ok(child) => return handle_child(child),
-                Err(err) => return Err(ShellError::IoError(err)),
-            }
+    };
+    // programs following the convention are told too, which is what's wanted when the output goes to a log
+    if args.no_color {
+        unsafe {
+            std::env::set_var("NO_COLOR", "1");
         }
-
-        let command = Command::new(program).args(args).spawn()?;
-        handle_child(command)
     }
 
-    fn execute(&mut self, input: &str) -> Result<u32, ShellError> {
-        let mut command = Lexer::new(input).map(|token| token.as_str());
-        let Some(program) = command.next() else {
-            return Ok(0);
-        };
-        let program = program.as_ref();
-
-        let args = command.collect::<Vec<_>>();
-        let args = args.iter().map(|t| t.as_ref()).collect::<Vec<_>>();
-
-        if let Some(f) = builtin::BUILTIN_COMMANDS.get(program) {
-            return f(self, &args).map(|()| 0);
+    let new_shell = || {
+        if args.privileged {
+            Shell::privileged()
+        } else {
+            Shell::new()
         }
+    };
 
-        self.execute_program(program, &args)
-    }
-
-    fn run(mut self) {
-        loop {
-            let input = self.prompt();
-            match self.execute(&input) {
-                Err(err) => {
-                    if !matches!(err, ShellError::ExitError(_))
-                        && !matches!(err, ShellError::BuiltinError)
-                    {
-                        println!("Shell: {err}");
-                    }
-
-                    self.last_command_return = Some(err.into());
-                }
-                Ok(code) => {
-                    self.last_command_return =
-                        (code > 0).then_some(OSReturn::Unknown(code as isize));
-                }
+    let mut shell = match args.mode {
+        Mode::Help => {
+            println!("{}", i18n::text(Message::Usage));
+            return;
+        }
+        Mode::Version => {
+            println!("{}", version::report(args.json));
+            return;
+        }
+        Mode::Complete { line, cursor } => {
+            let cursor = cursor.unwrap_or(line.len());
+            for candidate in completion::complete(&line, cursor, &Variables::new()) {
+                println!("{candidate}");
             }
+            return;
         }
-    }
-}
-
-fn main() -> Result<(), ()> {
-    unsafe {
-        std::env::set_var("SHELL", "sys:/bin/safa");
-    }
-
-    let mut args = std::env::args();
-    let program = args.next().expect("no program name passed");
-
-    let mut interactive = false;
-
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "-i" | "--interactive" => interactive = true,
-            "-c" => {
-                let Some(command) = args.next() else {
-                    println!("{program}: `-c` expected command");
-                    return Err(());
-                };
-
-                let mut shell = Shell::new();
-                return if let Err(err) = shell.execute(command.as_str()) {
-                    println!("{program}: {err}");
-                    Err(())
-                } else {
-                    Ok(())
-                };
+        Mode::Serve(address) => {
+            if let Err(err) = new_shell().serve(&address) {
+                eprintln!("{program}: --serve: {err}");
+                std::process::exit(1);
             }
-            "--help" => {
-                println!("usage: {program} [-i|--interactive|-c [command]]");
-                return Ok(());
+            return;
+        }
+        Mode::Command(command) => {
+            let mut shell = new_shell();
+            if let Some((name, rest)) = args.rest.split_first() {
+                shell.set_positional(name, rest);
+            } else {
+                shell.set_positional(&program, &[]);
             }
-            el => {
-                println!("{program}: unexpected argument `{el}`");
-                println!("usage: {program} [-i|--interactive|-c [command]]");
-                return Err(());
+            exit_with(&mut shell, |shell| shell.execute(&command));
+        }
+        Mode::Script(script) => {
+            let mut shell = new_shell();
+            exit_with(&mut shell, |shell| {
+                shell.execute_script(&script, &args.rest)
+            });
+        }
+        // SafaOS's std can't tell whether stdin is a terminal yet, shells there are only started by terminals
+        #[cfg(not(target_os = "safaos"))]
+        Mode::Interactive if !args.interactive && !std::io::stdin().is_terminal() => {
+            let mut script = String::new();
+            if let Err(err) = std::io::stdin().read_to_string(&mut script) {
+                eprintln!("{program}: stdin: {err}");
+                std::process::exit(1);
             }
+            let mut shell = new_shell();
+            shell.set_positional(&program, &args.rest);
+            exit_with(&mut shell, |shell| shell.execute(&script));
         }
-    }
-
-    if interactive {
-        print!("\x1B[38;2;255;192;203m");
-        print!(
-            r#"
- ,---.             ,---.           ,-----.   ,---.
-'   .-'   ,--,--. /  .-'  ,--,--. '  .-.  ' '   .-'
-`.  `-.  ' ,-.  | |  `-, ' ,-.  | |  | |  | `.  `-.
-.-'    | \ '-'  | |  .-' \ '-'  | '  '-'  ' .-'    |
-`-----'   `--`--' `--'    `--`--'  `-----'  `-----'
-        "#,
-        );
-
-        print!("\x1B[38;2;200;200;200m");
-        print!(
-            r#"
-| Welcome to SafaOS!
-| you are currently in ram:/, a playground
-| init ramdisk has been mounted at sys:/
-| sys:/bin is available in your PATH check it out for some binaries
-| the command `help` will provide a list of builtin commands and some terminal usage guide
-| to start GUI type opal-wm, ctrl+shift+T will open a terminal, to drag a window hold ctrl and the window.
-        "#
-        );
-
-        println!("\x1B[0m");
+        Mode::Interactive => new_shell(),
+    };
+
+    // programs starting login shells put a `-` before their name, as `login` does
+    let login = args.login || program.starts_with('-');
+    let quiet = args.quiet || std::env::var("SAFA_SHELL_QUIET").is_ok_and(|quiet| quiet == "1");
+    if login && !quiet {
+        shell.show_motd();
     }
 
     assert!(
-        readline::enter_raw_mode(),
+        safa::enter_raw_mode(),
         "FIXME: Shell isn't supported on targets without raw mode"
     );
-    let shell = Shell::new();
+    shell.set_positional(&program, &[]);
     shell.run();
-    Ok(())
+}
+
+/// Runs `f` then exits with the status it resulted in, reporting its error if any
+fn exit_with(shell: &mut Shell, f: impl FnOnce(&mut Shell) -> Result<u32, ShellError>) -> ! {
+    match f(shell) {
+        Err(err) => {
+            shell.report_error(&err);
+            std::process::exit(err.status() as i32);
+        }
+        Ok(code) => std::process::exit(code as i32),
+    }
 }
@@ -0,0 +1,37 @@
+//! The message of the day interactive login shells show when they start, the contents of `sys:/etc/motd` as they are
+//! if the file exists, otherwise the SafaOS logo and the welcome text, so an empty file shows nothing
+//!
+//! `$SAFA_MOTD` names another file to read it from
+
+use crate::{
+    colors::{Colors, Role},
+    i18n::{self, Message},
+    vars::Variables,
+};
+
+/// Where the message is read from, unless `$SAFA_MOTD` is set
+const PATH: &str = "sys:/etc/motd";
+
+/// The SafaOS logo shown above the welcome text
+const LOGO: &str = r#"
+ ,---.             ,---.           ,-----.   ,---.
+'   .-'   ,--,--. /  .-'  ,--,--. '  .-.  ' '   .-'
+`.  `-.  ' ,-.  | |  `-, ' ,-.  | |  | |  | `.  `-.
+.-'    | \ '-'  | |  .-' \ '-'  | '  '-'  ' .-'    |
+`-----'   `--`--' `--'    `--`--'  `-----'  `-----'
+        "#;
+
+/// Returns the message of the day, the logo and the welcome text are drawn in the `Banner` and `BannerText` colors
+pub fn render(vars: &Variables, colors: &Colors) -> String {
+    let path = vars.get("SAFA_MOTD");
+    if let Ok(message) = std::fs::read_to_string(path.as_deref().unwrap_or(PATH)) {
+        return message;
+    }
+
+    let logo = colors.foreground(Role::Banner, "38;2;255;192;203");
+    let text = colors.foreground(Role::BannerText, "38;2;200;200;200");
+    format!(
+        "\x1b[{logo}m{LOGO}\x1b[0;{text}m\n{}\n        \x1b[0m\n",
+        i18n::text(Message::Banner)
+    )
+}
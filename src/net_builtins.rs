@@ -0,0 +1,159 @@
+//! Minimal connectivity tests for bringing up networking on SafaOS, enabled by the `net-utils` feature
+//!
+//! Like the file utilities, they are only used when no program with the same name is found in `PATH`
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use phf::phf_map;
+
+use crate::{
+    Shell, ShellError,
+    builtin::{BuiltInCommandList, usage},
+};
+
+/// How long a connection attempt may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// The number of connection attempts `ping` makes by default
+const DEFAULT_PING_COUNT: u32 = 4;
+
+pub static NET_BUILTINS: BuiltInCommandList = phf_map! {
+    "ping" => ping,
+    "httpget" => httpget,
+};
+
+/// Resolves `host`, which may include a port, using `default_port` if it doesn't
+fn resolve(host: &str, default_port: u16) -> io::Result<SocketAddr> {
+    let addrs = match host.to_socket_addrs() {
+        Ok(addrs) => addrs,
+        Err(_) => (host, default_port).to_socket_addrs()?,
+    };
+
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address found"))
+}
+
+/// Checks that `host` is reachable by connecting to it over TCP, ICMP would need raw sockets
+fn ping(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (host, count) = match args {
+        [host] => (*host, DEFAULT_PING_COUNT),
+        [host, "-c", count] | ["-c", count, host] if count.parse::<u32>().is_ok() => {
+            (*host, count.parse().unwrap())
+        }
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage("ping <host[:port]> [-c count]")
+            )?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let addr = match resolve(host, 80) {
+        Ok(addr) => addr,
+        Err(err) => {
+            writeln!(shell.streams.stderr, "ping: {host}: {err}")?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let mut succeeded = 0;
+    for _ in 0..count {
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(_) => {
+                succeeded += 1;
+                writeln!(
+                    shell.streams.stdout,
+                    "connected to {addr} in {:.1} ms",
+                    start.elapsed().as_secs_f64() * 1000.
+                )?;
+            }
+            Err(err) => writeln!(shell.streams.stdout, "{addr}: {err}")?,
+        }
+    }
+
+    writeln!(
+        shell.streams.stdout,
+        "{count} attempts, {succeeded} succeeded"
+    )?;
+    if succeeded == 0 {
+        return Err(ShellError::BuiltinError);
+    }
+    Ok(())
+}
+
+/// Fetches `url` using HTTP/1.0, returns the body
+fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("only http:// URLs are supported"))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+
+    let addr = resolve(host, 80)?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: safa\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("malformed HTTP response"))?;
+    let header = String::from_utf8_lossy(&response[..header_end]);
+    let status_line = header.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+
+    if !status.starts_with('2') {
+        return Err(io::Error::other(format!("server replied `{status_line}`")));
+    }
+
+    Ok(response.split_off(header_end + 4))
+}
+
+fn httpget(shell: &mut Shell, args: &[&str]) -> Result<(), ShellError> {
+    let (url, output) = match args {
+        [url] => (*url, None),
+        [url, output] => (*url, Some(*output)),
+        _ => {
+            writeln!(
+                shell.streams.stderr,
+                "{}",
+                usage("httpget <http://url> [file]")
+            )?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    let body = match fetch(url) {
+        Ok(body) => body,
+        Err(err) => {
+            writeln!(shell.streams.stderr, "httpget: {url}: {err}")?;
+            return Err(ShellError::BuiltinError);
+        }
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, body)?,
+        None => {
+            shell.streams.stdout.write_all(&body)?;
+            shell.streams.stdout.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,858 @@
+//! Turns the tokens produced by the [`Lexer`] into a tree of commands the shell can execute
+
+use thiserror::Error;
+
+use crate::lexer::{Operator, Token, Tokens, Word, WordPart};
+
+#[derive(Debug, Error)]
+pub enum ParseErrorKind {
+    #[error("unexpected `{0}`")]
+    UnexpectedToken(String),
+    #[error("unexpected end of input, expected `{0}`")]
+    UnexpectedEof(&'static str),
+    #[error("expected a file name after `{0}`")]
+    ExpectedRedirectTarget(&'static str),
+    #[error("unterminated `{0}`")]
+    Unterminated(&'static str),
+    #[error("commands nested more than {MAX_DEPTH} levels deep")]
+    TooDeep,
+}
+
+/// How deeply subshells, groups and `case` items can be nested, the parser recurses into each of them
+/// and so do the commands executing them, so generated input nesting them without end would overflow the stack
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Error)]
+#[error("{kind}")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The byte offset in the input of the token the error was found at
+    pub offset: usize,
+    /// The number of the line the error was found on, starting at 1
+    pub line: usize,
+    /// The text of that line, without its line break
+    pub source_line: String,
+    /// The byte offset of the error in `source_line`
+    pub column: usize,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, input: &str, offset: usize) -> Self {
+        // an error at the end of the input points after the last line rather than at an empty one
+        let position = offset.min(input.trim_end_matches('\n').len());
+        let line_start = input[..position].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[position..]
+            .find('\n')
+            .map_or(input.len(), |i| position + i);
+
+        Self {
+            kind,
+            offset,
+            line: input[..line_start].matches('\n').count() + 1,
+            source_line: input[line_start..line_end].to_string(),
+            column: position - line_start,
+        }
+    }
+
+    /// The error of a command substitution in `input` found nested more than [`MAX_DEPTH`] levels deep,
+    /// those are parsed once they are executed so the shell keeps track of their depth
+    pub(crate) fn too_deep(input: &str) -> Self {
+        Self::new(ParseErrorKind::TooDeep, input, 0)
+    }
+
+    /// Shows the line the error was found on with a caret under where it was found:
+    /// ```text
+    /// 2 | echo a | | b
+    ///   |          ^
+    /// ```
+    pub fn diagnostic(&self) -> String {
+        let number = self.line.to_string();
+        // tabs are kept so the caret lines up with what is above it
+        let padding = self.source_line[..self.column]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        format!(
+            "{number} | {}\n{} | {padding}^",
+            self.source_line,
+            " ".repeat(number.len()),
+        )
+    }
+}
+
+/// A list of commands separated by `;`, executed one after another
+pub type List<'a> = Vec<AndOr<'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// `&&`, runs the next command only if the previous one succeeded
+    And,
+    /// `||`, runs the next command only if the previous one failed
+    Or,
+}
+
+/// A chain of pipelines connected with `&&` and `||`
+#[derive(Debug)]
+pub struct AndOr<'a> {
+    pub first: Pipeline<'a>,
+    pub rest: Vec<(Connector, Pipeline<'a>)>,
+    /// Whether it is followed by `&`, which runs it in the background
+    pub background: bool,
+    /// The chain as it appears in the input
+    pub source: &'a str,
+}
+
+/// Commands connected with `|`, each command's stdout is connected to the next one's stdin
+#[derive(Debug)]
+pub struct Pipeline<'a> {
+    /// Whether the pipeline is prefixed with `!` which inverts its exit status
+    pub negated: bool,
+    pub commands: Vec<Command<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `< file`
+    Input,
+    /// `> file`
+    Output,
+    /// `>> file`
+    Append,
+    /// `<& descriptor`, reads from a descriptor the shell holds such as the output of a coprocess
+    DuplicateInput,
+    /// `>& descriptor`, writes to a descriptor the shell holds such as the input of a coprocess
+    DuplicateOutput,
+}
+
+/// The descriptor written before a redirection operator, such as the `2` of `2>&1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoNumber<'a> {
+    Fixed(u32),
+    /// `{name}`, a new descriptor whose number is stored in the variable `name`,
+    /// or the descriptor whose number it holds when closing one with `{name}>&-`
+    Variable(&'a str),
+}
+
+#[derive(Debug)]
+pub struct Redirect<'a> {
+    /// The descriptor being redirected, stdin or stdout depending on the kind if `None`
+    pub descriptor: Option<IoNumber<'a>>,
+    pub kind: RedirectKind,
+    /// A path, or for `<&` and `>&` the number of the descriptor to duplicate or `-` to close the descriptor
+    pub target: Word<'a>,
+}
+
+/// Returns whether `name` is a valid variable name, made of letters, digits and `_` without starting with a digit
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+#[derive(Debug, Clone)]
+pub enum AssignmentValue<'a> {
+    /// `name=value`
+    Scalar(Word<'a>),
+    /// `name=(a b c)`, sets `name` to an indexed array of the fields the words expand to
+    Array(Vec<Word<'a>>),
+}
+
+impl<'a> AssignmentValue<'a> {
+    /// Returns the words the value is made of
+    pub fn words(&self) -> &[Word<'a>] {
+        match self {
+            AssignmentValue::Scalar(word) => std::slice::from_ref(word),
+            AssignmentValue::Array(words) => words,
+        }
+    }
+}
+
+/// `name=value` or `name=(words...)`
+#[derive(Debug)]
+pub struct Assignment<'a> {
+    pub name: &'a str,
+    pub value: AssignmentValue<'a>,
+}
+
+impl<'a> Assignment<'a> {
+    /// Returns the assignment `word` is, if it is of the form `name=value` with a valid variable name
+    fn from_word(word: &Word<'a>) -> Option<Self> {
+        let Some(WordPart::Literal(first)) = word.parts.first() else {
+            return None;
+        };
+
+        let (name, value) = first.split_once('=')?;
+        if !is_name(name) {
+            return None;
+        }
+
+        let mut parts = Vec::with_capacity(word.parts.len());
+        if !value.is_empty() {
+            parts.push(WordPart::Literal(value));
+        }
+        parts.extend_from_slice(&word.parts[1..]);
+
+        Some(Self {
+            name,
+            value: AssignmentValue::Scalar(Word {
+                raw: &word.raw[name.len() + 1..],
+                parts,
+            }),
+        })
+    }
+}
+
+/// Variable assignments followed by the words making up a program or a builtin and its arguments
+#[derive(Debug)]
+pub struct SimpleCommand<'a> {
+    pub assignments: Vec<Assignment<'a>>,
+    pub words: Vec<Word<'a>>,
+}
+
+/// `pattern | pattern) list ;;`, an item of a `case`
+#[derive(Debug)]
+pub struct CaseItem<'a> {
+    pub patterns: Vec<Word<'a>>,
+    pub body: List<'a>,
+}
+
+#[derive(Debug)]
+pub enum CommandKind<'a> {
+    Simple(SimpleCommand<'a>),
+    /// `( list )`, executed without leaking changes to the current directory or variables
+    Subshell(List<'a>),
+    /// `{ list; }`, executed in the current shell
+    Group(List<'a>),
+    /// `case word in items esac`, executes the first item with a pattern matching `word`
+    Case {
+        word: Word<'a>,
+        items: Vec<CaseItem<'a>>,
+    },
+    /// `name() { list; }`, defines a function running the `{ list; }` group it was written with
+    Function {
+        name: &'a str,
+        body: &'a str,
+    },
+    /// `coproc [name] command`, runs `command` in the background with its stdin and stdout connected to the shell,
+    /// `name` is only allowed before a `{ list; }` group or a `( list )` subshell
+    Coproc {
+        name: Option<&'a str>,
+        /// The source of the command, which runs in a child shell
+        body: &'a str,
+    },
+}
+
+#[derive(Debug)]
+pub struct Command<'a> {
+    pub kind: CommandKind<'a>,
+    pub redirects: Vec<Redirect<'a>>,
+}
+
+pub struct Parser<'a> {
+    input: &'a str,
+    tokens: Tokens<'a>,
+    /// How many compound commands the command being parsed is nested in
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            tokens: Tokens::new(input),
+            depth: 0,
+        }
+    }
+
+    /// Returns the offset of `slice` in the input, `slice` must have been taken from the input
+    fn offset_of(&self, slice: &str) -> usize {
+        slice.as_ptr() as usize - self.input.as_ptr() as usize
+    }
+
+    /// Consumes the word `word`, returning how it appears in the input
+    fn eat_raw_word(&mut self, word: &str) -> Option<&'a str> {
+        match self
+            .tokens
+            .next_if(|token| matches!(token, Token::Word(next) if next.as_literal() == Some(word)))
+        {
+            Some(Token::Word(word)) => Some(word.raw),
+            _ => None,
+        }
+    }
+
+    /// Parses the whole input as a list of commands
+    pub fn parse(mut self) -> Result<List<'a>, ParseError> {
+        let results = self.parse_list().and_then(|list| match self.tokens.peek() {
+            None => Ok(list),
+            Some(_) => Err(self.unexpected("end of input")),
+        });
+
+        // an unterminated quote runs until the end of the input, so errors found after it are caused by it
+        match self.tokens.unterminated() {
+            Some((offset, what))
+                if results
+                    .as_ref()
+                    .err()
+                    .is_none_or(|err| err.offset >= offset) =>
+            {
+                Err(ParseError::new(
+                    ParseErrorKind::Unterminated(what),
+                    self.input,
+                    offset,
+                ))
+            }
+            _ => results,
+        }
+    }
+
+    fn eat_op(&mut self, op: Operator) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Op(next) if *next == op))
+            .is_some()
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Word(next) if next.as_literal() == Some(word)))
+            .is_some()
+    }
+
+    /// Describes the next token as unexpected, `expected` is used if the input ended instead
+    fn unexpected(&mut self, expected: &'static str) -> ParseError {
+        let kind = match self.tokens.peek() {
+            Some(token) => ParseErrorKind::UnexpectedToken(token.to_string()),
+            None => ParseErrorKind::UnexpectedEof(expected),
+        };
+        let offset = self.tokens.offset();
+        ParseError::new(kind, self.input, offset)
+    }
+
+    /// Consumes a `;` or a line break, returns whether there was one
+    fn eat_separator(&mut self) -> bool {
+        self.eat_op(Operator::Semicolon) || self.eat_op(Operator::Newline)
+    }
+
+    /// Skips the line breaks allowed after operators such as `&&` and `|`
+    fn skip_newlines(&mut self) {
+        while self.eat_op(Operator::Newline) {}
+    }
+
+    fn at_list_end(&mut self) -> bool {
+        match self.tokens.peek() {
+            None | Some(Token::Op(Operator::RParen | Operator::DSemi)) => true,
+            Some(Token::Word(word)) => matches!(word.as_literal(), Some("}" | "esac")),
+            Some(_) => false,
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<List<'a>, ParseError> {
+        let mut list = Vec::new();
+
+        loop {
+            while self.eat_separator() {}
+            if self.at_list_end() {
+                break;
+            }
+
+            let mut and_or = self.parse_and_or()?;
+            if self.eat_op(Operator::Amp) {
+                and_or.background = true;
+            } else if !self.eat_separator() {
+                list.push(and_or);
+                break;
+            }
+            list.push(and_or);
+        }
+
+        Ok(list)
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOr<'a>, ParseError> {
+        let start = self.tokens.offset();
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let connector = if self.eat_op(Operator::And) {
+                Connector::And
+            } else if self.eat_op(Operator::Or) {
+                Connector::Or
+            } else {
+                break;
+            };
+
+            self.skip_newlines();
+            rest.push((connector, self.parse_pipeline()?));
+        }
+
+        let end = self.tokens.offset();
+        Ok(AndOr {
+            first,
+            rest,
+            background: false,
+            source: self.input[start..end].trim_end(),
+        })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline<'a>, ParseError> {
+        let negated = self.eat_word("!");
+        let mut commands = vec![self.parse_command()?];
+
+        while self.eat_op(Operator::Pipe) {
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline { negated, commands })
+    }
+
+    /// Parses a non-empty list terminated by `close`
+    fn parse_compound(
+        &mut self,
+        close: &'static str,
+        eat_close: fn(&mut Self) -> bool,
+    ) -> Result<List<'a>, ParseError> {
+        let list = self.nested(Self::parse_list)?;
+        if list.is_empty() || !eat_close(self) {
+            return Err(self.unexpected(close));
+        }
+
+        Ok(list)
+    }
+
+    /// Consumes the `2` of `2>file` or the `{name}` of `{name}>&-`, which has to be followed directly by a redirection
+    /// operator, otherwise it is an ordinary word
+    fn parse_io_number(&mut self) -> Option<IoNumber<'a>> {
+        match self.tokens.peek() {
+            Some(Token::Word(word))
+                if word.as_literal().is_some_and(|literal| {
+                    literal.starts_with(|c: char| c == '{' || c.is_ascii_digit())
+                }) => {}
+            _ => return None,
+        }
+
+        let start = self.tokens.offset();
+        let mut ahead = Tokens::new(&self.input[start..]);
+        let Some(Token::Word(word)) = ahead.next() else {
+            return None;
+        };
+        let literal = word.as_literal()?;
+        let io_number = match literal
+            .strip_prefix('{')
+            .and_then(|name| name.strip_suffix('}'))
+        {
+            Some(name) if is_name(name.split_once('[').map_or(name, |(array, _)| array)) => {
+                IoNumber::Variable(name)
+            }
+            Some(_) => return None,
+            None => IoNumber::Fixed(literal.parse().ok()?),
+        };
+
+        let adjacent = ahead.offset() == literal.len();
+        match ahead.next() {
+            Some(Token::Op(
+                Operator::Less
+                | Operator::Great
+                | Operator::DGreat
+                | Operator::LessAnd
+                | Operator::GreatAnd,
+            )) if adjacent => {
+                self.tokens.next();
+                Some(io_number)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_redirect(&mut self) -> Result<Option<Redirect<'a>>, ParseError> {
+        let descriptor = self.parse_io_number();
+        let kind = match self.tokens.peek() {
+            Some(Token::Op(Operator::Less)) => RedirectKind::Input,
+            Some(Token::Op(Operator::Great)) => RedirectKind::Output,
+            Some(Token::Op(Operator::DGreat)) => RedirectKind::Append,
+            Some(Token::Op(Operator::LessAnd)) => RedirectKind::DuplicateInput,
+            Some(Token::Op(Operator::GreatAnd)) => RedirectKind::DuplicateOutput,
+            _ => return Ok(None),
+        };
+
+        let Some(Token::Op(op)) = self.tokens.next() else {
+            unreachable!()
+        };
+
+        let offset = self.tokens.offset();
+        match self.tokens.next() {
+            Some(Token::Word(target)) => Ok(Some(Redirect {
+                descriptor,
+                kind,
+                target,
+            })),
+            _ => Err(ParseError::new(
+                ParseErrorKind::ExpectedRedirectTarget(op.as_str()),
+                self.input,
+                offset,
+            )),
+        }
+    }
+
+    fn parse_redirects(&mut self) -> Result<Vec<Redirect<'a>>, ParseError> {
+        let mut redirects = Vec::new();
+        while let Some(redirect) = self.parse_redirect()? {
+            redirects.push(redirect);
+        }
+        Ok(redirects)
+    }
+
+    /// Runs `parse` one level of nesting deeper, failing if the commands are nested more than [`MAX_DEPTH`] levels deep
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth == MAX_DEPTH {
+            let offset = self.tokens.offset();
+            return Err(ParseError::new(ParseErrorKind::TooDeep, self.input, offset));
+        }
+        self.depth += 1;
+        let results = parse(self);
+        self.depth -= 1;
+        results
+    }
+
+    fn parse_command(&mut self) -> Result<Command<'a>, ParseError> {
+        let kind = if self.eat_op(Operator::LParen) {
+            CommandKind::Subshell(self.parse_compound(")", |p| p.eat_op(Operator::RParen))?)
+        } else if self.eat_word("{") {
+            CommandKind::Group(self.parse_compound("}", |p| p.eat_word("}"))?)
+        } else if self.eat_word("case") {
+            self.parse_case()?
+        } else if self.eat_word("coproc") {
+            return self.parse_coproc();
+        } else {
+            return self.parse_simple();
+        };
+
+        let redirects = self.parse_redirects()?;
+        Ok(Command { kind, redirects })
+    }
+
+    fn parse_word(&mut self, expected: &'static str) -> Result<Word<'a>, ParseError> {
+        match self.tokens.next_if(|token| matches!(token, Token::Word(_))) {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// Parses the rest of a `case` after the `case` keyword
+    fn parse_case(&mut self) -> Result<CommandKind<'a>, ParseError> {
+        let word = self.parse_word("word")?;
+        self.skip_newlines();
+        if !self.eat_word("in") {
+            return Err(self.unexpected("in"));
+        }
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.eat_word("esac") {
+                break;
+            }
+
+            // the `(` before the patterns is optional
+            self.eat_op(Operator::LParen);
+            let mut patterns = vec![self.parse_word("pattern")?];
+            while self.eat_op(Operator::Pipe) {
+                patterns.push(self.parse_word("pattern")?);
+            }
+            if !self.eat_op(Operator::RParen) {
+                return Err(self.unexpected(")"));
+            }
+
+            let body = self.nested(Self::parse_list)?;
+            items.push(CaseItem { patterns, body });
+
+            // the last item may omit its `;;`
+            if !self.eat_op(Operator::DSemi) {
+                if self.eat_word("esac") {
+                    break;
+                }
+                return Err(self.unexpected("esac"));
+            }
+        }
+
+        Ok(CommandKind::Case { word, items })
+    }
+
+    /// Parses the rest of a `coproc` after the `coproc` keyword
+    fn parse_coproc(&mut self) -> Result<Command<'a>, ParseError> {
+        // a name is told apart from a command by the `{` or `(` following it, which needs a second token of lookahead
+        let start = self.tokens.offset();
+        let mut ahead = Tokens::new(&self.input[start..]);
+        let name = match (ahead.next(), ahead.next()) {
+            (Some(Token::Word(name)), Some(Token::Word(open)))
+                if open.as_literal() == Some("{") =>
+            {
+                name.as_literal()
+            }
+            (Some(Token::Word(name)), Some(Token::Op(Operator::LParen))) => name.as_literal(),
+            _ => None,
+        }
+        .filter(|name| is_name(name));
+        if name.is_some() {
+            self.tokens.next();
+        }
+
+        let start = self.tokens.offset();
+        self.nested(Self::parse_command)?;
+        let end = self.tokens.offset();
+        Ok(Command {
+            kind: CommandKind::Coproc {
+                name,
+                body: self.input[start..end].trim_end(),
+            },
+            redirects: Vec::new(),
+        })
+    }
+
+    /// Parses the rest of a `name() { list; }` function definition after its name and `(`
+    fn parse_function(&mut self, name: &'a str) -> Result<CommandKind<'a>, ParseError> {
+        if !self.eat_op(Operator::RParen) {
+            return Err(self.unexpected(")"));
+        }
+        self.skip_newlines();
+
+        let Some(open) = self.eat_raw_word("{") else {
+            return Err(self.unexpected("{"));
+        };
+        let list = self.nested(Self::parse_list)?;
+        let close = match self.eat_raw_word("}") {
+            Some(close) if !list.is_empty() => close,
+            _ => return Err(self.unexpected("}")),
+        };
+
+        // the body is kept as source since the parsed commands borrow the line defining the function
+        let body = &self.input[self.offset_of(open)..self.offset_of(close) + close.len()];
+        Ok(CommandKind::Function { name, body })
+    }
+
+    /// Parses the `(words...)` of an array assignment, returns `None` if there is no `(`
+    fn parse_array(&mut self) -> Result<Option<Vec<Word<'a>>>, ParseError> {
+        if !self.eat_op(Operator::LParen) {
+            return Ok(None);
+        }
+
+        let mut elements = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.tokens.next_if(|token| matches!(token, Token::Word(_))) {
+                Some(Token::Word(word)) => elements.push(word),
+                _ if self.eat_op(Operator::RParen) => return Ok(Some(elements)),
+                _ => return Err(self.unexpected(")")),
+            }
+        }
+    }
+
+    fn parse_simple(&mut self) -> Result<Command<'a>, ParseError> {
+        let mut assignments = Vec::new();
+        let mut words = Vec::new();
+        let mut redirects = Vec::new();
+
+        loop {
+            if let Some(redirect) = self.parse_redirect()? {
+                redirects.push(redirect);
+                continue;
+            }
+
+            let Some(Token::Word(word)) =
+                self.tokens.next_if(|token| matches!(token, Token::Word(_)))
+            else {
+                break;
+            };
+
+            match Assignment::from_word(&word) {
+                Some(mut assignment) if words.is_empty() => {
+                    // `name=(` starts an array, the `(` has to follow the `=` directly
+                    let end = self.offset_of(word.raw) + word.raw.len();
+                    let elements = match word.raw.len() == assignment.name.len() + 1
+                        && self.tokens.offset() == end
+                    {
+                        true => self.parse_array()?,
+                        false => None,
+                    };
+                    if let Some(elements) = elements {
+                        assignment.value = AssignmentValue::Array(elements);
+                    }
+                    assignments.push(assignment);
+                }
+                _ => words.push(word),
+            }
+        }
+
+        if assignments.is_empty() && words.is_empty() && redirects.is_empty() {
+            return Err(self.unexpected("command"));
+        }
+
+        // `name()` starts a function definition
+        let name = match (
+            assignments.as_slice(),
+            words.as_slice(),
+            redirects.as_slice(),
+        ) {
+            ([], [word], []) => word.as_literal(),
+            _ => None,
+        };
+        if let Some(name) = name.filter(|_| self.eat_op(Operator::LParen)) {
+            return Ok(Command {
+                kind: self.parse_function(name)?,
+                redirects: Vec::new(),
+            });
+        }
+
+        Ok(Command {
+            kind: CommandKind::Simple(SimpleCommand { assignments, words }),
+            redirects,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AssignmentValue, CommandKind, Connector, IoNumber, List, ParseErrorKind, Parser,
+        RedirectKind, SimpleCommand,
+    };
+
+    fn parse(input: &str) -> List<'_> {
+        Parser::new(input)
+            .parse()
+            .unwrap_or_else(|err| panic!("`{input}`: {err}"))
+    }
+
+    fn simple<'l, 'a>(list: &'l List<'a>, i: usize) -> &'l SimpleCommand<'a> {
+        match &list[i].first.commands[0].kind {
+            CommandKind::Simple(command) => command,
+            kind => panic!("{kind:?} isn't a simple command"),
+        }
+    }
+
+    fn raw<'a>(command: &SimpleCommand<'a>) -> Vec<&'a str> {
+        command.words.iter().map(|word| word.raw).collect()
+    }
+
+    #[test]
+    fn lists() {
+        let list = parse("a; ! b | c && d || e &\nf");
+        assert_eq!(list.len(), 3);
+        assert_eq!(raw(simple(&list, 0)), ["a"]);
+
+        let and_or = &list[1];
+        assert!(and_or.background);
+        assert!(and_or.first.negated);
+        assert_eq!(and_or.first.commands.len(), 2);
+        let connectors = and_or.rest.iter().map(|(connector, _)| *connector);
+        assert!(connectors.eq([Connector::And, Connector::Or]));
+        assert_eq!(and_or.source, "! b | c && d || e");
+        assert_eq!(raw(simple(&list, 2)), ["f"]);
+    }
+
+    #[test]
+    fn assignments() {
+        let list = parse("a=1 b=(x 'y z') cmd c=2");
+        let command = simple(&list, 0);
+        assert_eq!(raw(command), ["cmd", "c=2"]);
+        assert_eq!(command.assignments[0].name, "a");
+        assert!(
+            matches!(&command.assignments[0].value, AssignmentValue::Scalar(word) if word.raw == "1")
+        );
+        assert_eq!(command.assignments[1].name, "b");
+        let words = command.assignments[1].value.words();
+        assert_eq!(
+            words.iter().map(|word| word.raw).collect::<Vec<_>>(),
+            ["x", "'y z'"]
+        );
+    }
+
+    #[test]
+    fn redirects() {
+        let list = parse("cmd <in 2>>log {fd}>&- >&3");
+        let redirects = &list[0].first.commands[0].redirects;
+        let found = redirects
+            .iter()
+            .map(|redirect| (redirect.descriptor, redirect.kind, redirect.target.raw))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (None, RedirectKind::Input, "in"),
+                (Some(IoNumber::Fixed(2)), RedirectKind::Append, "log"),
+                (
+                    Some(IoNumber::Variable("fd")),
+                    RedirectKind::DuplicateOutput,
+                    "-"
+                ),
+                (None, RedirectKind::DuplicateOutput, "3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_commands() {
+        let list = parse("(a; b) && { c; }");
+        assert!(
+            matches!(&list[0].first.commands[0].kind, CommandKind::Subshell(list) if list.len() == 2)
+        );
+        assert!(
+            matches!(&list[0].rest[0].1.commands[0].kind, CommandKind::Group(list) if list.len() == 1)
+        );
+
+        let list = parse("case $x in a | b) one;; *) two;; esac");
+        let CommandKind::Case { word, items } = &list[0].first.commands[0].kind else {
+            panic!("not a case");
+        };
+        assert_eq!(word.raw, "$x");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].patterns.len(), 2);
+
+        let list = parse("f() { echo hi; }; coproc worker { cat; }");
+        assert!(matches!(
+            list[0].first.commands[0].kind,
+            CommandKind::Function {
+                name: "f",
+                body: "{ echo hi; }"
+            }
+        ));
+        assert!(matches!(
+            list[1].first.commands[0].kind,
+            CommandKind::Coproc {
+                name: Some("worker"),
+                body: "{ cat; }"
+            }
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let kind = |input| Parser::new(input).parse().unwrap_err().kind;
+        assert!(matches!(kind("a | | b"), ParseErrorKind::UnexpectedToken(token) if token == "|"));
+        assert!(matches!(kind("( a"), ParseErrorKind::UnexpectedEof(")")));
+        assert!(matches!(
+            kind("a >"),
+            ParseErrorKind::ExpectedRedirectTarget(">")
+        ));
+        assert!(matches!(kind("a )"), ParseErrorKind::UnexpectedToken(token) if token == ")"));
+        assert!(matches!(kind("echo 'a"), ParseErrorKind::Unterminated("'")));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let diagnostic = |input| Parser::new(input).parse().unwrap_err().diagnostic();
+        assert_eq!(
+            diagnostic("echo a | | b"),
+            "1 | echo a | | b\n  |          ^"
+        );
+        assert_eq!(diagnostic("a\nb\n\tc | ;"), "3 | \tc | ;\n  | \t    ^");
+        // an error at the end of the input points after the last line
+        assert_eq!(diagnostic("( a\n"), "1 | ( a\n  |    ^");
+    }
+}
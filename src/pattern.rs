@@ -0,0 +1,171 @@
+//! Glob-style patterns as used by `case`: `*`, `?`, `[...]` bracket expressions and `|` alternatives,
+//! a character preceded by a `\` only matches itself
+
+/// Characters with a special meaning in patterns, escaped when they come from quoted text
+pub const SPECIAL: &[char] = &['*', '?', '[', ']', '\\', '|'];
+
+/// Splits `pattern` on the `|` separating its alternatives
+pub fn alternatives(pattern: &str) -> Vec<&str> {
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in pattern.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '|' => {
+                alternatives.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    alternatives.push(&pattern[start..]);
+    alternatives
+}
+
+/// Matches `c` against the bracket expression at the start of `pattern`, which follows a `[`,
+/// returns whether it matched and the rest of the pattern, or `None` if the expression isn't closed
+fn match_bracket(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut rest) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let (start, after) = match rest.split_first()? {
+            // a `]` right after the `[` is part of the set
+            (']', after) if !first => return Some((matched != negated, after)),
+            ('\\', after) => after.split_first()?,
+            (start, after) => (start, after),
+        };
+        first = false;
+
+        match after {
+            ['-', end, after @ ..] if *end != ']' => {
+                matched |= (*start..=*end).contains(&c);
+                rest = after;
+            }
+            _ => {
+                matched |= *start == c;
+                rest = after;
+            }
+        }
+    }
+}
+
+fn match_chars(pattern: &[char], text: &[char]) -> bool {
+    let Some((&first, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+
+    match first {
+        '*' => {
+            // consecutive stars match the same as a single one
+            let rest = &rest[rest.iter().take_while(|c| **c == '*').count()..];
+            (0..=text.len()).any(|skip| match_chars(rest, &text[skip..]))
+        }
+        '?' => !text.is_empty() && match_chars(rest, &text[1..]),
+        '[' => {
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            match match_bracket(rest, c) {
+                Some((matched, rest)) => matched && match_chars(rest, &text[1..]),
+                // an unclosed `[` is an ordinary character
+                None => c == '[' && match_chars(rest, &text[1..]),
+            }
+        }
+        '\\' if !rest.is_empty() => {
+            text.first() == Some(&rest[0]) && match_chars(&rest[1..], &text[1..])
+        }
+        c => text.first() == Some(&c) && match_chars(rest, &text[1..]),
+    }
+}
+
+/// Returns whether the whole of `text` matches `pattern`, `pattern` shouldn't contain `|` alternatives
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    match_chars(&pattern, &text)
+}
+
+/// Returns whether `pattern` has a `*`, `?` or `[` that isn't escaped
+pub fn has_wildcards(pattern: &str) -> bool {
+    let mut escaped = false;
+    pattern.chars().any(|c| match c {
+        _ if escaped => {
+            escaped = false;
+            false
+        }
+        '\\' => {
+            escaped = true;
+            false
+        }
+        c => matches!(c, '*' | '?' | '['),
+    })
+}
+
+/// Removes the `\` escaping the characters of `pattern`
+fn unescape(pattern: &str) -> String {
+    let mut results = String::new();
+    let mut escaped = false;
+    for c in pattern.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        escaped = false;
+        results.push(c);
+    }
+    results
+}
+
+/// Returns the sorted paths of the files matching `pattern`, one directory at a time,
+/// names starting with a `.` only match components that start with one too
+pub fn glob(pattern: &str) -> Vec<String> {
+    let mut paths = vec![String::new()];
+
+    for (i, component) in pattern.split('/').enumerate() {
+        let join = |path: &str, name: &str| match i {
+            0 => name.to_string(),
+            _ => format!("{path}/{name}"),
+        };
+
+        if !has_wildcards(component) {
+            let name = unescape(component);
+            paths = paths.iter().map(|path| join(path, &name)).collect();
+            continue;
+        }
+
+        let mut matched = Vec::new();
+        for path in &paths {
+            let dir = match (i, path.as_str()) {
+                (0, _) => ".",
+                (_, "") => "/",
+                (_, path) => path,
+            };
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+                if matches(component, &name) {
+                    matched.push(join(path, &name));
+                }
+            }
+        }
+        paths = matched;
+    }
+
+    paths.retain(|path| std::fs::symlink_metadata(path).is_ok());
+    paths.sort();
+    paths
+}
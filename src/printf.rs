@@ -0,0 +1,202 @@
+//! Formatting for the `printf` builtin
+
+use std::{iter::Peekable, str::Chars};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PrintfError {
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("unknown conversion `%{0}`")]
+    UnknownConversion(char),
+    #[error("missing conversion after `%`")]
+    MissingConversion,
+}
+
+/// Parses `arg` as an integer the way `%d` and `%x` expect, an empty or missing argument is `0`
+fn parse_integer(arg: &str) -> Result<i64, PrintfError> {
+    let invalid = || PrintfError::InvalidNumber(arg.to_string());
+    let (negative, digits) = match arg.trim().strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, arg.trim().trim_start_matches('+')),
+    };
+
+    let value = if digits.is_empty() {
+        0
+    } else if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).map_err(|_| invalid())?
+    } else if let Some(char) = digits.strip_prefix(['\'', '"']) {
+        // `'a` is the code of `a`
+        char.chars().next().map_or(0, |c| c as i64)
+    } else {
+        digits.parse().map_err(|_| invalid())?
+    };
+
+    Ok(if negative { -value } else { value })
+}
+
+/// Pushes the character the escape sequence following a `\` stands for, such as a line break for `\n`
+fn push_escape(chars: &mut Peekable<Chars>, output: &mut String) {
+    let Some(c) = chars.next() else {
+        output.push('\\');
+        return;
+    };
+
+    match c {
+        'n' => output.push('\n'),
+        't' => output.push('\t'),
+        'r' => output.push('\r'),
+        'a' => output.push('\u{7}'),
+        'b' => output.push('\u{8}'),
+        'f' => output.push('\u{c}'),
+        'v' => output.push('\u{b}'),
+        'e' => output.push('\u{1b}'),
+        '\\' => output.push('\\'),
+        '"' => output.push('"'),
+        '\'' => output.push('\''),
+        '0'..='7' => {
+            let mut code = c.to_digit(8).unwrap();
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        code = code * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            output.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+        }
+        c => {
+            output.push('\\');
+            output.push(c);
+        }
+    }
+}
+
+/// A `%` conversion's flags and width, such as the `-8` in `%-8s`
+#[derive(Default)]
+struct Spec {
+    left_align: bool,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(chars: &mut Peekable<Chars>) -> Self {
+        let mut spec = Spec::default();
+        while let Some(flag) = chars.next_if(|c| matches!(c, '-' | '0')) {
+            match flag {
+                '-' => spec.left_align = true,
+                _ => spec.zero_pad = true,
+            }
+        }
+
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            spec.width = spec.width * 10 + digit as usize;
+            chars.next();
+        }
+
+        if chars.next_if_eq(&'.').is_some() {
+            let mut precision = 0;
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                precision = precision * 10 + digit as usize;
+                chars.next();
+            }
+            spec.precision = Some(precision);
+        }
+
+        spec
+    }
+
+    fn pad(&self, str: &str, numeric: bool, output: &mut String) {
+        let len = str.chars().count();
+        let padding = self.width.saturating_sub(len);
+
+        if self.left_align {
+            output.push_str(str);
+            output.extend(std::iter::repeat_n(' ', padding));
+        } else if self.zero_pad && numeric {
+            // zeros go after the sign
+            let (sign, digits) = match str.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", str),
+            };
+            output.push_str(sign);
+            output.extend(std::iter::repeat_n('0', padding));
+            output.push_str(digits);
+        } else {
+            output.extend(std::iter::repeat_n(' ', padding));
+            output.push_str(str);
+        }
+    }
+}
+
+/// Formats `args` according to `format`, the format is reused until all the arguments are consumed
+pub fn format(format: &str, args: &[&str]) -> Result<String, PrintfError> {
+    let mut output = String::new();
+    let mut args = args.iter().copied().peekable();
+
+    loop {
+        let mut consumed = false;
+        let mut chars = format.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => push_escape(&mut chars, &mut output),
+                '%' if chars.next_if_eq(&'%').is_some() => output.push('%'),
+                '%' => {
+                    let spec = Spec::parse(&mut chars);
+                    let conversion = chars.next().ok_or(PrintfError::MissingConversion)?;
+                    let arg = args.next().inspect(|_| consumed = true).unwrap_or("");
+
+                    match conversion {
+                        's' => {
+                            let arg = match spec.precision {
+                                Some(precision) => arg.chars().take(precision).collect(),
+                                None => arg.to_string(),
+                            };
+                            spec.pad(&arg, false, &mut output);
+                        }
+                        // like `%s` but with the escape sequences in the argument expanded
+                        'b' => {
+                            let mut expanded = String::new();
+                            let mut arg_chars = arg.chars().peekable();
+                            while let Some(c) = arg_chars.next() {
+                                match c {
+                                    '\\' => push_escape(&mut arg_chars, &mut expanded),
+                                    c => expanded.push(c),
+                                }
+                            }
+                            spec.pad(&expanded, false, &mut output);
+                        }
+                        'c' => spec.pad(
+                            &arg.chars().next().map(String::from).unwrap_or_default(),
+                            false,
+                            &mut output,
+                        ),
+                        'd' | 'i' => spec.pad(&parse_integer(arg)?.to_string(), true, &mut output),
+                        'u' => {
+                            spec.pad(&(parse_integer(arg)? as u64).to_string(), true, &mut output)
+                        }
+                        'x' => spec.pad(&format!("{:x}", parse_integer(arg)?), true, &mut output),
+                        'X' => spec.pad(&format!("{:X}", parse_integer(arg)?), true, &mut output),
+                        'o' => spec.pad(&format!("{:o}", parse_integer(arg)?), true, &mut output),
+                        c => return Err(PrintfError::UnknownConversion(c)),
+                    }
+                }
+                c => output.push(c),
+            }
+        }
+
+        // a format without conversions is printed once no matter how many arguments are left
+        if args.peek().is_none() || !consumed {
+            return Ok(output);
+        }
+    }
+}
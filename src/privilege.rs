@@ -0,0 +1,50 @@
+//! The integration point for running programs with elevated privileges, used by the `elevate` builtin
+//!
+//! Scripts should use `elevate` instead of calling a platform's mechanism directly,
+//! so they keep working once SafaOS gains one
+
+use crate::i18n::Message;
+
+/// Returns the program that runs the program passed as its first argument with elevated privileges,
+/// or why privileges can't be elevated on this system
+pub fn elevating_program() -> Result<&'static str, Message> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "safaos")] {
+            Err(Message::ElevationUnsupported)
+        } else {
+            Ok("sudo")
+        }
+    }
+}
+
+/// Inherited variables that change how the shell behaves, which privileged shells don't trust
+const UNTRUSTED_VARIABLES: &[&str] = &[
+    "ENV",
+    "BASH_ENV",
+    "IFS",
+    "CDPATH",
+    "DRIVEPATH",
+    "PS1",
+    "SAFA_SHELL_CONFIG",
+    "SAFA_SHELL_LOG",
+    "SAFA_COMPLETIONS_DIR",
+    "SAFA_LOCALE_DIR",
+    "SAFA_MOTD",
+    "SAFA_INIT_SOCKET",
+    "SAFA_CWD_SOCKET",
+];
+
+/// Removes the inherited variables that could change how a privileged shell behaves,
+/// such as the ones pointing it at files to load or changing how words are split
+pub fn sanitize_environment() {
+    let untrusted = std::env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| {
+            UNTRUSTED_VARIABLES.contains(&name.as_str()) || name.starts_with("SAFA_COLOR_")
+        })
+        .collect::<Vec<_>>();
+
+    for name in untrusted {
+        unsafe { std::env::remove_var(name) };
+    }
+}
@@ -0,0 +1,130 @@
+//! `<(command)` process substitutions, which give programs the output of `command` as a file they can read
+//!
+//! On unix hosts the output goes into a pipe that the program the substitution is an argument of inherits and opens as
+//! `/dev/fd/N`, SafaOS has no way of naming a pipe yet so substitutions fail there with an error
+
+use std::{
+    io::{self, PipeReader},
+    process::{Child, Command},
+};
+
+#[cfg(unix)]
+mod sys {
+    use std::{
+        io::{self, PipeReader},
+        os::{
+            fd::{AsRawFd, RawFd},
+            unix::process::CommandExt,
+        },
+        process::Command,
+    };
+
+    /// The same on Linux, macOS and the BSDs
+    const F_SETFD: i32 = 2;
+
+    // the C library is always linked on unix
+    unsafe extern "C" {
+        fn fcntl(fd: i32, cmd: i32, ...) -> i32;
+    }
+
+    /// Clears the close-on-exec flag std sets on pipes in the child only, so just the program `command` runs
+    /// inherits `readers` under the same numbers
+    pub fn pass_to(readers: Vec<RawFd>, command: &mut Command) {
+        // runs in the child before it executes the program, where allocating isn't safe
+        let pass = move || {
+            for &fd in &readers {
+                if unsafe { fcntl(fd, F_SETFD, 0) } == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(pass) };
+    }
+
+    pub fn path(reader: &PipeReader) -> String {
+        format!("/dev/fd/{}", reader.as_raw_fd())
+    }
+}
+
+/// A running `<(command)` whose output is read through [`ProcessSubstitution::path`]
+#[derive(Debug)]
+pub struct ProcessSubstitution {
+    child: Child,
+    reader: PipeReader,
+    /// Whether the program the substitution is an argument of was given the reader
+    passed: bool,
+}
+
+impl ProcessSubstitution {
+    /// Spawns `command` with its stdout going into a pipe the programs spawned afterwards can read
+    pub fn start(mut command: Command) -> io::Result<Self> {
+        if !cfg!(unix) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "process substitution isn't supported on this system",
+            ));
+        }
+
+        let (reader, writer) = io::pipe()?;
+        let child = command.stdout(writer).spawn()?;
+        // only the child holds the write end now, so the reader sees the end of the output once it exits
+        drop(command);
+
+        Ok(Self {
+            child,
+            reader,
+            passed: false,
+        })
+    }
+
+    /// Gives `command` the readers of the substitutions expanded since the last program was spawned, which are the
+    /// arguments of the program `command` runs
+    pub fn pass_to(substitutions: &mut [Self], command: &mut Command) {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::fd::AsRawFd;
+
+                let readers = substitutions
+                    .iter_mut()
+                    .filter(|substitution| !substitution.passed)
+                    .map(|substitution| {
+                        substitution.passed = true;
+                        substitution.reader.as_raw_fd()
+                    })
+                    .collect::<Vec<_>>();
+                if !readers.is_empty() {
+                    sys::pass_to(readers, command);
+                }
+            } else {
+                _ = (substitutions, command);
+            }
+        }
+    }
+
+    /// The path programs open to read the output of the command
+    pub fn path(&self) -> String {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                sys::path(&self.reader)
+            } else {
+                unreachable!("process substitutions are only started on unix")
+            }
+        }
+    }
+
+    /// Closes the shell's ends of the pipes then waits for the commands, which fail to write if their output wasn't
+    /// all read
+    ///
+    /// Every reader is closed before waiting for any command, a command still writing to a reader that is open
+    /// would otherwise never exit
+    pub fn finish_all(substitutions: impl IntoIterator<Item = Self>) {
+        let children = substitutions
+            .into_iter()
+            .map(|substitution| substitution.child)
+            .collect::<Vec<_>>();
+        for mut child in children {
+            _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Renders the shell prompt in one of the bundled themes, or from a `$PS1` template
+
+use std::{fmt::Write, path::Path, time::SystemTime};
+
+use crate::{
+    OSReturn, PromptContext,
+    colors::{Colors, Role},
+    config::Config,
+    identity, terminal,
+    time::{format_duration, format_utc_time},
+    width,
+};
+
+/// An optional piece of information shown in the prompt after the current directory
+pub struct Segment {
+    pub name: &'static str,
+    /// Returns the text of the segment, `None` hides it
+    render: fn(&PromptContext) -> Option<String>,
+}
+
+/// The segments that can be enabled using `prompt segments`
+pub static SEGMENTS: &[Segment] = &[
+    Segment {
+        name: "time",
+        render: |_| Some(format_utc_time(SystemTime::now())),
+    },
+    Segment {
+        name: "duration",
+        render: |context| context.duration.map(format_duration),
+    },
+];
+
+impl Segment {
+    pub fn from_name(name: &str) -> Option<&'static Self> {
+        SEGMENTS.iter().find(|segment| segment.name == name)
+    }
+}
+
+/// Shortens `cwd` to its last `max_dirs` directories, such as `…/deep/dir`
+fn truncate_path(cwd: &str, max_dirs: Option<usize>, glyphs: Glyphs) -> String {
+    let Some(max_dirs) = max_dirs else {
+        return cwd.to_string();
+    };
+
+    let dirs = cwd
+        .split('/')
+        .filter(|dir| !dir.is_empty())
+        .collect::<Vec<_>>();
+    // a drive such as `ram:` isn't a directory
+    let len = match dirs.first() {
+        Some(first) if first.ends_with(':') => dirs.len() - 1,
+        _ => dirs.len(),
+    };
+
+    if len <= max_dirs {
+        return cwd.to_string();
+    }
+    format!(
+        "{}/{}",
+        glyphs.ellipsis(),
+        dirs[dirs.len() - max_dirs..].join("/")
+    )
+}
+
+/// The settings of the prompt rendered when `$PS1` isn't set
+#[derive(Default)]
+pub struct Prompt {
+    pub theme: Theme,
+    pub glyphs: Glyphs,
+    /// How many of the current directory's trailing directories are shown, all of them if `None`
+    pub max_dirs: Option<usize>,
+    pub segments: Vec<&'static Segment>,
+    /// Whether the terminal's title shows the running command and the current directory, see [`title`]
+    pub title: bool,
+}
+
+impl Prompt {
+    /// Parses the value of the `prompt.dirs` setting, `all` or a positive number
+    pub fn parse_max_dirs(value: &str) -> Option<Option<usize>> {
+        match value {
+            "all" => Some(None),
+            value => value.parse().ok().filter(|max| *max > 0).map(Some),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            theme: config
+                .get("prompt.theme")
+                .and_then(Theme::from_name)
+                .unwrap_or_default(),
+            glyphs: config
+                .get("prompt.glyphs")
+                .and_then(Glyphs::from_name)
+                .unwrap_or_default(),
+            max_dirs: config
+                .get("prompt.dirs")
+                .and_then(Self::parse_max_dirs)
+                .unwrap_or_default(),
+            segments: config
+                .get("prompt.segments")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|name| Segment::from_name(name.trim()))
+                .collect(),
+            title: config.get("prompt.title") == Some("on"),
+        }
+    }
+
+    /// Renders the prompt, shortening the current directory so it takes at most half of the terminal's `columns`
+    pub fn render(
+        &self,
+        context: &PromptContext,
+        colors: &Colors,
+        columns: Option<usize>,
+    ) -> String {
+        let full_cwd = context.cwd.display().to_string();
+        let mut cwd = truncate_path(&full_cwd, self.max_dirs, self.glyphs);
+
+        // leaves room to type, the last directory is always shown whatever its length
+        if let Some(columns) = columns {
+            let dirs = full_cwd.split('/').filter(|dir| !dir.is_empty()).count();
+            let mut max_dirs = self.max_dirs.unwrap_or(dirs).min(dirs);
+            while width::width(&cwd) > columns / 2 && max_dirs > 1 {
+                max_dirs -= 1;
+                cwd = truncate_path(&full_cwd, Some(max_dirs), self.glyphs);
+            }
+        }
+
+        let segments = self
+            .segments
+            .iter()
+            .filter_map(|segment| (segment.render)(context))
+            .collect::<Vec<_>>();
+
+        self.theme
+            .render(&cwd, &segments, context.last_return, self.glyphs, colors)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// `ram:/ [1] # `
+    #[default]
+    Classic,
+    /// `ram:/ # ` with the `#` colored by the last command's status
+    Minimal,
+    /// Colored segments separated by powerline arrows
+    Powerline,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Classic, Theme::Minimal, Theme::Powerline];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Minimal => "minimal",
+            Theme::Powerline => "powerline",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    fn render(
+        &self,
+        cwd: &str,
+        segments: &[String],
+        last_return: Option<&OSReturn>,
+        glyphs: Glyphs,
+        colors: &Colors,
+    ) -> String {
+        let mut prompt = String::new();
+
+        match self {
+            Theme::Classic => {
+                paint(&mut prompt, &colors.foreground(Role::Cwd, "35"), cwd);
+                for segment in segments {
+                    paint(
+                        &mut prompt,
+                        &colors.foreground(Role::Segment, "36"),
+                        segment,
+                    );
+                }
+                if let Some(code) = last_return {
+                    let status = format!("[{code}]");
+                    paint(&mut prompt, &colors.foreground(Role::Error, "31"), &status);
+                }
+                write!(prompt, "# ").unwrap();
+            }
+            Theme::Minimal => {
+                paint(&mut prompt, &colors.foreground(Role::Cwd, ""), cwd);
+                for segment in segments {
+                    paint(&mut prompt, &colors.foreground(Role::Segment, "2"), segment);
+                }
+                let color = match last_return {
+                    Some(_) => colors.foreground(Role::Error, "31"),
+                    None => colors.foreground(Role::Success, "32"),
+                };
+                paint(&mut prompt, &color, "#");
+            }
+            Theme::Powerline => {
+                // the (text, background, arrow) colors of each block, the arrow is the background as a foreground
+                let block = |role, text: &str, background: &str, arrow: &str| {
+                    (
+                        colors.shown(text),
+                        colors.background(role, background),
+                        colors.foreground(role, arrow),
+                    )
+                };
+                let mut blocks = vec![(block(Role::Cwd, "30", "45", "35"), cwd.to_string())];
+                blocks.extend(
+                    segments
+                        .iter()
+                        .map(|segment| (block(Role::Segment, "30", "44", "34"), segment.clone())),
+                );
+                if let Some(code) = last_return {
+                    blocks.push((block(Role::Error, "97", "41", "31"), code.to_string()));
+                }
+
+                let separator = glyphs.separator();
+                let mut previous_arrow = None;
+                for ((foreground, background, arrow), text) in blocks {
+                    if let Some(previous) = previous_arrow {
+                        // the arrow is drawn in the previous block's color over the next block's
+                        write!(prompt, "\x1b[0;{previous};{background}m{separator}").unwrap();
+                    }
+                    write!(prompt, "\x1b[0;{foreground};{background}m {text} ").unwrap();
+                    previous_arrow = Some(arrow);
+                }
+                let last = previous_arrow.unwrap_or_default();
+                write!(prompt, "\x1b[0;{last}m{separator}\x1b[0m ").unwrap();
+            }
+        }
+
+        prompt
+    }
+}
+
+/// Pushes `text` drawn with the SGR parameters `color` followed by a space, `text` is left as is if `color` is empty
+fn paint(prompt: &mut String, color: &str, text: &str) {
+    if color.is_empty() {
+        write!(prompt, "{text} ").unwrap();
+    } else {
+        write!(prompt, "\x1b[{color}m{text}\x1b[0m ").unwrap();
+    }
+}
+
+/// Expands the escapes in a `$PS1` prompt template:
+/// - `\u` the user and `\h` the hostname
+/// - `\w` the current directory and `\W` its last component
+/// - `\?` the last command's status
+/// - `\t` the time as `HH:MM:SS` in UTC and `\D` how long the last command took
+/// - `\$` a `#`, `\n` a line break, `\e` an escape character and `\\` a backslash
+pub fn expand_escapes(template: &str, context: &PromptContext) -> String {
+    let mut prompt = String::new();
+    let mut chars = template.chars();
+    let cwd = context.cwd;
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            prompt.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => prompt.push_str(&identity::user()),
+            Some('h') => prompt.push_str(&identity::hostname()),
+            Some('w') => write!(prompt, "{}", cwd.display()).unwrap(),
+            Some('W') => match cwd.file_name() {
+                Some(name) => prompt.push_str(&name.to_string_lossy()),
+                None => write!(prompt, "{}", cwd.display()).unwrap(),
+            },
+            Some('?') => match context.last_return {
+                Some(code) => write!(prompt, "{code}").unwrap(),
+                None => prompt.push('0'),
+            },
+            Some('t') => prompt.push_str(&format_utc_time(SystemTime::now())),
+            Some('D') => {
+                if let Some(duration) = context.duration {
+                    prompt.push_str(&format_duration(duration));
+                }
+            }
+            Some('$') => prompt.push('#'),
+            Some('n') => prompt.push('\n'),
+            Some('e') => prompt.push('\x1b'),
+            Some('\\') | None => prompt.push('\\'),
+            Some(c) => {
+                prompt.push('\\');
+                prompt.push(c);
+            }
+        }
+    }
+
+    prompt
+}
+
+/// Whether the prompt may use the separator glyphs from powerline and nerd fonts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// Decide based on the terminal, see [`Glyphs::resolve`]
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+impl Glyphs {
+    pub const ALL: [Glyphs; 3] = [Glyphs::Auto, Glyphs::Unicode, Glyphs::Ascii];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Glyphs::Auto => "auto",
+            Glyphs::Unicode => "unicode",
+            Glyphs::Ascii => "ascii",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|glyphs| glyphs.name() == name)
+    }
+
+    /// Turns [`Glyphs::Auto`] into either [`Glyphs::Unicode`] or [`Glyphs::Ascii`],
+    /// SafaOS's console font and terminals without a UTF-8 locale get ASCII
+    pub fn resolve(self) -> Self {
+        match self {
+            Glyphs::Auto if cfg!(target_os = "safaos") || !terminal::detect().unicode => {
+                Glyphs::Ascii
+            }
+            Glyphs::Auto => Glyphs::Unicode,
+            glyphs => glyphs,
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self.resolve() {
+            Glyphs::Ascii => ">",
+            _ => "\u{e0b0}",
+        }
+    }
+
+    fn ellipsis(self) -> &'static str {
+        match self.resolve() {
+            Glyphs::Ascii => "...",
+            _ => "…",
+        }
+    }
+}
+
+/// Returns the terminal title shown while the first line of `command` runs in `cwd`,
+/// or while the shell waits for a line there if `command` is `None`
+pub fn title(command: Option<&str>, cwd: &Path) -> String {
+    let command =
+        command.and_then(|command| command.lines().map(str::trim).find(|line| !line.is_empty()));
+    format!("{} - {}", command.unwrap_or("safa"), cwd.display())
+}
@@ -1,5 +1,7 @@
-use embedded_io::{ErrorType, Read as EmbRead, Write as EmbWrite};
-use std::io::{Read, Stdin, Stdout, Write};
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+};
 
 #[cfg(target_os = "safaos")]
 pub fn enter_raw_mode() -> bool {
@@ -29,41 +31,137 @@ pub fn enter_raw_mode() -> bool {
     true
 }
 
+/// Waits up to `timeout` for a key to be pressed on the terminal and reads it, returns `None` if none was
+///
+/// SafaOS can't wait for input with a timeout yet, so this only waits out `timeout` there
+#[cfg(unix)]
+pub fn poll_key(timeout: std::time::Duration) -> std::io::Result<Option<u8>> {
+    #[repr(C)]
+    struct PollFd {
+        fd: i32,
+        events: i16,
+        revents: i16,
+    }
+
+    /// The same on Linux, macOS and the BSDs
+    const POLLIN: i16 = 1;
+
+    // the C library is always linked on unix
+    unsafe extern "C" {
+        fn poll(fds: *mut PollFd, count: std::ffi::c_uint, timeout: i32) -> i32;
+    }
+
+    let mut stdin = PollFd {
+        fd: 0,
+        events: POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+    match unsafe { poll(&mut stdin, 1, timeout) } {
+        -1 => {
+            let err = std::io::Error::last_os_error();
+            match err.kind() {
+                std::io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(err),
+            }
+        }
+        0 => Ok(None),
+        _ => {
+            let mut key = [0];
+            match std::io::stdin().read(&mut key)? {
+                0 => Ok(None),
+                _ => Ok(Some(key[0])),
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn poll_key(timeout: std::time::Duration) -> std::io::Result<Option<u8>> {
+    std::thread::sleep(timeout);
+    Ok(None)
+}
+
+/// What [`IOWrapper::receive`] read
+pub enum Received {
+    Byte(u8),
+    /// The terminal was resized, see [`crate::system::catch_resize`]
+    Resize,
+}
+
 pub struct IOWrapper {
-    pub stdin: Stdin,
-    pub stdout: Stdout,
+    pub stdin: Box<dyn Read>,
+    pub stdout: Box<dyn Write>,
+    /// Input read from stdin that the line editor hasn't consumed yet
+    pending: VecDeque<u8>,
 }
 
 impl IOWrapper {
     pub fn new() -> Self {
+        Self::with_streams(std::io::stdin(), std::io::stdout())
+    }
+
+    pub fn with_streams(stdin: impl Read + 'static, stdout: impl Write + 'static) -> Self {
         Self {
-            stdin: std::io::stdin(),
-            stdout: std::io::stdout(),
+            stdin: Box::new(stdin),
+            stdout: Box::new(stdout),
+            pending: VecDeque::new(),
         }
     }
-}
 
-impl Default for IOWrapper {
-    fn default() -> Self {
-        Self::new()
+    /// Takes the complete lines that were already received after the line the editor returned,
+    /// these come from pasting several lines at once since nobody types faster than the shell reads
+    pub fn take_pending_lines(&mut self) -> Vec<String> {
+        let Some(end) = self
+            .pending
+            .iter()
+            .rposition(|byte| matches!(byte, b'\r' | b'\n'))
+        else {
+            return Vec::new();
+        };
+
+        let bytes = self.pending.drain(..=end).collect::<Vec<_>>();
+        String::from_utf8_lossy(&bytes)
+            .split(['\r', '\n'])
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
     }
-}
 
-impl ErrorType for IOWrapper {
-    type Error = embedded_io::ErrorKind;
-}
+    /// Reads a single byte of input, returns `None` if the input ended or can't be read
+    ///
+    /// Whatever else was received stays pending, see [`IOWrapper::take_pending_lines`]
+    pub fn read_byte(&mut self) -> Option<u8> {
+        loop {
+            if let Received::Byte(byte) = self.receive()? {
+                return Some(byte);
+            }
+        }
+    }
+
+    /// Reads a single byte of input like [`IOWrapper::read_byte`], unless the terminal is resized first
+    pub fn receive(&mut self) -> Option<Received> {
+        while self.pending.is_empty() {
+            let mut chunk = [0; 256];
+            match self.stdin.read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(len) => self.pending.extend(&chunk[..len]),
+                // a signal arrived while waiting for a key
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                    if crate::system::take_resized() {
+                        return Some(Received::Resize);
+                    }
+                }
+                Err(_) => return None,
+            }
+        }
 
-impl EmbRead for IOWrapper {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        Ok(self.stdin.read(buf).map_err(|e| e.kind())?)
+        self.pending.pop_front().map(Received::Byte)
     }
 }
 
-impl EmbWrite for IOWrapper {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        Ok(self.stdout.write(buf).map_err(|e| e.kind())?)
-    }
-    fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(self.stdout.flush().map_err(|e| e.kind())?)
+impl Default for IOWrapper {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,99 @@
+//! Lets other programs drive a persistent shell session using `safa --serve <socket>`
+//!
+//! Clients send one command line per line, for each one the shell replies with:
+//! - `o <text>` for every line the command wrote to its stdout or stderr
+//! - `s <status>` once the command has finished, with its numeric exit status
+
+use std::io::{self, BufRead, Write};
+
+use crate::{Shell, stdio::Input, stdio::SharedOutput};
+
+/// Frames the output of a command into `o <text>` lines
+#[derive(Clone)]
+struct FramedOutput {
+    inner: SharedOutput,
+    at_line_start: bool,
+}
+
+impl FramedOutput {
+    fn new(inner: SharedOutput) -> Self {
+        Self {
+            inner,
+            at_line_start: true,
+        }
+    }
+}
+
+impl Write for FramedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.at_line_start {
+                self.inner.write_all(b"o ")?;
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    /// Ends the current line if the command didn't, a frame can't be continued after flushing
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.at_line_start {
+            self.inner.write_all(b"\n")?;
+            self.at_line_start = true;
+        }
+        self.inner.flush()
+    }
+}
+
+impl Shell {
+    /// Serves a single client until it stops sending commands or runs `exit`
+    fn serve_connection(
+        &mut self,
+        input: impl BufRead,
+        output: impl Write + Send + 'static,
+    ) -> io::Result<()> {
+        let mut output = SharedOutput::new(output);
+        self.streams.stdin = Input::Null;
+
+        for line in input.lines() {
+            let line = line?;
+            let status = self.execute_line_into(&line, FramedOutput::new(output.clone()))?;
+            writeln!(output, "s {status}")?;
+            output.flush()?;
+            // `exit` ends the client's session rather than the server
+            if self.exiting.take().is_some() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serves commands on the socket at `address`, or on stdin and stdout if `address` is `-`,
+    /// clients are served one after another and share the same session
+    pub fn serve(mut self, address: &str) -> io::Result<()> {
+        if address == "-" {
+            return self.serve_connection(io::stdin().lock(), io::stdout());
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                let listener = std::os::unix::net::UnixListener::bind(address)?;
+                for stream in listener.incoming() {
+                    let stream = stream?;
+                    let input = io::BufReader::new(stream.try_clone()?);
+                    if let Err(err) = self.serve_connection(input, stream) {
+                        eprintln!("safa: --serve: connection closed: {err}");
+                    }
+                }
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "sockets aren't supported on this system, use `-` to serve on stdin and stdout",
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! The protocol the `svc` builtin speaks with the init system's service manager,
+//! kept in this module so only it has to change as that interface evolves
+//!
+//! The manager listens on the socket at `$SAFA_INIT_SOCKET`, or [`DEFAULT_SOCKET`] if it isn't set.
+//! Each request is a single `<action> <service>` line, the manager answers with a single line:
+//! `ok <message>` on success or `err <message>` on failure
+
+use std::io::{self, BufRead, BufReader, Write};
+
+/// Where the service manager listens when `$SAFA_INIT_SOCKET` isn't set
+pub const DEFAULT_SOCKET: &str = "/run/safa-init.sock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Start,
+    Stop,
+    Restart,
+    Status,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [Action::Start, Action::Stop, Action::Restart, Action::Status];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Action::Start => "start",
+            Action::Stop => "stop",
+            Action::Restart => "restart",
+            Action::Status => "status",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// The manager's answer to a request
+#[derive(Debug)]
+pub struct Reply {
+    pub ok: bool,
+    pub message: String,
+}
+
+fn parse_reply(line: &str) -> io::Result<Reply> {
+    let line = line.trim_end();
+    let (status, message) = line.split_once(' ').unwrap_or((line, ""));
+    let ok = match status {
+        "ok" => true,
+        "err" => false,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected reply `{line}` from the service manager"),
+            ));
+        }
+    };
+
+    Ok(Reply {
+        ok,
+        message: message.to_string(),
+    })
+}
+
+/// Exchanges a request and its reply over `stream`
+fn exchange(mut stream: impl io::Read + Write, action: Action, service: &str) -> io::Result<Reply> {
+    writeln!(stream, "{} {service}", action.name())?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    parse_reply(&line)
+}
+
+#[cfg(unix)]
+fn connect(socket: &str) -> io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(socket).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("can't reach the service manager at {socket}: {err}"),
+        )
+    })
+}
+
+#[cfg(not(unix))]
+fn connect(_socket: &str) -> io::Result<io::Empty> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "talking to the service manager isn't supported on this system yet",
+    ))
+}
+
+/// Asks the service manager to apply `action` to `service`
+pub fn request(action: Action, service: &str) -> io::Result<Reply> {
+    let socket = std::env::var("SAFA_INIT_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET.to_string());
+    exchange(connect(&socket)?, action, service)
+}
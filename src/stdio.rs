@@ -0,0 +1,164 @@
+//! The standard streams of the commands executed by the shell, redirections replace them for the duration of a command
+
+use std::{
+    fs::File,
+    io::{self, PipeReader, PipeWriter, Read, Write},
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+
+/// Where a command reads its input from
+#[derive(Debug, Default)]
+pub enum Input {
+    /// The shell's own stdin
+    #[default]
+    Inherit,
+    /// Nothing to read, used when the shell's stdin isn't meant for the commands
+    Null,
+    File(File),
+    /// The reading end of a pipe the previous command in a pipeline writes to
+    Pipe(PipeReader),
+}
+
+/// Where a command writes its output to
+#[derive(Debug)]
+pub enum Output {
+    /// The shell's own stdout
+    Stdout,
+    /// The shell's own stderr
+    Stderr,
+    File(File),
+    /// The writing end of a pipe the next command in a pipeline reads from
+    Pipe(PipeWriter),
+    /// The pipe forwarding the output of a line to an embedder, whose forwarder waits for every copy of it to be
+    /// closed, so jobs started in the background are never given it
+    Forwarded(PipeWriter),
+    /// Closed with `>&-`, writing fails and programs are given nowhere to write to
+    Closed,
+}
+
+impl Input {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Self::Inherit => Self::Inherit,
+            Self::Null => Self::Null,
+            Self::File(file) => Self::File(file.try_clone()?),
+            Self::Pipe(pipe) => Self::Pipe(pipe.try_clone()?),
+        })
+    }
+
+    /// Returns a [`Stdio`] that can be given to a child process
+    pub fn to_stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Inherit => Stdio::inherit(),
+            Self::Null => Stdio::null(),
+            Self::File(file) => file.try_clone()?.into(),
+            Self::Pipe(pipe) => pipe.try_clone()?.into(),
+        })
+    }
+}
+
+impl Output {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Self::Stdout => Self::Stdout,
+            Self::Stderr => Self::Stderr,
+            Self::File(file) => Self::File(file.try_clone()?),
+            Self::Pipe(pipe) => Self::Pipe(pipe.try_clone()?),
+            Self::Forwarded(pipe) => Self::Forwarded(pipe.try_clone()?),
+            Self::Closed => Self::Closed,
+        })
+    }
+
+    /// Returns a [`Stdio`] that can be given to a child process
+    pub fn to_stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Stdout => io::stdout().into(),
+            Self::Stderr => io::stderr().into(),
+            Self::File(file) => file.try_clone()?.into(),
+            Self::Pipe(pipe) | Self::Forwarded(pipe) => pipe.try_clone()?.into(),
+            Self::Closed => Stdio::null(),
+        })
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Inherit => io::stdin().read(buf),
+            Self::Null => Ok(0),
+            Self::File(file) => file.read(buf),
+            Self::Pipe(pipe) => pipe.read(buf),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout => io::stdout().write(buf),
+            Self::Stderr => io::stderr().write(buf),
+            Self::File(file) => file.write(buf),
+            Self::Pipe(pipe) | Self::Forwarded(pipe) => pipe.write(buf),
+            Self::Closed => Err(io::Error::other("bad file descriptor")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().flush(),
+            Self::Stderr => io::stderr().flush(),
+            Self::File(file) => file.flush(),
+            Self::Pipe(pipe) | Self::Forwarded(pipe) => pipe.flush(),
+            Self::Closed => Ok(()),
+        }
+    }
+}
+
+/// The streams the currently executing command is connected to
+#[derive(Debug)]
+pub struct Streams {
+    pub stdin: Input,
+    pub stdout: Output,
+    pub stderr: Output,
+}
+
+impl Default for Streams {
+    fn default() -> Self {
+        Self {
+            stdin: Input::Inherit,
+            stdout: Output::Stdout,
+            stderr: Output::Stderr,
+        }
+    }
+}
+
+impl Streams {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            stdin: self.stdin.try_clone()?,
+            stdout: self.stdout.try_clone()?,
+            stderr: self.stderr.try_clone()?,
+        })
+    }
+}
+
+/// A writer shared between the line editor and the thread forwarding the commands' output to an embedder
+#[derive(Clone)]
+pub struct SharedOutput(Arc<Mutex<dyn Write + Send>>);
+
+impl SharedOutput {
+    pub fn new(output: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(output)))
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
@@ -0,0 +1,62 @@
+//! Suggests the commands the user may have meant when a command isn't found,
+//! unless `$SAFA_SUGGEST` is set to `0`
+
+use crate::{completion, vars::Variables};
+
+/// How many suggestions are shown at most
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The number of single character insertions, deletions, substitutions and transpositions of adjacent characters
+/// needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // distances[i][j] is the distance between the first i characters of `a` and the first j of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// Whether commands are suggested, which can be disabled by setting `$SAFA_SUGGEST` to `0`
+pub fn enabled(vars: &Variables) -> bool {
+    vars.get("SAFA_SUGGEST").is_none_or(|value| value != "0")
+}
+
+/// Returns the builtins and programs in `PATH` with a name close to `name`, closest first
+pub fn similar_commands(name: &str) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let mut candidates = completion::command_names("")
+        .into_iter()
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+    candidates.sort();
+
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
@@ -0,0 +1,220 @@
+//! Introspection of the system the shell runs on, used by the `mounts`, `lsdev` and `ps` builtins
+//!
+//! `ps` isn't supported on SafaOS yet, safa-api has no call listing the processes
+
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// A running process as listed by `ps`
+#[derive(Debug)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    pub state: String,
+    /// The resident memory in KiB, if known
+    pub memory: Option<u64>,
+}
+
+/// The drive prefixes SafaOS mounts filesystems at
+#[cfg(target_os = "safaos")]
+const KNOWN_DRIVES: &[&str] = &["sys:", "ram:", "dev:", "proc:"];
+
+/// Returns the drives that are currently mounted
+#[cfg(target_os = "safaos")]
+pub fn mounts() -> io::Result<Vec<String>> {
+    Ok(KNOWN_DRIVES
+        .iter()
+        .filter(|drive| std::fs::read_dir(format!("{drive}/")).is_ok())
+        .map(|drive| drive.to_string())
+        .collect())
+}
+
+/// Where the kernel exposes the devices
+#[cfg(target_os = "safaos")]
+const DEVICES_DIR: &str = "dev:/";
+#[cfg(not(target_os = "safaos"))]
+const DEVICES_DIR: &str = "/dev";
+
+/// Returns the names of the devices the kernel exposes under [`DEVICES_DIR`]
+pub fn devices() -> io::Result<Vec<String>> {
+    let mut devices = std::fs::read_dir(DEVICES_DIR)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<io::Result<Vec<_>>>()?;
+    devices.sort();
+    Ok(devices)
+}
+
+/// Returns the running processes ordered by pid
+///
+/// safa-api has no call listing the processes yet, so this always fails on SafaOS
+#[cfg(target_os = "safaos")]
+pub fn processes() -> io::Result<Vec<Process>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "listing processes isn't supported on SafaOS yet",
+    ))
+}
+
+/// Returns the running processes ordered by pid, read from `/proc`
+#[cfg(not(target_os = "safaos"))]
+pub fn processes() -> io::Result<Vec<Process>> {
+    let mut processes = Vec::new();
+
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        // the process may have exited since the directory was listed
+        let Ok(status) = std::fs::read_to_string(entry.path().join("status")) else {
+            continue;
+        };
+
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(str::trim)
+        };
+
+        processes.push(Process {
+            pid,
+            name: field("Name:").unwrap_or_default().to_string(),
+            state: field("State:").unwrap_or_default().to_string(),
+            memory: field("VmRSS:")
+                .and_then(|rss| rss.split_whitespace().next())
+                .and_then(|kib| kib.parse().ok()),
+        });
+    }
+
+    processes.sort_by_key(|process| process.pid);
+    Ok(processes)
+}
+
+/// Returns the mount points listed in `/proc/mounts`
+#[cfg(not(target_os = "safaos"))]
+pub fn mounts() -> io::Result<Vec<String>> {
+    let mounts = std::fs::read_to_string("/proc/mounts")?;
+    Ok(mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        // spaces and the like are escaped in octal, such as `\040`
+        .map(|mount| mount.replace("\\040", " ").replace("\\011", "\t"))
+        .collect())
+}
+
+#[cfg(unix)]
+mod signals {
+    /// The same on Linux, macOS and the BSDs
+    pub const SIGHUP: i32 = 1;
+    pub const SIGWINCH: i32 = 28;
+
+    // the C library is always linked on unix
+    unsafe extern "C" {
+        pub fn kill(pid: i32, signal: i32) -> i32;
+        pub fn setsid() -> i32;
+        pub fn signal(signal: i32, handler: usize) -> usize;
+        pub fn siginterrupt(signal: i32, interrupt: i32) -> i32;
+    }
+}
+
+/// Set once the terminal the shell was started from is closed
+static HUNG_UP: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the hang up sent once the shell's terminal is closed from ending the shell on unix hosts, so it can exit
+/// by itself and hang up its jobs, which are in process groups of their own and aren't sent it, see [`hung_up`]
+pub fn catch_hang_up() {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            extern "C" fn on_hang_up(_: i32) {
+                HUNG_UP.store(true, Ordering::Relaxed);
+            }
+            unsafe { signals::signal(signals::SIGHUP, on_hang_up as extern "C" fn(i32) as usize) };
+        }
+    }
+}
+
+/// Whether the shell's terminal was closed since [`catch_hang_up`]
+pub fn hung_up() -> bool {
+    HUNG_UP.load(Ordering::Relaxed)
+}
+
+/// Set when the terminal is resized, until [`take_resized`] is called
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+/// Makes the terminal being resized interrupt the reads waiting for a key on unix hosts, so the line being edited can
+/// be drawn again for the new size right away, see [`take_resized`]
+///
+/// SafaOS has no signals yet, its terminal's size can't be queried either
+pub fn catch_resize() {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            extern "C" fn on_resize(_: i32) {
+                RESIZED.store(true, Ordering::Relaxed);
+            }
+            unsafe {
+                signals::signal(signals::SIGWINCH, on_resize as extern "C" fn(i32) as usize);
+                signals::siginterrupt(signals::SIGWINCH, 1);
+            }
+        }
+    }
+}
+
+/// Whether the terminal was resized since this was last called
+pub fn take_resized() -> bool {
+    RESIZED.swap(false, Ordering::Relaxed)
+}
+
+/// Puts the program `command` runs in a process group of its own on unix hosts, so the signals the terminal sends to
+/// the shell's group such as the hang up once it is closed don't reach it, see [`hang_up`]
+pub fn own_process_group(command: &mut std::process::Command) {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        } else {
+            _ = command;
+        }
+    }
+}
+
+/// Tells the process group `pid` leads the terminal it was started from is gone, which ends its processes
+/// unless they handle that
+pub fn hang_up(pid: u32) -> io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            // a negative ID refers to the process group
+            if unsafe { signals::kill(-(pid as i32), signals::SIGHUP) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        } else {
+            _ = pid;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "SafaOS doesn't have signals yet",
+            ))
+        }
+    }
+}
+
+/// Makes the program `command` runs independent of the shell's terminal, in a session of its own on unix hosts,
+/// SafaOS has no sessions so its programs are only detached by not being given the terminal as their streams
+pub fn detach(command: &mut std::process::Command) {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::process::CommandExt;
+
+            let new_session = || match unsafe { signals::setsid() } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            };
+            // runs in the child before it executes the program
+            unsafe { command.pre_exec(new_session) };
+        } else {
+            _ = command;
+        }
+    }
+}
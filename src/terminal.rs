@@ -0,0 +1,93 @@
+//! What the terminal the shell runs in supports, guessed from `$TERM` and the locale
+//! since terminals can't be asked without waiting on a reply that some of them never send,
+//! and how big it is
+
+/// The `$TERM` of the terminals known to clear their scrollback on `\x1b[3J`, and of their variants such as `xterm-256color`
+const SCROLLBACK_CLEARING: &[&str] = &[
+    "xterm",
+    "screen",
+    "tmux",
+    "rxvt",
+    "linux",
+    "alacritty",
+    "kitty",
+    "foot",
+    "wezterm",
+    "konsole",
+    "vte",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// Whether escape sequences such as colors and cursor movements are understood, `dumb` terminals print them as is
+    pub escapes: bool,
+    /// Whether `\x1b[3J` clears the scrollback
+    pub clear_scrollback: bool,
+    /// Whether the font has glyphs beyond ASCII, such as the powerline arrows
+    pub unicode: bool,
+    /// Whether the window or tab has a title `\x1b]0;title\x07` changes
+    pub title: bool,
+}
+
+/// Returns whether `term` is `name` or one of its variants
+fn is_term(term: &str, name: &str) -> bool {
+    term.strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+}
+
+fn locale_is_utf8() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// Guesses the capabilities of the terminal from the environment
+pub fn detect() -> Capabilities {
+    let term = std::env::var("TERM").unwrap_or_default();
+
+    // SafaOS's terminal doesn't set `$TERM` and is given `safaos` at startup, it understands the common sequences
+    // but keeps its scrollback and its font only covers ASCII
+    if cfg!(target_os = "safaos") && (term.is_empty() || term == "safaos") {
+        return Capabilities {
+            escapes: true,
+            clear_scrollback: false,
+            unicode: false,
+            title: true,
+        };
+    }
+
+    Capabilities {
+        escapes: term != "dumb",
+        clear_scrollback: SCROLLBACK_CLEARING.iter().any(|name| is_term(&term, name)),
+        // the linux virtual console's fonts don't include the powerline glyphs
+        unicode: term != "dumb" && term != "linux" && locale_is_utf8(),
+        // nor do they have a title
+        title: term != "dumb" && term != "linux",
+    }
+}
+
+/// Returns the escape sequence setting the terminal's title to `title`,
+/// control characters such as line breaks and escapes are replaced by spaces so they can't end the sequence early
+pub fn title_sequence(title: &str) -> String {
+    let title = title
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>();
+    format!("\x1b]0;{title}\x07")
+}
+
+/// Returns the number of columns and lines of the terminal, `None` if it can't be queried as is the case on SafaOS
+pub fn size() -> Option<(usize, usize)> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "safaos")] {
+            None
+        } else {
+            let (columns, lines) = termion::terminal_size().ok()?;
+            Some((columns as usize, lines as usize))
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Formatting of timestamps without a timezone database, times are shown in UTC
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Converts days since the unix epoch into a `(year, month, day)` date in the proleptic gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Splits `time` into days since the unix epoch and seconds since the start of that day
+fn days_and_seconds(time: SystemTime) -> (i64, i64) {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+
+    (seconds.div_euclid(86400), seconds.rem_euclid(86400))
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM:SS UTC`
+pub fn format_utc(time: SystemTime) -> String {
+    let (days, _) = days_and_seconds(time);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02} {} UTC",
+        format_utc_time(time)
+    )
+}
+
+/// Formats the time of day of `time` as `HH:MM:SS`
+pub fn format_utc_time(time: SystemTime) -> String {
+    let (_, seconds) = days_and_seconds(time);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Formats `duration` for humans, such as `250ms`, `4.2s` or `3m12s`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}h{}m", seconds / 3600, seconds / 60 % 60)
+    } else if seconds >= 60 {
+        format!("{}m{}s", seconds / 60, seconds % 60)
+    } else if seconds >= 1 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
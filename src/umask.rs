@@ -0,0 +1,106 @@
+//! The file creation mask, which removes permissions from the files created by redirections and by spawned programs,
+//! configured using the `umask` builtin
+
+#[cfg(unix)]
+mod sys {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    type Mode = u32;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    type Mode = u16;
+
+    // the C library is always linked on unix
+    unsafe extern "C" {
+        fn umask(mask: Mode) -> Mode;
+    }
+
+    pub fn get() -> u32 {
+        // the mask can only be read by replacing it
+        let mask = unsafe { umask(0) };
+        unsafe { umask(mask) };
+        mask as u32
+    }
+
+    pub fn set(mask: u32) {
+        unsafe { umask(mask as Mode) };
+    }
+}
+
+/// Returns the mask the shell creates files with, `None` on systems without one such as SafaOS which has no file permissions yet
+pub fn get() -> Option<u32> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            Some(sys::get())
+        } else {
+            None
+        }
+    }
+}
+
+/// Sets the mask, which programs spawned afterwards inherit
+pub fn set(mask: u32) {
+    #[cfg(unix)]
+    sys::set(mask);
+    _ = mask;
+}
+
+const CLASSES: [(char, u32); 3] = [('u', 6), ('g', 3), ('o', 0)];
+const PERMISSIONS: [(char, u32); 3] = [('r', 4), ('w', 2), ('x', 1)];
+
+/// Parses an octal mask such as `022` or a symbolic one such as `u=rwx,g=rx,o=` changing `current`,
+/// the symbolic form lists the permissions that are kept rather than removed
+pub fn parse(value: &str, current: u32) -> Option<u32> {
+    if value.chars().all(|c| c.is_digit(8)) {
+        return u32::from_str_radix(value, 8)
+            .ok()
+            .filter(|mask| *mask <= 0o777);
+    }
+
+    // the permissions that are kept
+    let mut allowed = !current & 0o777;
+    for clause in value.split(',') {
+        let op_index = clause.find(['=', '+', '-'])?;
+        let (who, rest) = clause.split_at(op_index);
+        let mut chars = rest.chars();
+        let op = chars.next()?;
+
+        let mut permissions = 0;
+        for c in chars {
+            permissions |= PERMISSIONS.iter().find(|(name, _)| *name == c)?.1;
+        }
+
+        let who = if who.is_empty() { "a" } else { who };
+        for class in who.chars() {
+            let shifts = match class {
+                'a' => CLASSES.iter().map(|(_, shift)| *shift).collect::<Vec<_>>(),
+                class => vec![CLASSES.iter().find(|(name, _)| *name == class)?.1],
+            };
+
+            for shift in shifts {
+                match op {
+                    '=' => allowed = (allowed & !(0o7 << shift)) | (permissions << shift),
+                    '+' => allowed |= permissions << shift,
+                    _ => allowed &= !(permissions << shift),
+                }
+            }
+        }
+    }
+
+    Some(!allowed & 0o777)
+}
+
+/// Formats `mask` as the permissions it keeps, such as `u=rwx,g=rx,o=rx` for `022`
+pub fn symbolic(mask: u32) -> String {
+    CLASSES
+        .iter()
+        .map(|(class, shift)| {
+            let allowed = (!mask >> shift) & 0o7;
+            let permissions = PERMISSIONS
+                .iter()
+                .filter(|(_, bit)| allowed & bit != 0)
+                .map(|(name, _)| *name)
+                .collect::<String>();
+            format!("{class}={permissions}")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,200 @@
+//! Shell variables, exported variables live in the process environment so children inherit them
+//! while the rest are only visible to the shell itself
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    local: HashMap<String, String>,
+    /// The indexed arrays set with `name=(a b c)`, which can't be exported
+    arrays: HashMap<String, Vec<String>>,
+    /// The variables declared using `local` by each function being executed, along with the values they shadow
+    scopes: Vec<Vec<(String, Option<String>)>>,
+    /// Whether the process environment is ignored, see [`Variables::isolated`]
+    isolated: bool,
+    /// The variables marked using `readonly`, which commands can no longer change
+    readonly: BTreeSet<String>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Variables that neither read nor change the process environment, so expanding words with them only depends on `values`
+    pub fn isolated<'a>(values: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            local: values
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            arrays: HashMap::new(),
+            scopes: Vec::new(),
+            isolated: true,
+            readonly: BTreeSet::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        // `$arr` is the first element of the array `arr`
+        let array = || self.arrays.get(name).and_then(|array| array.first());
+        match self.local.get(name).or_else(array) {
+            Some(value) => Some(Cow::Borrowed(value)),
+            None if self.isolated => None,
+            None => std::env::var(name).ok().map(Cow::Owned),
+        }
+    }
+
+    /// Returns the names of all the variables, exported or not
+    pub fn names(&self) -> impl Iterator<Item = String> + '_ {
+        let exported = std::env::vars_os()
+            .filter(|_| !self.isolated)
+            .filter_map(|(name, _)| name.into_string().ok());
+        self.local
+            .keys()
+            .chain(self.arrays.keys())
+            .cloned()
+            .chain(exported)
+    }
+
+    /// Returns the elements of the array `name`, a variable that isn't an array is an array of its value
+    pub fn array(&self, name: &str) -> Vec<String> {
+        match self.arrays.get(name) {
+            Some(array) => array.clone(),
+            None => self.get(name).map(Cow::into_owned).into_iter().collect(),
+        }
+    }
+
+    /// Returns the arrays along with their elements
+    pub fn arrays(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.arrays
+            .iter()
+            .map(|(name, array)| (name.as_str(), array.as_slice()))
+    }
+
+    /// Replaces the variable `name` by an array of `elements`
+    pub fn set_array(&mut self, name: &str, elements: Vec<String>) {
+        self.unset(name);
+        self.arrays.insert(name.to_string(), elements);
+    }
+
+    /// Returns the variables that aren't exported along with their values
+    pub fn unexported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.local
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Marks `name` as read-only, the shell still changes it but assignments and `unset` fail
+    pub fn set_readonly(&mut self, name: &str) {
+        self.readonly.insert(name.to_string());
+    }
+
+    pub fn is_readonly(&self, name: &str) -> bool {
+        self.readonly.contains(name)
+    }
+
+    /// Returns the names of the read-only variables in order
+    pub fn readonly_names(&self) -> impl Iterator<Item = &str> {
+        self.readonly.iter().map(String::as_str)
+    }
+
+    /// Removes the variable `name`, from the environment too if it is exported
+    pub fn unset(&mut self, name: &str) {
+        if self.arrays.remove(name).is_some() {
+            return;
+        }
+        if self.local.remove(name).is_none() && std::env::var_os(name).is_some() {
+            unsafe { std::env::remove_var(name) };
+        }
+    }
+
+    /// Exports the variable `name` so spawned programs inherit it, setting it to `value` if given
+    pub fn export(&mut self, name: &str, value: Option<String>) {
+        let value = value
+            .or_else(|| self.local.remove(name))
+            .or_else(|| std::env::var(name).ok())
+            .unwrap_or_default();
+        self.local.remove(name);
+        unsafe { std::env::set_var(name, value) };
+    }
+
+    /// Returns the positional parameters `$1`, `$2`...
+    pub fn positional(&self) -> Vec<String> {
+        let count = self
+            .get("#")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+        (1..=count)
+            .map(|i: usize| self.get(&i.to_string()).unwrap_or_default().into_owned())
+            .collect()
+    }
+
+    /// Starts the scope of a function's `local` variables
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Ends the innermost scope, restoring the variables its `local` declarations shadowed
+    pub fn pop_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+
+        for (name, previous) in scope.into_iter().rev() {
+            match previous {
+                Some(value) => self.set(&name, value),
+                None => self.unset(&name),
+            }
+        }
+    }
+
+    /// Makes `name` local to the innermost scope then sets it to `value`,
+    /// returns `false` if there is no scope because no function is being executed
+    pub fn declare_local(&mut self, name: &str, value: String) -> bool {
+        let previous = self.get(name).map(Cow::into_owned);
+        let Some(scope) = self.scopes.last_mut() else {
+            return false;
+        };
+
+        if !scope.iter().any(|(local, _)| local == name) {
+            scope.push((name.to_string(), previous));
+        }
+        self.set(name, value);
+        true
+    }
+
+    /// Sets `$0` to `name` and the positional parameters `$1`, `$2`... to `args`,
+    /// along with `$#`, `$*` and `$@`, which is kept as an array so `"$@"` keeps them apart
+    pub fn set_positional(&mut self, name: &str, args: &[String]) {
+        self.local
+            .retain(|var, _| !var.bytes().all(|b| b.is_ascii_digit()));
+        self.local.insert("0".to_string(), name.to_string());
+        for (i, arg) in args.iter().enumerate() {
+            self.local.insert((i + 1).to_string(), arg.clone());
+        }
+
+        self.local.insert("#".to_string(), args.len().to_string());
+        self.local.insert("*".to_string(), args.join(" "));
+        self.arrays.insert("@".to_string(), args.to_vec());
+    }
+
+    /// Sets the variable `name` to `value`, updating the environment if it is exported
+    /// or the first element if it is an array
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        if let Some(array) = self.arrays.get_mut(name) {
+            match array.first_mut() {
+                Some(first) => *first = value,
+                None => array.push(value),
+            }
+        } else if !self.isolated && std::env::var_os(name).is_some() {
+            unsafe { std::env::set_var(name, value) };
+        } else {
+            self.local.insert(name.to_string(), value);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! What `safa --version` and the `version` builtin report, so bug reports identify the exact build of the shell
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{json::Value, time::format_utc};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The commit the shell was built from, recorded by the build script when building from a git checkout
+const COMMIT: Option<&str> = option_env!("SAFA_GIT_COMMIT");
+
+/// When the shell was built in seconds since the unix epoch, recorded by the build script
+const BUILD_TIME: Option<&str> = option_env!("SAFA_BUILD_TIME");
+
+/// Returns the Cargo features the shell was built with
+fn features() -> Vec<&'static str> {
+    [
+        ("fs-builtins", cfg!(feature = "fs-builtins")),
+        ("net-utils", cfg!(feature = "net-utils")),
+        ("cwd-events", cfg!(feature = "cwd-events")),
+        ("env-bootstrap", cfg!(feature = "env-bootstrap")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+/// Returns the system the shell was built for, `safaos` or the host's such as `linux`
+fn target() -> &'static str {
+    std::env::consts::OS
+}
+
+fn build_date() -> Option<String> {
+    let seconds = BUILD_TIME?.parse().ok()?;
+    Some(format_utc(UNIX_EPOCH + Duration::from_secs(seconds)))
+}
+
+/// Returns the build information as lines for humans or as a JSON object if `json` is set
+pub fn report(json: bool) -> String {
+    let features = features();
+    if json {
+        let value = Value::from_iter([
+            ("version", Value::from(VERSION)),
+            ("commit", COMMIT.into()),
+            ("build_date", build_date().into()),
+            ("target", target().into()),
+            ("safaos", cfg!(target_os = "safaos").into()),
+            (
+                "features",
+                Value::Array(features.into_iter().map(Value::from).collect()),
+            ),
+        ]);
+        return value.to_string();
+    }
+
+    let features = match features.is_empty() {
+        true => "none".to_string(),
+        false => features.join(", "),
+    };
+    format!(
+        "safa {VERSION}\ncommit: {}\nbuilt: {}\ntarget: {}\nfeatures: {features}",
+        COMMIT.unwrap_or("unknown"),
+        build_date().unwrap_or_else(|| "unknown".to_string()),
+        target(),
+    )
+}
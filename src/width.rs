@@ -0,0 +1,263 @@
+//! How many columns text takes in a terminal, and where its grapheme clusters, the characters as the user sees them, start
+//!
+//! The tables cover the common scripts and emoji rather than all of Unicode: East Asian wide and fullwidth characters
+//! and emoji take two columns, combining marks, joiners and variation selectors take none and join the character before them
+
+/// The ranges of characters taking two columns
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18aff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f251),
+    (0x1f300, 0x1f320),
+    (0x1f32d, 0x1f335),
+    (0x1f337, 0x1f37c),
+    (0x1f37e, 0x1f393),
+    (0x1f3a0, 0x1f3ca),
+    (0x1f3cf, 0x1f3d3),
+    (0x1f3e0, 0x1f3f0),
+    (0x1f3f4, 0x1f3f4),
+    (0x1f3f8, 0x1f43e),
+    (0x1f440, 0x1f440),
+    (0x1f442, 0x1f4fc),
+    (0x1f4ff, 0x1f53d),
+    (0x1f54b, 0x1f54e),
+    (0x1f550, 0x1f567),
+    (0x1f57a, 0x1f57a),
+    (0x1f595, 0x1f596),
+    (0x1f5a4, 0x1f5a4),
+    (0x1f5fb, 0x1f64f),
+    (0x1f680, 0x1f6c5),
+    (0x1f6cc, 0x1f6cc),
+    (0x1f6d0, 0x1f6d2),
+    (0x1f6d5, 0x1f6d7),
+    (0x1f6dc, 0x1f6df),
+    (0x1f6eb, 0x1f6ec),
+    (0x1f6f4, 0x1f6fc),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f90c, 0x1f93a),
+    (0x1f93c, 0x1f945),
+    (0x1f947, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// The ranges of characters taking no column that join the character before them
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x05bf, 0x05bf),
+    (0x05c1, 0x05c2),
+    (0x05c4, 0x05c5),
+    (0x05c7, 0x05c7),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x06e7, 0x06e8),
+    (0x06ea, 0x06ed),
+    (0x0900, 0x0902),
+    (0x093a, 0x093a),
+    (0x093c, 0x093c),
+    (0x0941, 0x0948),
+    (0x094d, 0x094d),
+    (0x0951, 0x0957),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1160, 0x11ff),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x20d0, 0x20ff),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0x1f3fb, 0x1f3ff),
+    (0xe0020, 0xe007f),
+    (0xe0100, 0xe01ef),
+];
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+/// Asks for the emoji presentation of the character before it, which takes two columns
+const EMOJI_PRESENTATION: char = '\u{fe0f}';
+
+fn in_table(c: char, table: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// Returns how many columns `c` takes on its own, control characters take none
+pub fn char_width(c: char) -> usize {
+    if c.is_control() || in_table(c, ZERO_WIDTH) {
+        0
+    } else if in_table(c, WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Returns the length of the grapheme cluster at the start of `text`
+fn cluster_len(text: &str) -> usize {
+    let mut chars = text.char_indices().peekable();
+    let Some((_, first)) = chars.next() else {
+        return 0;
+    };
+
+    // a pair of regional indicators is a flag
+    if is_regional_indicator(first) {
+        return match chars.next() {
+            Some((i, c)) if is_regional_indicator(c) => i + c.len_utf8(),
+            _ => first.len_utf8(),
+        };
+    }
+
+    let mut end = first.len_utf8();
+    let mut joined = false;
+    for (i, c) in chars {
+        if !joined && !in_table(c, ZERO_WIDTH) {
+            break;
+        }
+        joined = c == ZERO_WIDTH_JOINER;
+        end = i + c.len_utf8();
+    }
+    end
+}
+
+/// Returns the grapheme clusters of `text` along with their offsets
+pub fn clusters(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let len = cluster_len(&text[offset..]);
+        let cluster = (offset, &text[offset..offset + len]);
+        offset += len;
+        (len > 0).then_some(cluster)
+    })
+}
+
+/// Returns how many columns the grapheme cluster `cluster` takes
+fn cluster_width(cluster: &str) -> usize {
+    let mut chars = cluster.chars();
+    let first = chars.next().map_or(0, char_width);
+    match chars.next() {
+        Some(c) if is_regional_indicator(c) => 2,
+        _ if cluster.contains(EMOJI_PRESENTATION) => first.max(2),
+        _ => first,
+    }
+}
+
+/// Returns how many columns `text` takes
+pub fn width(text: &str) -> usize {
+    clusters(text)
+        .map(|(_, cluster)| cluster_width(cluster))
+        .sum()
+}
+
+/// Returns how many columns `text` takes once shown by the terminal, not counting its escape sequences such as colors
+pub fn visible_width(text: &str) -> usize {
+    let mut visible = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            visible.push(c);
+            continue;
+        }
+        match chars.next() {
+            // control sequences end with a byte in `@..=~`
+            Some('[') => _ = chars.find(|c| ('@'..='~').contains(c)),
+            // operating system commands such as the window title end with a bell or `ESC \`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    width(&visible)
+}
+
+/// Returns the offset of the grapheme cluster that ends at `offset` in `text`
+pub fn previous_boundary(text: &str, offset: usize) -> Option<usize> {
+    clusters(text)
+        .map(|(start, _)| start)
+        .take_while(|&start| start < offset)
+        .last()
+}
+
+/// Returns the offset of the end of the grapheme cluster that starts at `offset` in `text`
+pub fn next_boundary(text: &str, offset: usize) -> Option<usize> {
+    let len = cluster_len(&text[offset..]);
+    (len > 0).then_some(offset + len)
+}
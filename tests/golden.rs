@@ -0,0 +1,143 @@
+//! Runs every `tests/golden/<group>/<case>.sh` through the shell binary's stdin and compares what it printed and the
+//! status it exited with against `<case>.stdout`, `<case>.stderr` and `<case>.status`, a missing file standing for
+//! no output or a status of 0
+//!
+//! Each case runs in an empty directory which is also `$HOME`, with an empty `PATH` so only builtins are found, and
+//! the directory's path is replaced with `<dir>` in the output. Commands aren't suggested for unknown ones since the
+//! suggestions depend on the builtins the enabled features add
+//!
+//! Setting `SAFA_BLESS=1` writes the output of every case as its expected output instead of comparing them
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// What a case printed and exited with
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+impl Outcome {
+    fn run(case: &Path, dir: &Path) -> Outcome {
+        let script = fs::read(case).expect("failed to read the case");
+        let bin = dir.join("bin");
+        fs::create_dir_all(&bin).expect("failed to create the case's directory");
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_safa"))
+            .current_dir(dir)
+            .env_clear()
+            .env("HOME", dir)
+            .env("PATH", &bin)
+            .env("TERM", "dumb")
+            .env("NO_COLOR", "1")
+            .env("SAFA_SUGGEST", "0")
+            .env("SAFA_SHELL_CONFIG", dir.join("config"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn the shell");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(&script)
+            .expect("failed to write the case to the shell");
+        let output = child
+            .wait_with_output()
+            .expect("failed to wait for the shell");
+
+        let dir = dir.to_string_lossy();
+        let normalize = |bytes: &[u8]| String::from_utf8_lossy(bytes).replace(&*dir, "<dir>");
+        Outcome {
+            stdout: normalize(&output.stdout),
+            stderr: normalize(&output.stderr),
+            status: output.status.code().unwrap_or(-1),
+        }
+    }
+
+    fn expected(case: &Path) -> Outcome {
+        let read =
+            |extension| fs::read_to_string(case.with_extension(extension)).unwrap_or_default();
+        Outcome {
+            stdout: read("stdout"),
+            stderr: read("stderr"),
+            status: read("status").trim().parse().unwrap_or(0),
+        }
+    }
+
+    /// Writes the outcome as the case's expected one, removing the files which are no longer needed
+    fn bless(&self, case: &Path) {
+        let status = if self.status == 0 {
+            String::new()
+        } else {
+            format!("{}\n", self.status)
+        };
+        for (extension, contents) in [
+            ("stdout", &self.stdout),
+            ("stderr", &self.stderr),
+            ("status", &status),
+        ] {
+            let path = case.with_extension(extension);
+            if contents.is_empty() {
+                _ = fs::remove_file(path);
+            } else {
+                fs::write(path, contents).expect("failed to write the expected output");
+            }
+        }
+    }
+}
+
+/// Returns the cases sorted by group then name
+fn cases() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut cases = Vec::new();
+    for group in fs::read_dir(root).expect("failed to read tests/golden") {
+        for case in fs::read_dir(group.unwrap().path()).unwrap() {
+            let case = case.unwrap().path();
+            if case.extension().is_some_and(|extension| extension == "sh") {
+                cases.push(case);
+            }
+        }
+    }
+    cases.sort();
+    cases
+}
+
+#[test]
+fn golden() {
+    let bless = std::env::var("SAFA_BLESS").is_ok_and(|bless| bless == "1");
+    let scratch = std::env::temp_dir().join(format!("safa-golden-{}", std::process::id()));
+
+    let mut failures = String::new();
+    for (i, case) in cases().iter().enumerate() {
+        let dir = scratch.join(i.to_string());
+        let outcome = Outcome::run(case, &dir);
+        if bless {
+            outcome.bless(case);
+            continue;
+        }
+
+        let expected = Outcome::expected(case);
+        if outcome != expected {
+            _ = writeln!(
+                failures,
+                "{}:\n  expected: {expected:?}\n  got:      {outcome:?}",
+                case.display()
+            );
+        }
+    }
+    _ = fs::remove_dir_all(&scratch);
+
+    assert!(
+        failures.is_empty(),
+        "cases differ from their golden files, rerun with SAFA_BLESS=1 if intended:\n{failures}"
+    );
+}
@@ -0,0 +1,2 @@
+cd /nonexistent-directory
+printf 'status %s\n' "$?"
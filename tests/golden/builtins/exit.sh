@@ -0,0 +1,4 @@
+printf 'printf "sourced\\n"\nexit 4\nprintf "not reached\\n"\n' > script
+f() { source ./script; printf 'not reached\n'; }
+f
+printf 'not reached\n'
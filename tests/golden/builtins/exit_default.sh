@@ -0,0 +1,2 @@
+cd missing
+exit
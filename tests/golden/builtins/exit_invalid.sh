@@ -0,0 +1,2 @@
+exit abc
+printf 'not reached\n'
@@ -0,0 +1,3 @@
+greet() { printf 'hello %s, %s argument(s)\n' "$1" "$#"; return 4; }
+greet world extra
+printf 'status %s\n' "$?"
@@ -0,0 +1,9 @@
+set -o
+set -o autorehash
+set -e
+set +o
+set +o errexit
+set -o
+set -o nosuchoption
+set -o menu-select
+set -o
@@ -0,0 +1,5 @@
+readonly fixed=1
+fixed=2
+printf '%s\n' "$fixed"
+unset fixed
+printf '%s\n' "$fixed"
@@ -0,0 +1,5 @@
+nosuch || printf 'tested failures are fine\n'
+set -e
+printf 'before\n'
+nosuch
+printf 'not reached\n'
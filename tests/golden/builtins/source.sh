@@ -0,0 +1,4 @@
+printf 'printf "sourced %%s\\n" "$1"\nreturn 3\nprintf "not reached\\n"\n' > script
+source ./script arg
+printf 'status %s\n' "$?"
+. ./script dot
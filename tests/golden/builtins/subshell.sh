@@ -0,0 +1,7 @@
+(exit 3); printf 'status %s\n' "$?"
+(f() { printf 'defined\n'; })
+f
+(set -e; cd missing; printf 'not reached\n'); printf 'status %s\n' "$?"
+cd missing
+printf 'errexit is off\n'
+(x=inner; printf '%s\n' "$x"); printf '[%s]\n' "$x"
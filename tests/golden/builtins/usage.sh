@@ -0,0 +1,2 @@
+set -x
+return
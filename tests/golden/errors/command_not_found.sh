@@ -0,0 +1,2 @@
+nosuchcommand argument
+printf 'status %s\n' "$?"
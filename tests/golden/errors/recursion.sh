@@ -0,0 +1,6 @@
+forever() { forever; }
+forever
+printf 'status %s\n' "$?"
+printf '. ./self\n' > self
+. ./self
+printf 'status %s\n' "$?"
@@ -0,0 +1,2 @@
+printf 'reached\n'
+printf '%s\n' "unterminated
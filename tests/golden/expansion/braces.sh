@@ -0,0 +1,2 @@
+printf '%s\n' {a,b}{1,2}
+printf '%s\n' 'no{a,b}' "{a,b}"
@@ -0,0 +1,9 @@
+words="one two  three"
+printf '<%s>' $words
+printf '\n'
+printf '<%s>' "$words"
+printf '\n'
+IFS=:
+path=a:b::c
+printf '<%s>' $path
+printf '\n'
@@ -0,0 +1,6 @@
+printf 'a\n' | cd missing | printf 'b\n'
+printf '%s\n' "${PIPESTATUS[0]} ${PIPESTATUS[1]} ${PIPESTATUS[2]}"
+printf 'a\n' | cd missing | printf 'b\n'
+printf '%s\n' "${#PIPESTATUS[@]}: ${PIPESTATUS[@]}"
+cd missing | printf 'b\n'
+printf '%s\n' $PIPESTATUS
@@ -0,0 +1,8 @@
+f() { printf '[%s]' "$@"; printf '\n'; printf '[%s]' $@; printf '\n'; printf '[%s]\n' "$*"; }
+f 'p q' r
+count() { printf '%s\n' "$#"; }
+count "$@"
+g() { count "$@"; }
+g 'a b' '' c
+IFS=,
+f 'p q' r
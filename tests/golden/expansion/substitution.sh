@@ -0,0 +1,6 @@
+printf '%s\n' $(printf inner)
+printf '%s\n' "$(printf '%s' "$(printf nested)")"
+printf '<%s>' $(printf 'split words')
+printf '\n'
+v="result: $(printf 'x'; exit 1)"; printf '%s %s\n' "$v" "$?"
+printf '%s\n' "outer $(printf '%s' "inner $(exit 3)")"
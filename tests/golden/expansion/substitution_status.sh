@@ -0,0 +1,6 @@
+x=$(exit 4); printf 'status %s\n' "$?"
+printf '[%s]\n' $(printf 'hi\n'; exit 2); printf 'status %s\n' "$?"
+y=$(printf 'out\n'; exit 5); printf '%s %s\n' "$y" "$?"
+set -e
+z=$(exit 6)
+printf 'not reached\n'
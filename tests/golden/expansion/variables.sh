@@ -0,0 +1,4 @@
+name=world
+printf 'hello %s\n' "$name" "${name}s"
+printf '[%s]\n' "$unset"
+printf '%s\n' "$HOME"
@@ -0,0 +1,4 @@
+nosuch || printf 'or\n'
+printf 'a' && printf 'nd\n'
+nosuch && printf 'not reached\n'
+printf 'status %s\n' "$?"
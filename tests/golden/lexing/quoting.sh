@@ -0,0 +1,3 @@
+printf '%s|\n' 'single $HOME' "double" \$escaped a\ b "a"'b'c ''
+printf '%s|\n' "tab	inside" 'new
+line'
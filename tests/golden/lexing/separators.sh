@@ -0,0 +1,4 @@
+printf 'a\n';printf 'b\n'
+printf 'c\n' ; printf 'd\n'   # a comment
+   printf 'e\n'	# blanks before a command
+printf 'not # a comment\n'